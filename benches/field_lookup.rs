@@ -0,0 +1,140 @@
+//! Compares the per-particle field lookups of a timestep with temporary
+//! matrices allocated on the heap against preallocated strain and vorticity
+//! fields with fixed-size matrices.
+//!
+//! Run with `cargo bench --bench field_lookup`.
+
+use ndarray::{s, Array, ArrayView, Ix2, Ix5};
+use num_complex::Complex;
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+use stochasticsampling::distribution::Distribution;
+use stochasticsampling::flowfield::screening::HydroScreening;
+use stochasticsampling::flowfield::spectral_solver::SpectralSolver;
+use stochasticsampling::flowfield::stress::stresses::stress_active;
+use stochasticsampling::integrators::langevin_builder::modifiers::{
+    jeffrey_strain, jeffrey_vorticity,
+};
+use stochasticsampling::integrators::langevin_builder::TimeStep;
+use stochasticsampling::integrators::LangevinBuilder;
+use stochasticsampling::mesh::get_cell_index;
+use stochasticsampling::mesh::grid_width::GridWidth;
+use stochasticsampling::particle::Particle;
+use stochasticsampling::vector::Matrix3;
+use stochasticsampling::{BoxSize, Float, GridSize};
+
+const NUMBER_OF_PARTICLES: usize = 500_000;
+const REPETITIONS: u32 = 5;
+const SHAPE: Float = 0.5;
+const TIMESTEP: Float = 0.01;
+
+fn to_matrix(a: &Array<Float, Ix2>) -> Matrix3 {
+    let mut m = Matrix3::default();
+    for ((i, j), v) in a.indexed_iter() {
+        m[i][j] = *v;
+    }
+    m
+}
+
+/// Timestep of the Jeffery terms as it was done before, with a new gradient
+/// transpose and 3x3 arrays on the heap for every particle.
+fn allocating(
+    particles: &mut [Particle],
+    grad: ArrayView<Complex<Float>, Ix5>,
+    gw: &GridWidth,
+    gs: &GridSize,
+    bs: &BoxSize,
+) {
+    let mut grad_t = grad.to_owned();
+    grad_t.swap_axes(0, 1);
+    let vorticity = (&grad - &grad_t) * 0.5;
+    let strain = (&grad + &grad_t) * 0.5;
+
+    particles.par_iter_mut().for_each(|p| {
+        let (ix, iy, iz) = get_cell_index(&p.position, gw, gs);
+        let vortm = vorticity.slice(s![.., .., ix, iy, iz]).map(|v| v.re);
+        let strainm = strain.slice(s![.., .., ix, iy, iz]).map(|v| v.re);
+
+        *p = LangevinBuilder::new(p)
+            .with_param(jeffrey_vorticity, &to_matrix(&vortm))
+            .with_param(jeffrey_strain, (SHAPE, &to_matrix(&strainm)))
+            .step(&TimeStep(TIMESTEP))
+            .finalize(bs);
+    });
+}
+
+/// Timestep of the Jeffery terms using the preallocated fields of the solver.
+fn preallocated(
+    particles: &mut [Particle],
+    solver: &SpectralSolver,
+    gw: &GridWidth,
+    gs: &GridSize,
+    bs: &BoxSize,
+) {
+    let (strain, vorticity) = solver.get_strain_vorticity();
+
+    particles.par_iter_mut().for_each(|p| {
+        let (ix, iy, iz) = get_cell_index(&p.position, gw, gs);
+
+        *p = LangevinBuilder::new(p)
+            .with_param(jeffrey_vorticity, &vorticity[[ix, iy, iz]])
+            .with_param(jeffrey_strain, (SHAPE, &strain[[ix, iy, iz]]))
+            .step(&TimeStep(TIMESTEP))
+            .finalize(bs);
+    });
+}
+
+/// Returns the mean duration of `f`.
+fn measure<F: FnMut()>(mut f: F) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..REPETITIONS {
+        f();
+    }
+    start.elapsed() / REPETITIONS
+}
+
+fn main() {
+    let bs = BoxSize {
+        x: 32.,
+        y: 32.,
+        z: 32.,
+    };
+    let gs = GridSize {
+        x: 32,
+        y: 32,
+        z: 32,
+        phi: 8,
+        theta: 8,
+    };
+    let gw = GridWidth::new(gs, bs);
+
+    let particles = Particle::create_isotropic(NUMBER_OF_PARTICLES, &bs, 1);
+    let mut d = Distribution::new(gs, bs);
+    d.sample_scaled_from(&particles, bs.x * bs.y * bs.z);
+
+    let mut solver = SpectralSolver::new(gs, bs, stress_active);
+    let grad = solver
+        .mean_flow_field(HydroScreening::None, &d)
+        .1
+        .to_owned();
+
+    let mut p = particles.clone();
+    let old = measure(|| allocating(&mut p, grad.view(), &gw, &gs, &bs));
+    let mut p_old = particles.clone();
+    allocating(&mut p_old, grad.view(), &gw, &gs, &bs);
+
+    let mut p = particles.clone();
+    let new = measure(|| preallocated(&mut p, &solver, &gw, &gs, &bs));
+    let mut p_new = particles.clone();
+    preallocated(&mut p_new, &solver, &gw, &gs, &bs);
+
+    assert_eq!(p_old, p_new);
+
+    println!(
+        "{} particles on a {}^3 grid, Jeffery terms only",
+        NUMBER_OF_PARTICLES, gs.x
+    );
+    println!("{:<16}{:?}", "allocating:", old);
+    println!("{:<16}{:?}", "preallocated:", new);
+}
@@ -0,0 +1,85 @@
+//! Compares field lookups at the particle positions for unsorted particles,
+//! particles sorted by grid cell and sorted positions in a structure of arrays.
+//!
+//! Run with `cargo bench --bench particle_sorting`.
+
+use ndarray::{Array, Ix5};
+use std::time::{Duration, Instant};
+use stochasticsampling::mesh::grid_width::GridWidth;
+use stochasticsampling::mesh::{get_cell_index, sort_by_cell};
+use stochasticsampling::particle::{Particle, Position};
+use stochasticsampling::{BoxSize, Float, GridSize};
+
+const NUMBER_OF_PARTICLES: usize = 2_000_000;
+const REPETITIONS: u32 = 10;
+
+/// Sums the 3x3 matrix at the cell of every position, like the Jeffery terms
+/// in a timestep.
+fn lookup<'a, I>(positions: I, field: &Array<Float, Ix5>, gw: &GridWidth, gs: &GridSize) -> Float
+where
+    I: Iterator<Item = &'a Position>,
+{
+    let mut sum = 0.;
+    for p in positions {
+        let (ix, iy, iz) = get_cell_index(p, gw, gs);
+        for i in 0..3 {
+            for j in 0..3 {
+                sum += field[[i, j, ix, iy, iz]];
+            }
+        }
+    }
+    sum
+}
+
+/// Returns the mean duration of `f` and the result of its last call.
+fn measure<F: FnMut() -> Float>(mut f: F) -> (Duration, Float) {
+    let mut res = f();
+    let start = Instant::now();
+    for _ in 0..REPETITIONS {
+        res = f();
+    }
+    (start.elapsed() / REPETITIONS, res)
+}
+
+fn main() {
+    let bs = BoxSize {
+        x: 64.,
+        y: 64.,
+        z: 64.,
+    };
+    let gs = GridSize {
+        x: 64,
+        y: 64,
+        z: 64,
+        phi: 1,
+        theta: 1,
+    };
+    let gw = GridWidth::new(gs, bs);
+    let field = Array::from_shape_fn((3, 3, gs.x, gs.y, gs.z), |(i, j, x, y, z)| {
+        (i + j + x + y + z) as Float
+    });
+
+    let mut particles = Particle::create_isotropic(NUMBER_OF_PARTICLES, &bs, 1);
+
+    let (unsorted, res_unsorted) =
+        measure(|| lookup(particles.iter().map(|p| &p.position), &field, &gw, &gs));
+
+    let start = Instant::now();
+    sort_by_cell(&mut particles, &gw, &gs);
+    let sorting = start.elapsed();
+
+    let (sorted, res_sorted) =
+        measure(|| lookup(particles.iter().map(|p| &p.position), &field, &gw, &gs));
+
+    let positions: Vec<Position> = particles.iter().map(|p| p.position).collect();
+    let (soa, res_soa) = measure(|| lookup(positions.iter(), &field, &gw, &gs));
+
+    assert!((res_unsorted - res_sorted).abs() <= 1e-6 * res_unsorted);
+    assert!((res_sorted - res_soa).abs() <= 1e-6 * res_sorted);
+
+    println!("{} particles on a {}^3 grid", NUMBER_OF_PARTICLES, gs.x);
+    println!("{:<24}{:?}", "unsorted:", unsorted);
+    println!("{:<24}{:?}", "sorted:", sorted);
+    println!("{:<24}{:?}", "sorted, positions only:", soa);
+    println!("{:<24}{:?}", "sorting:", sorting);
+}
@@ -24,6 +24,23 @@ use fftw3_ffi::fftw_execute_dft;
 #[cfg(feature = "single")]
 use fftw3_ffi::fftwf_execute_dft as fftw_execute_dft;
 
+#[cfg(not(feature = "single"))]
+use fftw3_ffi::fftw_plan_guru_dft;
+#[cfg(feature = "single")]
+use fftw3_ffi::fftwf_plan_guru_dft as fftw_plan_guru_dft;
+
+#[cfg(not(feature = "single"))]
+use fftw3_ffi::fftw_plan_guru_r2r;
+#[cfg(feature = "single")]
+use fftw3_ffi::fftwf_plan_guru_r2r as fftw_plan_guru_r2r;
+
+#[cfg(not(feature = "single"))]
+use fftw3_ffi::fftw_execute_r2r;
+#[cfg(feature = "single")]
+use fftw3_ffi::fftwf_execute_r2r as fftw_execute_r2r;
+
+use fftw3_ffi::{fftw_iodim, fftw_r2r_kind};
+
 #[cfg(feature = "single")]
 pub type FFTWComplex = ::fftw3_ffi::fftwf_complex;
 #[cfg(not(feature = "single"))]
@@ -54,6 +71,34 @@ pub enum FFTFlags {
     EstimateUnaligned = (::fftw3_ffi::FFTW_ESTIMATE | ::fftw3_ffi::FFTW_UNALIGNED) as isize,
 }
 
+/// Kinds of real-to-real transformations. The data is assumed to be sampled
+/// at the cell centers `(n + 1/2) L / N`, so that the type II transformations
+/// expand the data in the modes `cos(pi m z / L)` and `sin(pi (m + 1) z / L)`
+/// respectively. The type III transformations are their inverse up to a
+/// factor `2 N`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum R2RKind {
+    /// Discrete cosine transformation (FFTW_REDFT10)
+    DCT2,
+    /// Inverse of `DCT2` (FFTW_REDFT01)
+    DCT3,
+    /// Discrete sine transformation (FFTW_RODFT10)
+    DST2,
+    /// Inverse of `DST2` (FFTW_RODFT01)
+    DST3,
+}
+
+impl R2RKind {
+    fn to_fftw(self) -> fftw_r2r_kind {
+        match self {
+            R2RKind::DCT2 => fftw_r2r_kind::FFTW_REDFT10,
+            R2RKind::DCT3 => fftw_r2r_kind::FFTW_REDFT01,
+            R2RKind::DST2 => fftw_r2r_kind::FFTW_RODFT10,
+            R2RKind::DST3 => fftw_r2r_kind::FFTW_RODFT01,
+        }
+    }
+}
+
 impl FFTPlan {
     /// Create a new FFTW3 complex to complex plan.
     /// INFO: According to FFTW3 documentation r2c/c2r can be more efficient.
@@ -207,6 +252,102 @@ impl FFTPlan {
         NonNull::new(plan).map(|p| FFTPlan { plan: p })
     }
 
+    /// Create a new FFTW3 complex to complex plan for an inplace
+    /// transformation along the first two axes of a 3D array only, i.e. for
+    /// every index of the last axis a 2D transformation is done.
+    /// WARNING: This is an unormalized transformation. A forwards and
+    /// backwards transformation will lead to input data scaled by the number
+    /// of elements of the first two axes.
+    pub fn new_c2c_inplace_3d_xy(
+        arr: &mut ArrayViewMut<Complex<Float>, Ix3>,
+        direction: FFTDirection,
+        flags: FFTFlags,
+    ) -> Option<FFTPlan> {
+        let (n0, n1, n2) = arr.dim();
+        let p = arr.as_ptr() as *mut FFTWComplex;
+
+        let dims = [
+            fftw_iodim {
+                n: n0 as std::os::raw::c_int,
+                is: (n1 * n2) as std::os::raw::c_int,
+                os: (n1 * n2) as std::os::raw::c_int,
+            },
+            fftw_iodim {
+                n: n1 as std::os::raw::c_int,
+                is: n2 as std::os::raw::c_int,
+                os: n2 as std::os::raw::c_int,
+            },
+        ];
+        let howmany = [fftw_iodim {
+            n: n2 as std::os::raw::c_int,
+            is: 1,
+            os: 1,
+        }];
+
+        let plan;
+        unsafe {
+            plan = fftw_plan_guru_dft(
+                2,
+                dims.as_ptr(),
+                1,
+                howmany.as_ptr(),
+                p,
+                p,
+                direction as std::os::raw::c_int,
+                flags as std::os::raw::c_uint,
+            );
+        }
+
+        NonNull::new(plan).map(|p| FFTPlan { plan: p })
+    }
+
+    /// Create a new FFTW3 real to real plan for an inplace transformation of
+    /// a complex 3D array along its last axis. Real and imaginary parts are
+    /// transformed independently.
+    /// WARNING: This is an unormalized transformation. A type II and its
+    /// inverse type III transformation will lead to input data scaled by two
+    /// times the length of the last axis.
+    pub fn new_r2r_inplace_3d_z(
+        arr: &mut ArrayViewMut<Complex<Float>, Ix3>,
+        kind: R2RKind,
+        flags: FFTFlags,
+    ) -> Option<FFTPlan> {
+        let (n0, n1, n2) = arr.dim();
+        let p = arr.as_ptr() as *mut Float;
+
+        // strides are given in units of real numbers
+        let dims = [fftw_iodim {
+            n: n2 as std::os::raw::c_int,
+            is: 2,
+            os: 2,
+        }];
+        let howmany = [
+            fftw_iodim {
+                n: (n0 * n1) as std::os::raw::c_int,
+                is: (2 * n2) as std::os::raw::c_int,
+                os: (2 * n2) as std::os::raw::c_int,
+            },
+            fftw_iodim { n: 2, is: 1, os: 1 },
+        ];
+        let kind = [kind.to_fftw()];
+
+        let plan;
+        unsafe {
+            plan = fftw_plan_guru_r2r(
+                1,
+                dims.as_ptr(),
+                2,
+                howmany.as_ptr(),
+                p,
+                p,
+                kind.as_ptr(),
+                flags as std::os::raw::c_uint,
+            );
+        }
+
+        NonNull::new(plan).map(|p| FFTPlan { plan: p })
+    }
+
     /// Execute FFTW# plan for associated given input and output.
     pub fn execute(&self) {
         unsafe { fftw_execute(self.plan.as_ptr()) }
@@ -229,6 +370,15 @@ impl FFTPlan {
             fftw_execute_dft(self.plan.as_ptr(), p, p);
         }
     }
+
+    /// Reuse real to real plan for different arrays. The plan has to be
+    /// created by `new_r2r_inplace_3d_z` for an array of the same shape.
+    pub fn reexecute3d_r2r(&self, a: &mut ArrayViewMut<Complex<Float>, Ix3>) {
+        let p = a.as_ptr() as *mut Float;
+        unsafe {
+            fftw_execute_r2r(self.plan.as_ptr(), p, p);
+        }
+    }
 }
 
 /// Automatically destroy FFTW3 plan, when going out of scope.
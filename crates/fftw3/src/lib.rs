@@ -172,4 +172,127 @@ mod tests {
             );
         }
     }
+
+    /// A type II and type III real to real transformation along the last axis
+    /// should be an identity operation (except for normalization factors).
+    /// WARNING: Not thread safe. Run with `env RUST_TEST_THREADS=1 cargo test`.
+    #[test]
+    fn test_r2r_identity_3d_z() {
+        let shape = ndarray::Dim([3usize, 4, 7]);
+
+        let mut input = FFTData3D::new(shape);
+        for ((i, j, k), v) in input.data.indexed_iter_mut() {
+            *v = Complex::new((i + 2 * j + k * k) as Float, (i * j) as Float - k as Float);
+        }
+
+        for &(kind, inverse) in &[
+            (fft::R2RKind::DCT2, fft::R2RKind::DCT3),
+            (fft::R2RKind::DST2, fft::R2RKind::DST3),
+        ] {
+            let mut output = input.data.to_owned();
+
+            let plan_forward = FFTPlan::new_r2r_inplace_3d_z(
+                &mut output.view_mut(),
+                kind,
+                fft::FFTFlags::Estimate,
+            )
+            .unwrap();
+            let plan_backward = FFTPlan::new_r2r_inplace_3d_z(
+                &mut output.view_mut(),
+                inverse,
+                fft::FFTFlags::Estimate,
+            )
+            .unwrap();
+
+            plan_forward.reexecute3d_r2r(&mut output.view_mut());
+            plan_backward.reexecute3d_r2r(&mut output.view_mut());
+
+            for (left, right) in input.data.iter().zip(output.iter()) {
+                let diff = *left - *right / (2 * shape[2]) as Float;
+                assert!(
+                    diff.norm() <= 100. * EPSILON,
+                    "{:?}: {} != {}",
+                    kind,
+                    left,
+                    right
+                );
+            }
+        }
+    }
+
+    /// The type II sine transformation of a sampled sine mode is only nonzero
+    /// at the corresponding index.
+    #[test]
+    fn test_dst_mode() {
+        use std::f64::consts::PI;
+
+        let n = 8;
+        let m = 3;
+        let shape = ndarray::Dim([2usize, 1, n]);
+
+        let mut data = FFTData3D::new(shape);
+        for ((_, _, k), v) in data.data.indexed_iter_mut() {
+            let s = (PI * (m as f64) * (k as f64 + 0.5) / n as f64).sin() as Float;
+            *v = Complex::new(s, -s);
+        }
+
+        let plan = FFTPlan::new_r2r_inplace_3d_z(
+            &mut data.data.view_mut(),
+            fft::R2RKind::DST2,
+            fft::FFTFlags::Estimate,
+        )
+        .unwrap();
+        plan.execute();
+
+        for ((_, _, k), v) in data.data.indexed_iter() {
+            let expected = if k + 1 == m { n as Float } else { 0. };
+            assert!((v.re - expected).abs() <= 100. * EPSILON, "{} at {}", v, k);
+            assert!((v.im + expected).abs() <= 100. * EPSILON, "{} at {}", v, k);
+        }
+    }
+
+    /// Transforming only along the first two axes for and back should be an
+    /// identity operation (except for normalization factors).
+    #[test]
+    fn test_fft_identity_3d_xy() {
+        let shape = ndarray::Dim([5usize, 6, 3]);
+
+        let mut input = FFTData3D::new(shape);
+        for ((i, j, k), v) in input.data.indexed_iter_mut() {
+            *v = Complex::new((i * j + k) as Float, (i + j * k) as Float);
+        }
+
+        let mut output = input.data.to_owned();
+        let plan_forward = FFTPlan::new_c2c_inplace_3d_xy(
+            &mut output.view_mut(),
+            fft::FFTDirection::Forward,
+            fft::FFTFlags::Estimate,
+        )
+        .unwrap();
+        let plan_backward = FFTPlan::new_c2c_inplace_3d_xy(
+            &mut output.view_mut(),
+            fft::FFTDirection::Backward,
+            fft::FFTFlags::Estimate,
+        )
+        .unwrap();
+
+        plan_forward.reexecute3d(&mut output.view_mut());
+
+        // the mean over x and y of every z-slice is stored in the zero mode
+        for k in 0..shape[2] {
+            let sum = input
+                .data
+                .index_axis(ndarray::Axis(2), k)
+                .iter()
+                .fold(Complex::new(0., 0.), |a, b| a + b);
+            assert!((output[[0, 0, k]] - sum).norm() <= 100. * EPSILON);
+        }
+
+        plan_backward.reexecute3d(&mut output.view_mut());
+
+        for (left, right) in input.data.iter().zip(output.iter()) {
+            let diff = *left - *right / (shape[0] * shape[1]) as Float;
+            assert!(diff.norm() <= 100. * EPSILON, "{} != {}", left, right);
+        }
+    }
 }
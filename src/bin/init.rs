@@ -74,26 +74,52 @@ pub fn init_simulation(settings: &Settings, init_type: InitType) -> Result<Simul
             simulation.init(p);
         }
         InitType::Distribution => {
-            let p = match settings.simulation.init_distribution {
-                InitDistribution::Isotropic => {
-                    info!("Using isotropic initial condition.");
-                    Particle::create_isotropic(
-                        settings.simulation.number_of_particles,
-                        &settings.simulation.box_size,
-                        settings.simulation.seed,
-                    )
-                }
-                InitDistribution::Homogeneous => {
-                    info!("Using spatial homogeneous initial condition.");
-                    Particle::create_homogeneous(
-                        settings.simulation.number_of_particles,
-                        settings.parameters.magnetic_reorientation
-                            / settings.parameters.diffusion.rotational,
-                        &settings.simulation.box_size,
-                        settings.simulation.seed,
-                    )
+            let sim = &settings.simulation;
+            let mut p = Vec::with_capacity(sim.number_of_particles);
+
+            // use a different seed for every species to avoid identical
+            // positions of their particles
+            for (i, s) in settings
+                .parameters
+                .species(sim.number_of_particles)
+                .iter()
+                .enumerate()
+            {
+                let seed = sim.seed.wrapping_add(i as u64);
+                // tumbling decorrelates the orientation like additional
+                // rotational diffusion
+                let rotdiff = s.diffusion.rotational
+                    + settings
+                        .parameters
+                        .tumbling
+                        .effective_rotational_diffusion(sim.dimensionality);
+                let kappa =
+                    s.magnetic_moment * settings.parameters.magnetic_reorientation / rotdiff;
+
+                match sim.init_distribution {
+                    // non-magnetic species are isotropic anyway
+                    InitDistribution::Homogeneous if kappa != 0. => {
+                        info!(
+                            "Using spatial homogeneous initial condition for species {}.",
+                            i
+                        );
+                        p.append(&mut Particle::create_homogeneous(
+                            s.number_of_particles,
+                            kappa,
+                            &sim.box_size,
+                            seed,
+                        ))
+                    }
+                    _ => {
+                        info!("Using isotropic initial condition for species {}.", i);
+                        p.append(&mut Particle::create_isotropic(
+                            s.number_of_particles,
+                            &sim.box_size,
+                            seed,
+                        ))
+                    }
                 }
-            };
+            }
 
             simulation.init(p);
         }
@@ -20,11 +20,14 @@ use crate::init::InitType;
 use crate::output::path::OutputPath;
 use crate::output::worker::Worker;
 use clap::load_yaml;
-use clap::App;
+use clap::{App, ArgMatches};
 use colored::*;
+use fftw3::fft;
 use log::{debug, error, info};
 use pbr::ProgressBar;
+use std::env;
 use std::path::Path;
+use std::str::FromStr;
 use stochasticsampling::output::OutputEntry;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -34,9 +37,6 @@ pub fn version() -> String {
     if cfg!(feature = "single") {
         s.push_str("-s");
     }
-    if cfg!(feature = "quasi2d") {
-        s.push_str("-q2d");
-    };
     format!("{}-{}{}", VERSION, env!("VERGEN_SHA_SHORT"), s)
 }
 
@@ -74,7 +74,7 @@ fn run() -> Result<()> {
 
     let settings_file_name = cli_matches.value_of("parameter_file").unwrap();
 
-    let mut settings = if cli_matches.is_present("si_units") {
+    let settings = if cli_matches.is_present("si_units") {
         settings::si::read_parameter_file(settings_file_name)
             .chain_err(|| "Error reading parameter file.")?
             .into_settings()
@@ -83,6 +83,72 @@ fn run() -> Result<()> {
             .chain_err(|| "Error reading parameter file.")?
     };
 
+    let runs = match cli_matches.value_of("convergence") {
+        Some(factors) => convergence_runs(&settings, factors)?,
+        None => vec![settings],
+    };
+
+    // The global thread pool and the threads of FFTW are shared by all runs
+    // of a convergence study.
+    init_threads()?;
+
+    for settings in runs {
+        if cli_matches.is_present("convergence") {
+            info!(
+                "Convergence study: running '{}' with grid size {:?}.",
+                settings.environment.prefix, settings.simulation.grid_size
+            );
+        }
+        run_case(settings, &cli_matches)?;
+    }
+
+    fft::fttw_finalize();
+
+    Ok(())
+}
+
+/// Returns the settings of a convergence study, which runs the same case on
+/// grids refined by the comma separated `factors` one after the other.
+fn convergence_runs(settings: &Settings, factors: &str) -> Result<Vec<Settings>> {
+    factors
+        .split(',')
+        .map(|f| {
+            let factor = f
+                .trim()
+                .parse::<usize>()
+                .chain_err(|| format!("Invalid refinement factor '{}'.", f))?;
+            settings
+                .with_refined_grid(factor)
+                .chain_err(|| format!("Cannot refine the grid by {}.", factor))
+        })
+        .collect()
+}
+
+/// Sets up the global thread pool and the threads of FFTW with the number of
+/// threads given by the environment variable `RAYON_NUM_THREADS`. Both can
+/// only be set up once per process.
+fn init_threads() -> Result<()> {
+    let num_threads = env::var("RAYON_NUM_THREADS")
+        .ok()
+        .and_then(|s| usize::from_str(&s).ok())
+        .ok_or("No environment variable 'RAYON_NUM_THREADS' set.")?;
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build_global()
+        .chain_err(|| "Unable to create the thread pool.")?;
+
+    // Initialze threads of FFTW
+    if fft::fftw_init(Some(num_threads)).is_err() {
+        bail!("Unable to initialize the threads of FFTW.");
+    }
+
+    Ok(())
+}
+
+/// Prepares the output and the initial condition of a single simulation with
+/// the settings `settings` and runs it.
+fn run_case(mut settings: Settings, cli_matches: &ArgMatches) -> Result<()> {
     let output_dir = Path::new(cli_matches.value_of("output_directory").unwrap());
     let path = OutputPath::new(output_dir, &settings.environment.prefix);
     path.create()
@@ -146,6 +212,9 @@ fn run_simulation(
         info!("Saving initial condition.");
         let mut initial = OutputEntry::default();
         initial.distribution = Some(simulation.get_distribution());
+        if simulation.get_number_of_species() > 1 {
+            initial.species_distributions = Some(simulation.get_species_distributions());
+        }
         initial.particles = if settings.simulation.output_at_timestep.particles.is_some() {
             settings
                 .simulation
@@ -157,6 +226,16 @@ fn run_simulation(
             None
         };
 
+        if settings.simulation.output_at_timestep.tracers.is_some() {
+            initial.tracers = Some(simulation.get_tracers());
+        }
+
+        if settings.simulation.output_at_timestep.chemical.is_some() {
+            initial.chemical = simulation.get_chemical();
+        }
+
+        initial.parameters = simulation.get_scheduled_parameters();
+
         out.append(initial)
             .chain_err(|| "Unable to append initial condition.")?;
     }
@@ -199,6 +278,17 @@ fn run_simulation(
                         None
                     }
                 }),
+            species_distributions: settings
+                .simulation
+                .output_at_timestep
+                .distribution
+                .and_then(|x| {
+                    if timestep % x == 0 && simulation.get_number_of_species() > 1 {
+                        Some(simulation.get_species_distributions())
+                    } else {
+                        None
+                    }
+                }),
             flowfield: settings
                 .simulation
                 .output_at_timestep
@@ -240,10 +330,56 @@ fn run_simulation(
                         None
                     }
                 }),
+            stress: settings.simulation.output_at_timestep.stress.and_then(|x| {
+                if timestep % x == 0 {
+                    info!("Timestep {}: Save stress...", timestep);
+                    Some(simulation.get_bulk_stress())
+                } else {
+                    None
+                }
+            }),
+            tracers: settings
+                .simulation
+                .output_at_timestep
+                .tracers
+                .and_then(|x| {
+                    if timestep % x == 0 {
+                        info!("Timestep {}: Save tracers...", timestep);
+                        Some(simulation.get_tracers())
+                    } else {
+                        None
+                    }
+                }),
+            chemical: settings
+                .simulation
+                .output_at_timestep
+                .chemical
+                .and_then(|x| {
+                    if timestep % x == 0 {
+                        info!("Timestep {}: Save chemical...", timestep);
+                        simulation.get_chemical()
+                    } else {
+                        None
+                    }
+                }),
+            parameters: simulation.get_scheduled_parameters(),
             timestep: timestep,
         };
 
-        if entry.distribution.is_some() || entry.flowfield.is_some() || entry.particles.is_some() {
+        let parameters_due = settings
+            .simulation
+            .output_at_timestep
+            .parameters
+            .map_or(false, |x| timestep % x == 0);
+
+        if entry.distribution.is_some()
+            || entry.flowfield.is_some()
+            || entry.particles.is_some()
+            || entry.stress.is_some()
+            || entry.tracers.is_some()
+            || entry.chemical.is_some()
+            || (parameters_due && entry.parameters.is_some())
+        {
             debug!("Some output is appended to queue.");
             match out.append(entry) {
                 Ok(_) => (),
@@ -287,3 +423,86 @@ fn run_simulation(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array;
+    use stochasticsampling::distribution::OrientationRepresentation;
+    use stochasticsampling::flowfield::stress::stresses::stress_active;
+    use stochasticsampling::polydispersity::AttributeDistribution;
+    use stochasticsampling::Float;
+
+    /// Every run of a convergence study sets up its own simulation in the
+    /// same process.
+    #[test]
+    fn convergence_study() {
+        let settings = settings::read_parameter_file("./test/parameter.toml").unwrap();
+        let runs = convergence_runs(&settings, "1, 2").unwrap();
+        assert_eq!(runs.len(), 2);
+
+        for settings in &runs {
+            let mut simulation = init::init_simulation(settings, InitType::Distribution).unwrap();
+            assert_eq!(simulation.do_timestep(), 1);
+        }
+    }
+
+    /// Passive tracers draw their random numbers from their own generators,
+    /// which are part of the snapshot. So they neither change the trajectories
+    /// of the particles nor their own trajectories, when a simulation is
+    /// resumed.
+    #[test]
+    fn tracer_random_numbers() {
+        let mut settings = settings::read_parameter_file("./test/parameter.toml").unwrap();
+        settings.parameters.tracer_diffusion = 0.5;
+        let mut plain = init::init_simulation(&settings, InitType::Distribution).unwrap();
+
+        settings.simulation.number_of_tracers = 20;
+        let mut traced = init::init_simulation(&settings, InitType::Distribution).unwrap();
+        for _ in 0..2 {
+            plain.do_timestep();
+            traced.do_timestep();
+        }
+        assert_eq!(plain.get_particles(), traced.get_particles());
+
+        let snapshot = traced.get_snapshot();
+        traced.do_timestep();
+
+        let mut resumed = init::init_simulation(&settings, InitType::Distribution).unwrap();
+        resumed.resume(snapshot);
+        resumed.do_timestep();
+        assert_eq!(resumed.get_tracers(), traced.get_tracers());
+    }
+
+    /// The active stress of polydisperse particles is proportional to their
+    /// speed. Spherical harmonics of degree two represent it exactly.
+    #[test]
+    fn speed_weighted_active_stress() {
+        let mut settings = settings::read_parameter_file("./test/parameter.toml").unwrap();
+        settings.simulation.orientation =
+            OrientationRepresentation::SphericalHarmonics { degree: 2 };
+        settings.parameters.polydispersity.speed = AttributeDistribution::LogNormal { sigma: 0.5 };
+        let simulation = init::init_simulation(&settings, InitType::Distribution).unwrap();
+
+        let particles = simulation.get_particles();
+        assert!(particles.iter().any(|p| p.attributes.speed != 1.));
+
+        let mut expect = Array::zeros((3, 3));
+        for p in &particles {
+            let o = p.orientation;
+            expect += &(stress_active(o.phi, o.theta) * p.attributes.speed);
+        }
+        expect /= particles.len() as Float;
+
+        let active = simulation.get_bulk_stress().active;
+        for ((i, j), e) in expect.indexed_iter() {
+            assert!(
+                (active[i][j] - e).abs() < 1e-12,
+                "{:?}: {} != {}",
+                (i, j),
+                active[i][j],
+                e
+            );
+        }
+    }
+}
@@ -22,8 +22,8 @@ const COMPRESSION: u32 = 1;
 pub enum IOWorkerMsg {
     Quit,
     Snapshot(Snapshot),
-    Output(OutputEntry),
-    Settings(Settings),
+    Output(Box<OutputEntry>),
+    Settings(Box<Settings>),
 }
 
 struct OutputFile {
@@ -67,14 +67,14 @@ impl Worker {
 
     pub fn write_metadata(&self, settings: Settings) -> Result<()> {
         self.tx
-            .send(IOWorkerMsg::Settings(settings))
+            .send(IOWorkerMsg::Settings(Box::new(settings)))
             .chain_err(|| "Cannot write metadata to output file.")
     }
 
     pub fn append(&self, output: OutputEntry) -> Result<()> {
         debug!("Some data was appended to the output queue.");
         self.tx
-            .send(IOWorkerMsg::Output(output))
+            .send(IOWorkerMsg::Output(Box::new(output)))
             .chain_err(|| "Cannot append data to file.")?;
 
         Ok(())
@@ -3,48 +3,64 @@
 
 pub mod settings;
 
-use self::settings::Settings;
-use fftw3::fft;
-use ndarray::{Array, ArrayView, Axis, Ix2, Ix4, Ix5};
+use self::settings::{Parameters, Settings, SimulationSettings, Species, VolumeExclusionModel};
+use ndarray::{Array, ArrayView, Ix2, Ix3, Ix4};
 use num_complex::Complex;
 
-use ndarray::s;
-use rand_distr::StandardNormal;
 use rand::distributions::Uniform;
 use rand::Rng;
 use rand::SeedableRng;
+use rand_distr::StandardNormal;
 use rand_pcg::Pcg32;
 use rayon;
 use rayon::prelude::*;
 use serde_derive::{Deserialize, Serialize};
-use std::env;
-use std::str::FromStr;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use stochasticsampling::chemical_field::chemical_solver::ChemicalSolver;
 use stochasticsampling::consts::TWOPI;
-// use stochasticsampling::distribution::density_gradient::DensityGradient;
+use stochasticsampling::distribution::density_gradient::DensityGradient;
 use stochasticsampling::distribution::Distribution;
+use stochasticsampling::flowfield::regularisation::ForceRegularisation;
+use stochasticsampling::flowfield::screening::HydroScreening;
 use stochasticsampling::flowfield::spectral_solver::SpectralSolver;
 use stochasticsampling::flowfield::stress::stresses::*;
+use stochasticsampling::flowfield::stress::{BulkStress, BulkStressMeter};
+use stochasticsampling::flowfield::wall_solver::WallSpectralSolver;
 use stochasticsampling::flowfield::FlowField3D;
+use stochasticsampling::integrators::langevin::vector_gradient_at_cell;
 use stochasticsampling::integrators::langevin_builder::modifiers::*;
 use stochasticsampling::integrators::langevin_builder::TimeStep;
 use stochasticsampling::integrators::LangevinBuilder;
+use stochasticsampling::magnetic_interaction::external_field::FieldMap;
 use stochasticsampling::magnetic_interaction::magnetic_solver::MagneticSolver;
-use stochasticsampling::mesh::get_cell_index;
+use stochasticsampling::magnetic_interaction::near_field::NearField;
+use stochasticsampling::mesh::fft_helper::SpectralFilter;
 use stochasticsampling::mesh::grid_width::GridWidth;
-// use stochasticsampling::mesh::interpolate::interpolate_vector_field;
-use stochasticsampling::particle::Particle;
-use stochasticsampling::vector::VectorD;
+use stochasticsampling::mesh::interpolate::interpolate_vector_field;
+use stochasticsampling::mesh::{get_cell_index, sort_by_cell};
+use stochasticsampling::particle::{Orientation, Particle, ParticleAttributes, Position};
+use stochasticsampling::schedule::Schedule;
+use stochasticsampling::steric::StericInteraction;
+use stochasticsampling::vector::{mat_add, Matrix3, VectorD};
 use stochasticsampling::Float;
 
-#[cfg(feature = "single")]
-use std::f32::consts::PI;
-#[cfg(not(feature = "single"))]
-use std::f64::consts::PI;
-
 struct ParamCache {
     // trans_diff: Float,
     // rot_diff: Float,
     grid_width: GridWidth,
+    /// indices of the particles of every species
+    species_ranges: Vec<Range<usize>>,
+    /// relative magnetic moment of every species
+    magnetic_moments: Vec<Float>,
+    /// magnetic moment of every particle normalised like the magnetisation
+    /// on the mesh, only used for the magnetic near field
+    particle_moments: Vec<Float>,
+    /// contribution of every particle to the magnetisation on the mesh
+    volume_per_particle: Float,
+    /// particles have individual attributes, whose weighted distributions
+    /// enter the magnetisation and the passive stresses
+    polydisperse: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -54,28 +70,217 @@ pub struct RandomVector {
     pub z: Float,
     pub axis_angle: Float,
     pub rotate_angle: Float,
+    /// random number deciding about a tumble and the tumble itself, only
+    /// drawn with tumbling
+    pub tumble: Option<(Float, RotDiff)>,
+}
+
+/// Solver of the Stokes equation for the boundary conditions in z direction.
+enum FlowSolver {
+    Periodic(SpectralSolver),
+    Walls(WallSpectralSolver),
+}
+
+impl FlowSolver {
+    fn add_species<F>(&mut self, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        match self {
+            FlowSolver::Periodic(s) => s.add_species(stress),
+            FlowSolver::Walls(s) => s.add_species(stress),
+        }
+    }
+
+    fn set_magnetic_dipole_stress(&mut self, prefactor: Float, moments: Vec<Float>) {
+        match self {
+            FlowSolver::Periodic(s) => s.set_magnetic_dipole_stress(prefactor, moments),
+            FlowSolver::Walls(s) => s.set_magnetic_dipole_stress(prefactor, moments),
+        }
+    }
+
+    fn set_species_stress<F>(&mut self, species: usize, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        match self {
+            FlowSolver::Periodic(s) => s.set_species_stress(species, stress),
+            FlowSolver::Walls(s) => s.set_species_stress(species, stress),
+        }
+    }
+
+    fn add_weighted_stress<F>(&mut self, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        match self {
+            FlowSolver::Periodic(s) => s.add_weighted_stress(stress),
+            FlowSolver::Walls(s) => s.add_weighted_stress(stress),
+        }
+    }
+
+    fn set_weighted_stress<F>(&mut self, index: usize, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        match self {
+            FlowSolver::Periodic(s) => s.set_weighted_stress(index, stress),
+            FlowSolver::Walls(s) => s.set_weighted_stress(index, stress),
+        }
+    }
+
+    fn set_magnetic_field(
+        &mut self,
+        field: ArrayView<Complex<Float>, Ix4>,
+        dists: &[Distribution],
+    ) {
+        match self {
+            FlowSolver::Periodic(s) => s.set_magnetic_field(field, dists),
+            FlowSolver::Walls(s) => s.set_magnetic_field(field, dists),
+        }
+    }
+
+    /// Sets the body force of the particles, which is only supported for
+    /// periodic boundaries, see `check_settings`.
+    fn set_body_force(&mut self, force: [Float; 3]) {
+        match self {
+            FlowSolver::Periodic(s) => s.set_body_force(force),
+            FlowSolver::Walls(_) => assert!(
+                force == [0.; 3],
+                "The body force of the particles is not supported in combination with walls."
+            ),
+        }
+    }
+
+    /// Sets the spectral filter, which is only supported for periodic
+    /// boundaries, see `check_settings`.
+    fn set_filter(&mut self, filter: SpectralFilter) {
+        match self {
+            FlowSolver::Periodic(s) => s.set_filter(filter),
+            FlowSolver::Walls(_) => assert!(
+                filter == SpectralFilter::None,
+                "The spectral filter is not supported in combination with walls."
+            ),
+        }
+    }
+
+    fn set_regularisation(&mut self, regularisation: ForceRegularisation) {
+        match self {
+            FlowSolver::Periodic(s) => s.set_regularisation(regularisation),
+            FlowSolver::Walls(s) => s.set_regularisation(regularisation),
+        }
+    }
+
+    /// Sets the strain of the box for Lees-Edwards boundary conditions, which
+    /// are only supported for periodic boundaries.
+    fn set_strain(&mut self, strain: Float) {
+        if let FlowSolver::Periodic(s) = self {
+            s.set_strain(strain);
+        }
+    }
+
+    fn mean_flow_field(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+        weighted: &[Distribution],
+    ) {
+        match self {
+            FlowSolver::Periodic(s) => {
+                s.mean_flow_field_of_weighted_species(screening, dists, weighted);
+            }
+            FlowSolver::Walls(s) => {
+                s.mean_flow_field_of_weighted_species(screening, dists, weighted);
+            }
+        }
+    }
+
+    fn get_flow_field(&self) -> ArrayView<'_, Complex<Float>, Ix4> {
+        match self {
+            FlowSolver::Periodic(s) => s.get_flow_field(),
+            FlowSolver::Walls(s) => s.get_flow_field(),
+        }
+    }
+
+    fn get_strain_vorticity(&self) -> (ArrayView<'_, Matrix3, Ix3>, ArrayView<'_, Matrix3, Ix3>) {
+        match self {
+            FlowSolver::Periodic(s) => s.get_strain_vorticity(),
+            FlowSolver::Walls(s) => s.get_strain_vorticity(),
+        }
+    }
+
+    fn get_real_flow_field(&self) -> FlowField3D {
+        match self {
+            FlowSolver::Periodic(s) => s.get_real_flow_field(),
+            FlowSolver::Walls(s) => s.get_real_flow_field(),
+        }
+    }
 }
 
 /// Main data structure representing the simulation.
 pub struct Simulation {
-    spectral_solver: SpectralSolver,
+    spectral_solver: FlowSolver,
     magnetic_solver: MagneticSolver,
-    // density_gradient: DensityGradient,
+    /// solver of the chemical field, only used with chemotaxis
+    chemical_solver: Option<ChemicalSolver>,
+    /// stress meter of every species
+    stress_meters: Vec<BulkStressMeter>,
+    /// gradient of the density, only used with the volume exclusion force
+    density_gradient: Option<DensityGradient>,
+    /// short-range pair interactions, only used if enabled
+    steric: Option<StericInteraction>,
+    /// short-range part of the magnetic dipole-dipole interaction, only used
+    /// if enabled
+    near_field: Option<NearField>,
+    /// spatially varying external magnetic field, only used if given
+    external_field: Option<FieldMap>,
+    /// loaded schedules of the time-dependent parameters
+    schedule: Vec<(String, Schedule)>,
+    /// values of the scheduled parameters in the last timestep
+    scheduled_values: BTreeMap<String, Float>,
     settings: Settings,
+    species: Vec<Species>,
     state: SimulationState,
     pcache: ParamCache,
 }
 
 /// Holds the current state of the simulation.
 struct SimulationState {
-    distribution: Distribution,
+    /// distribution of every species
+    distributions: Vec<Distribution>,
+    /// distributions of polydisperse particles weighted by the magnetic
+    /// moment of every species followed by the ones weighted by the shape and
+    /// the ones weighted by the speed, empty for monodisperse particles
+    weighted_distributions: Vec<Distribution>,
+    /// density of all species, preallocated to be reused every timestep
+    density: Array<Float, Ix3>,
+    /// density of a single species, preallocated to be reused every timestep
+    species_density: Array<Float, Ix3>,
     particles: Vec<Particle>,
     random_samples: Vec<RandomVector>,
+    /// passive tracers, which are only advected by the flow
+    tracers: Vec<Position>,
+    tracer_samples: Vec<[Float; 3]>,
     rng: Vec<Pcg32>,
+    /// random number generators of the tracers, which are independent of the
+    /// particles, so adding tracers does not change the particle trajectories
+    tracer_rng: Vec<Pcg32>,
     /// count timesteps
     timestep: usize,
 }
 
+impl SimulationState {
+    /// Calculates the density of all species.
+    fn update_density(&mut self) {
+        let (first, rest) = self.distributions.split_first().unwrap();
+        first.density_into(self.density.view_mut());
+        for d in rest {
+            d.density_into(self.species_density.view_mut());
+            self.density += &self.species_density;
+        }
+    }
+}
+
 /// Captures the full state of the simulation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Snapshot {
@@ -83,53 +288,167 @@ pub struct Snapshot {
     rng_state: Vec<Pcg32>,
     /// current timestep number
     timestep: usize,
+    /// species of every particle, empty for snapshots without species
+    #[serde(default)]
+    species: Vec<usize>,
+    #[serde(default)]
+    tracers: Vec<Position>,
+    /// empty for snapshots without tracers
+    #[serde(default)]
+    tracer_rng_state: Vec<Pcg32>,
+    /// concentration of the chemical, only with chemotaxis
+    #[serde(default)]
+    chemical: Option<Array<Float, Ix3>>,
+    /// attributes of every particle, empty for monodisperse particles
+    #[serde(default)]
+    attributes: Vec<ParticleAttributes>,
 }
 
 impl Simulation {
     /// Return a new simulation data structure, holding the state of the
     /// simulation.
-    pub fn new(settings: Settings) -> Simulation {
+    pub fn new(mut settings: Settings) -> Simulation {
+        // The schedules are read once and start with their values at time
+        // zero. A resumed simulation sets them in the first timestep.
+        let schedule: Vec<(String, Schedule)> = settings
+            .parameters
+            .schedule
+            .iter()
+            .map(|(p, s)| (p.clone(), s.load().expect("Cannot load the schedule.")))
+            .collect();
+        let scheduled_values = scheduled_values(&schedule, 0.);
+        settings.parameters = settings
+            .parameters
+            .with_values(&scheduled_values)
+            .expect("Invalid scheduled parameters.");
+
         // helper bindings for brevity
         let sim = settings.simulation;
-        let param = settings.parameters;
+        let species = settings.parameters.species(sim.number_of_particles);
+        let polydisperse = !settings.parameters.polydispersity.is_monodisperse();
+        let stress = |s: Species| species_stress(s, polydisperse);
 
-        if cfg!(feature = "quasi2d") && sim.grid_size.z != 1 {
-            panic!("z-direction must only contain 1 cell if feature 'quasi2d' is activated.");
+        let mut spectral_solver = if sim.walls.is_some() {
+            FlowSolver::Walls(WallSpectralSolver::with_orientation(
+                sim.grid_size,
+                sim.box_size,
+                sim.orientation,
+                stress(species[0]),
+            ))
+        } else {
+            let mut s = SpectralSolver::with_orientation(
+                sim.grid_size,
+                sim.box_size,
+                sim.orientation,
+                stress(species[0]),
+            );
+            s.set_dimensionality(sim.dimensionality);
+            // sedimenting particles push on the fluid
+            let gravity = settings.parameters.gravity;
+            s.set_body_force((gravity.unit_direction() * gravity.body_force).v);
+            FlowSolver::Periodic(s)
+        };
+        spectral_solver.set_regularisation(settings.parameters.force_regularisation);
+        spectral_solver.set_filter(sim.spectral_filter);
+        for s in &species[1..] {
+            spectral_solver.add_species(stress(*s));
+        }
+        if polydisperse {
+            for s in &species {
+                spectral_solver.add_weighted_stress(weighted_stresses(*s).0);
+            }
+            for s in &species {
+                spectral_solver.add_weighted_stress(weighted_stresses(*s).1);
+            }
+            for s in &species {
+                spectral_solver.add_weighted_stress(weighted_stresses(*s).2);
+            }
         }
+        let magnetic_dipole_stress = settings.parameters.magnetic_dipole.stress;
+        if magnetic_dipole_stress != 0. {
+            spectral_solver.set_magnetic_dipole_stress(
+                magnetic_dipole_stress,
+                species.iter().map(|s| s.magnetic_moment).collect(),
+            );
+        }
+        let mut magnetic_solver =
+            MagneticSolver::with_orientation(sim.grid_size, sim.box_size, sim.orientation);
+        magnetic_solver.set_filter(sim.spectral_filter);
+        // the mesh only handles the long-range part of the magnetic near field
+        let near_field = settings.parameters.magnetic_dipole.near_field.map(|p| {
+            magnetic_solver.set_splitting(p.splitting);
+            NearField::new(p, sim.box_size, sim.walls.is_none())
+        });
+        let external_field = settings.parameters.external_field_map.as_ref().map(|m| {
+            m.field
+                .load(sim.box_size)
+                .expect("Cannot load the external magnetic field.")
+        });
+        let chemical_solver = settings
+            .parameters
+            .chemotaxis
+            .map(|c| ChemicalSolver::new(sim.grid_size, sim.box_size, c.field));
+        let stress_meters = stress_meters(&sim, &species);
 
-        let stress = |phi, theta| {
-            param.stress.active * stress_active(phi, theta)
-                + param.stress.magnetic * stress_magnetic(phi, theta)
-                + param.shape * stress_magnetic_rods(phi, theta)
+        let mut species_ranges = Vec::with_capacity(species.len());
+        let mut start = 0;
+        for s in &species {
+            species_ranges.push(start..start + s.number_of_particles);
+            start += s.number_of_particles;
+        }
+        let density_gradient = match settings.parameters.volume_exclusion_model {
+            VolumeExclusionModel::Force => {
+                let mut g = DensityGradient::new(sim.grid_size, sim.box_size);
+                g.set_filter(sim.spectral_filter);
+                Some(g)
+            }
+            VolumeExclusionModel::Diffusive => None,
         };
 
-        let spectral_solver = SpectralSolver::new(sim.grid_size, sim.box_size, stress);
-        let magnetic_solver = MagneticSolver::new(sim.grid_size, sim.box_size);
-        // let density_gradient = DensityGradient::new(sim.grid_size, sim.box_size);
+        let steric = settings
+            .parameters
+            .steric
+            .map(|p| StericInteraction::new(p, sim.box_size, sim.walls.is_none()));
+
+        // every particle contributes the volume per particle to the
+        // magnetisation on the mesh
+        let volume_per_particle =
+            sim.box_size.x * sim.box_size.y * sim.box_size.z / sim.number_of_particles as Float;
 
         // normal distribution with variance timestep
         let seed = sim.seed;
 
-        let num_threads = env::var("RAYON_NUM_THREADS")
-            .ok()
-            .and_then(|s| usize::from_str(&s).ok())
-            .expect("No environment variable 'RAYON_NUM_THREADS' set.");
-
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(num_threads)
-            .build_global()
-            .unwrap();
-
-        // Initialze threads of FFTW
-        fft::fftw_init(Some(num_threads)).unwrap();
-
-        let rng = (0..num_threads)
+        // one random number generator per thread of the global thread pool,
+        // which is set up by `init_threads`
+        let rng = (0..rayon::current_num_threads())
             .map(|_| SeedableRng::seed_from_u64(seed))
             .collect();
 
+        // use a seed different from all species, the tracer positions and the
+        // particle attributes, see `init`
+        let tracer_seed = seed.wrapping_add(species.len() as u64 + 2);
+        let tracer_rng = (0..rayon::current_num_threads())
+            .map(|_| SeedableRng::seed_from_u64(tracer_seed))
+            .collect();
+
+        let new_distribution =
+            |_| Distribution::with_orientation(sim.grid_size, sim.box_size, sim.orientation);
+
         // initialize state with zeros
         let state = SimulationState {
-            distribution: Distribution::new(sim.grid_size, sim.box_size),
+            distributions: species.iter().map(new_distribution).collect(),
+            weighted_distributions: if polydisperse {
+                species
+                    .iter()
+                    .chain(&species)
+                    .chain(&species)
+                    .map(new_distribution)
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            density: Array::zeros((sim.grid_size.x, sim.grid_size.y, sim.grid_size.z)),
+            species_density: Array::zeros((sim.grid_size.x, sim.grid_size.y, sim.grid_size.z)),
             particles: Vec::with_capacity(sim.number_of_particles),
             random_samples: vec![
                 RandomVector {
@@ -138,24 +457,41 @@ impl Simulation {
                     z: 0.,
                     axis_angle: 0.,
                     rotate_angle: 0.,
+                    tumble: None,
                 };
                 sim.number_of_particles
             ],
+            tracers: Vec::with_capacity(sim.number_of_tracers),
+            tracer_samples: vec![[0.; 3]; sim.number_of_tracers],
             rng: rng,
+            tracer_rng,
             timestep: 0,
         };
 
         Simulation {
             spectral_solver: spectral_solver,
             magnetic_solver: magnetic_solver,
-            // density_gradient: density_gradient,
+            chemical_solver,
+            stress_meters,
+            density_gradient,
+            steric,
+            near_field,
+            external_field,
+            schedule,
+            scheduled_values,
             settings: settings,
             state: state,
             pcache: ParamCache {
                 // trans_diff: (2. * param.diffusion.translational * sim.timestep).sqrt(),
                 // rot_diff: (2. * param.diffusion.rotational * sim.timestep).sqrt(),
                 grid_width: GridWidth::new(sim.grid_size, sim.box_size),
+                species_ranges,
+                magnetic_moments: species.iter().map(|s| s.magnetic_moment).collect(),
+                particle_moments: Vec::with_capacity(sim.number_of_particles),
+                volume_per_particle,
+                polydisperse,
             },
+            species,
         }
     }
 
@@ -174,6 +510,7 @@ impl Simulation {
         // IMPORTANT: Set also the modulo quotiont for every particle, since it is not
         // provided for user given input.
         for p in &mut particles {
+            let attributes = p.attributes;
             // this makes sure, the input is sanitized
             *p = Particle::new(
                 p.position.x,
@@ -183,22 +520,93 @@ impl Simulation {
                 p.orientation.theta,
                 &bs,
             );
+            p.attributes = attributes;
+            if self.settings.simulation.dimensionality.is_planar() {
+                p.project_to_plane();
+            }
         }
 
         self.state.particles = particles;
 
-        // Do a first sampling, so that the initial condition can also be obtained
-        self.state.distribution.sample_from(&self.state.particles);
+        // use a seed different from all species and the tracers
+        let sim = self.settings.simulation;
+        if self.pcache.polydisperse {
+            let attributes = self
+                .settings
+                .parameters
+                .polydispersity
+                .sample(
+                    sim.number_of_particles,
+                    sim.seed.wrapping_add(self.species.len() as u64 + 1),
+                )
+                .expect("Cannot sample the particle attributes.");
+            for (p, a) in self.state.particles.iter_mut().zip(attributes) {
+                p.attributes = a;
+            }
+        }
+        self.update_particle_moments();
+
+        // use a seed different from all species, see `init_simulation`
+        self.state.tracers = Position::create_uniform(
+            sim.number_of_tracers,
+            &bs,
+            sim.seed.wrapping_add(self.species.len() as u64),
+        );
+        if sim.dimensionality.is_planar() {
+            for t in &mut self.state.tracers {
+                t.z = 0.;
+            }
+        }
 
-        self.state.distribution.dist *= self.settings.simulation.box_size.x
-            * self.settings.simulation.box_size.y
-            * self.settings.simulation.box_size.z;
+        // Do a first sampling, so that the initial condition can also be obtained
+        self.sample_distribution();
     }
 
     /// Resumes from a given snapshot
     pub fn resume(&mut self, snapshot: Snapshot) {
+        assert!(
+            snapshot.species.is_empty() || snapshot.species == self.species_of_particles(),
+            "Species of the particles in the snapshot do not match the species given in the \
+             parameter file."
+        );
         self.init(snapshot.particles);
 
+        // the attributes are not part of the serialized particles
+        if !snapshot.attributes.is_empty() {
+            assert!(
+                self.pcache.polydisperse && snapshot.attributes.len() == self.state.particles.len(),
+                "Attributes of the particles in the snapshot do not match the polydispersity \
+                 given in the parameter file."
+            );
+            for (p, a) in self.state.particles.iter_mut().zip(snapshot.attributes) {
+                p.attributes = a;
+            }
+            self.update_particle_moments();
+            self.sample_distribution();
+        }
+
+        // keep the freshly placed tracers for snapshots without tracers
+        if !snapshot.tracers.is_empty() {
+            assert!(
+                snapshot.tracers.len() == self.settings.simulation.number_of_tracers,
+                "Snapshot has not the same number of tracers ({}) as given in the parameter \
+                 file ({}).",
+                snapshot.tracers.len(),
+                self.settings.simulation.number_of_tracers
+            );
+            self.state.tracers = snapshot.tracers;
+
+            let rng = &mut self.state.tracer_rng;
+            for (r, s) in rng.iter_mut().zip(snapshot.tracer_rng_state) {
+                *r = s;
+            }
+        }
+
+        // keep the initial concentration for snapshots without it
+        if let (Some(s), Some(c)) = (&mut self.chemical_solver, snapshot.chemical) {
+            s.set_concentration(c);
+        }
+
         // Reset timestep
         self.state.timestep = snapshot.timestep;
         for (r, s) in self.state.rng.iter_mut().zip(snapshot.rng_state) {
@@ -213,9 +621,153 @@ impl Simulation {
             // assuming little endianess
             rng_state: self.state.rng.clone(),
             timestep: self.state.timestep,
+            species: self.species_of_particles(),
+            tracers: self.state.tracers.clone(),
+            tracer_rng_state: if self.state.tracers.is_empty() {
+                Vec::new()
+            } else {
+                self.state.tracer_rng.clone()
+            },
+            chemical: self.get_chemical(),
+            attributes: if self.pcache.polydisperse {
+                self.state.particles.iter().map(|p| p.attributes).collect()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    /// Returns the values of the scheduled parameters in the last timestep,
+    /// if there are any
+    pub fn get_scheduled_parameters(&self) -> Option<BTreeMap<String, Float>> {
+        if self.schedule.is_empty() {
+            None
+        } else {
+            Some(self.scheduled_values.clone())
+        }
+    }
+
+    /// Sets the scheduled parameters to their values at the beginning of the
+    /// current timestep.
+    fn apply_schedule(&mut self) {
+        if self.schedule.is_empty() {
+            return;
+        }
+
+        let t = self.state.timestep as Float * self.settings.simulation.timestep;
+
+        // Most schedules are constant for many timesteps. Everything, that is
+        // derived from the parameters, is only updated if a value changes.
+        let values = &self.scheduled_values;
+        if self
+            .schedule
+            .iter()
+            .all(|(p, s)| s.value_at(t) == values[p])
+        {
+            return;
+        }
+
+        self.scheduled_values = scheduled_values(&self.schedule, t);
+        let parameters = self
+            .settings
+            .parameters
+            .with_values(&self.scheduled_values)
+            .expect("Invalid scheduled parameters.");
+        self.set_parameters(parameters);
+    }
+
+    /// Replaces the parameters and updates everything, that is derived from
+    /// them in `new`. Stress kernels are only recalculated, if the stress of
+    /// a species changes.
+    fn set_parameters(&mut self, parameters: Parameters) {
+        let sim = self.settings.simulation;
+        let old = &self.settings.parameters;
+        let species = parameters.species(sim.number_of_particles);
+        let polydisperse = self.pcache.polydisperse;
+
+        let changed = |f: fn(&Species, &Species) -> bool| {
+            species.iter().zip(&self.species).any(|(a, b)| f(a, b))
+        };
+        let stress_changed = changed(|a, b| a.stress != b.stress || a.shape != b.shape);
+        let moments_changed = changed(|a, b| a.magnetic_moment != b.magnetic_moment);
+
+        if stress_changed {
+            let k = species.len();
+            for (i, s) in species.iter().enumerate() {
+                self.spectral_solver
+                    .set_species_stress(i, species_stress(*s, polydisperse));
+                if polydisperse {
+                    let (magnetic, rods, active) = weighted_stresses(*s);
+                    self.spectral_solver.set_weighted_stress(i, magnetic);
+                    self.spectral_solver.set_weighted_stress(k + i, rods);
+                    self.spectral_solver.set_weighted_stress(2 * k + i, active);
+                }
+            }
+            self.stress_meters = stress_meters(&sim, &species);
+        }
+
+        let dipole_stress = parameters.magnetic_dipole.stress;
+        let magnetic_moments: Vec<Float> = species.iter().map(|s| s.magnetic_moment).collect();
+        if (moments_changed || dipole_stress != old.magnetic_dipole.stress)
+            && (dipole_stress != 0. || old.magnetic_dipole.stress != 0.)
+        {
+            self.spectral_solver
+                .set_magnetic_dipole_stress(dipole_stress, magnetic_moments.clone());
+        }
+
+        let field = |p: &Parameters| p.external_field_map.as_ref().map(|m| m.field.clone());
+        if field(&parameters) != field(old) {
+            self.external_field = field(&parameters).map(|f| {
+                f.load(sim.box_size)
+                    .expect("Cannot load the external magnetic field.")
+            });
+        }
+
+        if parameters.gravity != old.gravity {
+            let gravity = parameters.gravity;
+            self.spectral_solver
+                .set_body_force((gravity.unit_direction() * gravity.body_force).v);
+        }
+
+        if parameters.force_regularisation != old.force_regularisation {
+            self.spectral_solver
+                .set_regularisation(parameters.force_regularisation);
+        }
+
+        self.settings.parameters = parameters;
+        self.species = species;
+        if moments_changed {
+            self.pcache.magnetic_moments = magnetic_moments;
+            self.update_particle_moments();
+        }
+    }
+
+    /// Calculates the magnetic moment of every particle for the magnetic near
+    /// field, which has to be repeated whenever the particles are reordered.
+    fn update_particle_moments(&mut self) {
+        let vpp = self.pcache.volume_per_particle;
+        let moments = &mut self.pcache.particle_moments;
+        moments.clear();
+        for (s, r) in self.species.iter().zip(&self.pcache.species_ranges) {
+            moments.extend(
+                self.state.particles[r.clone()]
+                    .iter()
+                    .map(|p| s.magnetic_moment * p.attributes.magnetic_moment * vpp),
+            );
         }
     }
 
+    /// Returns the species index of every particle.
+    fn species_of_particles(&self) -> Vec<usize> {
+        let mut species = vec![0; self.settings.simulation.number_of_particles];
+        for (i, r) in self.pcache.species_ranges.iter().enumerate() {
+            for s in &mut species[r.clone()] {
+                *s = i;
+            }
+        }
+        species
+    }
+
     // Getter
     /// Returns all particles
     pub fn get_particles(&self) -> Vec<Particle> {
@@ -227,9 +779,36 @@ impl Simulation {
         self.state.particles[..n].to_vec()
     }
 
-    /// Returns sampled distribution field
+    /// Returns the positions of all passive tracers
+    pub fn get_tracers(&self) -> Vec<Position> {
+        self.state.tracers.clone()
+    }
+
+    /// Returns the concentration of the chemical, if there is chemotaxis
+    pub fn get_chemical(&self) -> Option<Array<Float, Ix3>> {
+        self.chemical_solver
+            .as_ref()
+            .map(|s| s.get_concentration().to_owned())
+    }
+
+    /// Returns sampled distribution field of all particles
     pub fn get_distribution(&self) -> Distribution {
-        self.state.distribution.clone()
+        let (first, rest) = self.state.distributions.split_first().unwrap();
+        let mut d = first.clone();
+        for r in rest {
+            d.dist += &r.dist;
+        }
+        d
+    }
+
+    /// Returns sampled distribution field of every species
+    pub fn get_species_distributions(&self) -> Vec<Distribution> {
+        self.state.distributions.clone()
+    }
+
+    /// Returns the number of particle species
+    pub fn get_number_of_species(&self) -> usize {
+        self.species.len()
     }
 
     /// Returns sampled flow field
@@ -242,33 +821,121 @@ impl Simulation {
         self.magnetic_solver.get_real_magnet_field()
     }
 
+    /// Returns the box averaged stresses of the sampled distribution summed
+    /// over all species
+    pub fn get_bulk_stress(&self) -> BulkStress {
+        let dists = &self.state.distributions;
+        let weighted = &self.state.weighted_distributions;
+        let n = dists.len();
+        self.stress_meters
+            .iter()
+            .zip(dists)
+            .enumerate()
+            .map(|(i, (m, d))| {
+                if self.pcache.polydisperse {
+                    m.measure_weighted(&weighted[2 * n + i], &weighted[i], &weighted[n + i])
+                } else {
+                    m.measure(d)
+                }
+            })
+            .fold(BulkStress::default(), |a, b| a + b)
+    }
+
     /// Returns current timestep
     pub fn get_timestep(&self) -> usize {
         self.state.timestep
     }
 
+    /// Samples the distribution of every species from its particles. It is
+    /// renormalised by the box volume and the fraction of the species to keep
+    /// the overall number density constant. For polydisperse particles, the
+    /// distributions weighted by the magnetic moment and the shape are
+    /// sampled alike.
+    fn sample_distribution(&mut self) {
+        let bs = self.settings.simulation.box_size;
+        let n = self.settings.simulation.number_of_particles as Float;
+        let strain = self.lees_edwards_strain(self.state.timestep);
+        for (d, r) in self
+            .state
+            .distributions
+            .iter_mut()
+            .zip(&self.pcache.species_ranges)
+        {
+            d.set_strain(strain);
+            d.sample_scaled_from(
+                &self.state.particles[r.clone()],
+                bs.x * bs.y * bs.z * r.len() as Float / n,
+            );
+        }
+
+        let k = self.species.len();
+        for (i, d) in self.state.weighted_distributions.iter_mut().enumerate() {
+            let r = &self.pcache.species_ranges[i % k];
+            let weight = match i / k {
+                0 => |p: &Particle| p.attributes.magnetic_moment,
+                1 => |p: &Particle| p.attributes.shape,
+                _ => |p: &Particle| p.attributes.speed,
+            };
+            d.set_strain(strain);
+            d.sample_weighted_from(
+                &self.state.particles[r.clone()],
+                bs.x * bs.y * bs.z * r.len() as Float / n,
+                weight,
+            );
+        }
+    }
+
+    /// Returns the strain of the box at `timestep` for Lees-Edwards boundary
+    /// conditions, or zero if they are disabled.
+    fn lees_edwards_strain(&self, timestep: usize) -> Float {
+        let sim = self.settings.simulation;
+        if sim.lees_edwards {
+            self.settings
+                .parameters
+                .background_flow
+                .lees_edwards_strain(timestep as Float * sim.timestep, &sim.box_size)
+        } else {
+            0.
+        }
+    }
+
     /// Do the actual simulation timestep
     pub fn do_timestep(&mut self) -> usize {
+        self.apply_schedule();
+
+        // Sort particles periodically by grid cell to improve cache locality of
+        // the field lookups. The random samples are redrawn every timestep, so
+        // they need not to be reordered. Particles are only sorted within their
+        // species.
+        if let Some(n) = self.settings.simulation.sort_particles_every {
+            if self.state.timestep % n == 0 {
+                for r in &self.pcache.species_ranges {
+                    sort_by_cell(
+                        &mut self.state.particles[r.clone()],
+                        &self.pcache.grid_width,
+                        &self.settings.simulation.grid_size,
+                    );
+                }
+                self.update_particle_moments();
+            }
+        }
+
         // Sample probability distribution from ensemble.
-        self.state.distribution.sample_from(&self.state.particles);
-        // Renormalize distribution to keep number density constant.
-        self.state.distribution.dist *= self.settings.simulation.box_size.x
-            * self.settings.simulation.box_size.y
-            * self.settings.simulation.box_size.z;
+        self.sample_distribution();
 
         let range: rand::distributions::Uniform<Float> = Uniform::new(0., 1.);
 
         let chunksize = self.state.random_samples.len() / self.state.rng.len() + 1;
 
-        // let dt = (2.
-        //     * self.settings.parameters.diffusion.translational
-        //     * self.settings.simulation.timestep)
-        //     .sqrt();
-        let dr = (2.
-            * self.settings.parameters.diffusion.rotational
-            * self.settings.simulation.timestep)
-            .sqrt();
+        // Tumbles are only drawn, if enabled, to keep the random numbers of
+        // simulations without tumbling unchanged. Whether a particle tumbles
+        // is decided later, since the tumble rate may depend on its
+        // orientation.
+        let tumbling = self.settings.parameters.tumbling;
+        let tumble_sampler = tumbling.sampler();
 
+        // The rotation angle is drawn for unit rotational diffusion and scaled
+        // by the diffusion constant of each species.
         self.state
             .random_samples
             .par_chunks_mut(chunksize)
@@ -280,106 +947,366 @@ impl Simulation {
                         y: rng.sample::<Float, _>(StandardNormal),
                         z: rng.sample::<Float, _>(StandardNormal),
                         axis_angle: TWOPI * rng.sample(range),
-                        rotate_angle: rayleigh_pdf(dr, rng.sample(range)),
+                        rotate_angle: rayleigh_pdf(1., rng.sample(range)),
+                        tumble: if tumbling.is_enabled() {
+                            Some((
+                                rng.sample(range),
+                                RotDiff {
+                                    axis_angle: TWOPI * rng.sample(range),
+                                    rotate_angle: tumble_sampler.angle(rng.sample(range)),
+                                },
+                            ))
+                        } else {
+                            None
+                        },
                     };
                 }
             });
 
+        let chunksize = self.state.tracer_samples.len() / self.state.tracer_rng.len() + 1;
+
+        self.state
+            .tracer_samples
+            .par_chunks_mut(chunksize)
+            .zip(self.state.tracer_rng.par_iter_mut())
+            .for_each(|(c, rng)| {
+                for r in c.iter_mut() {
+                    for x in r.iter_mut() {
+                        *x = rng.sample(StandardNormal);
+                    }
+                }
+            });
+
         let sim = self.settings.simulation;
-        let param = self.settings.parameters;
+        let param = &self.settings.parameters;
         let gw = self.pcache.grid_width;
         let gs = self.settings.simulation.grid_size;
+        let planar = sim.dimensionality.is_planar();
+        let g = param.gravity.unit_direction();
+        let sedimentation_velocity = g * param.gravity.sedimentation;
+        let up = g * (-param.gravity.gyrotaxis);
+        let tumble_probability = tumbling.probability(sim.timestep);
+        let chemotaxis = param.chemotaxis;
+        let uniform_field: VectorD = [0., param.magnetic_reorientation, 0.].into();
+        let external_field = self.external_field.as_ref();
+        let gradient_drag = param
+            .external_field_map
+            .as_ref()
+            .map_or(0., |m| m.gradient_drag);
+        let grad_c = self.chemical_solver.as_ref().map(|s| s.get_gradient());
+
+        // All fields live in sheared coordinates with the strain used for
+        // sampling. Particles crossing the y boundary during this timestep are
+        // shifted by the offset of the images at the end of the timestep.
+        let strain = self.state.distributions[0].get_strain();
+        let offset = self.lees_edwards_strain(self.state.timestep + 1) * sim.box_size.y;
+        if sim.lees_edwards {
+            self.spectral_solver.set_strain(strain);
+            self.magnetic_solver.set_strain(strain);
+            if let Some(g) = &mut self.density_gradient {
+                g.set_strain(strain);
+            }
+        }
 
+        // The magnetisation of polydisperse particles is given by the
+        // distributions weighted by the magnetic moment.
+        let magnetic_dists = if self.pcache.polydisperse {
+            &self.state.weighted_distributions[..self.species.len()]
+        } else {
+            &self.state.distributions[..]
+        };
         let (b, grad_b) = self
             .magnetic_solver
-            .mean_magnetic_field(&self.state.distribution);
+            .mean_magnetic_field_of_species(magnetic_dists, &self.pcache.magnetic_moments);
+        // The torque of the mean dipole-dipole field enters the stress. With
+        // the near field, the mesh only provides its long-range part.
+        self.spectral_solver.set_magnetic_field(b, magnetic_dists);
 
         // Calculate flow field from distribution.
-        let (flow_field, grad_ff) = self
-            .spectral_solver
-            .mean_flow_field(param.hydro_screening, &self.state.distribution);
-
-        let mut grad_ff_t = grad_ff.clone();
-        // transpose
-        grad_ff_t.swap_axes(0, 1);
-        let vorticity_mat = (&grad_ff - &grad_ff_t) * 0.5;
-        let vorticity_mat = vorticity_mat.view();
-        let strain_mat = (&grad_ff + &grad_ff_t) * 0.5;
-        let strain_mat = strain_mat.view();
-
-        // let dens_grad = self.density_gradient.get_gradient(&self.state.distribution);
+        self.spectral_solver.mean_flow_field(
+            param.hydro_screening,
+            &self.state.distributions,
+            &self.state.weighted_distributions,
+        );
+        let flow_field = self.spectral_solver.get_flow_field();
+        let (strain_mat, vorticity_mat) = self.spectral_solver.get_strain_vorticity();
+
         // Calculate density
-        let dens = self.state.distribution.dist.view();
-        let sh = dens.dim();
-        let dens = dens.into_shape([sh.0, sh.1, sh.2, sh.3 * sh.4]).unwrap();
-        let dthph = self.pcache.grid_width.theta * self.pcache.grid_width.phi;
-        let dens = dens.sum_axis(Axis(3)) * dthph;
+        self.state.update_density();
+        let dens = &self.state.density;
+        let dens_grad = self
+            .density_gradient
+            .as_mut()
+            .map(|g| g.get_gradient_of_density(dens.view()));
+
+        // Steric forces and torques are calculated from the positions at the
+        // beginning of the timestep.
+        if let Some(s) = &mut self.steric {
+            s.update(&self.state.particles);
+        }
+        let steric_forces = self.steric.as_ref().map(|s| s.get_forces());
+        // The magnetic field of close neighbours is summed explicitly and
+        // added to the long-range mean field.
+        if let Some(nf) = &mut self.near_field {
+            nf.update(&self.state.particles, &self.pcache.particle_moments);
+        }
+        let near_field = self
+            .near_field
+            .as_ref()
+            .map(|nf| (nf.get_field(), nf.get_gradient()));
+
+        for (s, range) in self.species.iter().zip(&self.pcache.species_ranges) {
+            let dr = (2. * s.diffusion.rotational * sim.timestep).sqrt();
+
+            self.state.particles[range.clone()]
+                .par_iter_mut()
+                .zip(self.state.random_samples[range.clone()].par_iter())
+                .enumerate()
+                .for_each(|(i, (p, r))| {
+                    // attributes of polydisperse particles scale the
+                    // parameters of the species
+                    let m = s.magnetic_moment * p.attributes.magnetic_moment;
+                    let position = p.position.sheared(&sim.box_size, strain);
+                    let idx = get_cell_index(&position, &gw, &gs);
+                    // add imposed background flow to the self-generated flow
+                    let (bg_strain, bg_vort) = param
+                        .background_flow
+                        .strain_vorticity(&p.position, &sim.box_size);
+                    let flow = vector_field_at_cell_c(&flow_field.view(), idx)
+                        + param.background_flow.velocity(&p.position, &sim.box_size);
+                    let vortm = mat_add(&vorticity_mat[[idx.0, idx.1, idx.2]], &bg_vort);
+                    let strainm = mat_add(&strain_mat[[idx.0, idx.1, idx.2]], &bg_strain);
+
+                    // Volume exclusion either pushes particles down the
+                    // density gradient or enhances their diffusion.
+                    let (volex, volex_force) = match &dens_grad {
+                        Some(g) => (
+                            0.,
+                            vector_field_interpolated_c(g, &position, &gw)
+                                * (-param.volume_exclusion),
+                        ),
+                        None => (
+                            param.volume_exclusion * dens[[idx.0, idx.1, idx.2]],
+                            VectorD::zero(),
+                        ),
+                    };
+
+                    let (b_near, grad_b_near) = match near_field {
+                        Some((b, grad_b)) => (b[range.start + i], grad_b[range.start + i]),
+                        None => (VectorD::zero(), [[0.; 3]; 3]),
+                    };
+                    let b = (vector_field_at_cell_c(&b, idx) + b_near)
+                        * (param.magnetic_dipole.magnetic_dipole_dipole * m);
+                    let grad_b = mat_add(&vector_gradient_at_cell(&grad_b, idx), &grad_b_near);
+
+                    // The external field map adds to the uniform field in y
+                    // direction. It lives in the unsheared box.
+                    let (b_ext, grad_b_ext) = match external_field {
+                        Some(f) => f.field_at(&p.position),
+                        None => (VectorD::zero(), [[0.; 3]; 3]),
+                    };
+                    let b_ext = (b_ext + uniform_field) * m;
+
+                    // With chemotaxis, particles turn towards the gradient of
+                    // the chemical and tumble less often when swimming up the
+                    // gradient.
+                    let (grad_c, tumble_probability) = match (chemotaxis, &grad_c) {
+                        (Some(c), Some(g)) => {
+                            let grad_c = vector_field_at_cell_c(g, idx);
+                            let n = p.orientation.to_vector();
+                            (
+                                grad_c * c.alignment,
+                                tumbling.biased_probability(
+                                    sim.timestep,
+                                    c.tumble_bias * n.dot(&grad_c),
+                                ),
+                            )
+                        }
+                        _ => (VectorD::zero(), tumble_probability),
+                    };
+                    let tumbled = r.tumble.filter(|t| t.0 < tumble_probability).map(|t| t.1);
+
+                    // in the plane, the orientation diffuses by a normally
+                    // distributed angle
+                    let planar_angle = dr * r.z;
+                    // in the plane, tumbles turn clockwise or counterclockwise
+                    // depending on the random axis
+                    let planar_tumble = tumbled.map(|t| {
+                        if t.axis_angle < 0.5 * TWOPI {
+                            t.rotate_angle
+                        } else {
+                            -t.rotate_angle
+                        }
+                    });
+                    let dr = RotDiff {
+                        axis_angle: r.axis_angle,
+                        rotate_angle: dr * r.rotate_angle,
+                    };
+
+                    let diff = (2. * sim.timestep * (s.diffusion.translational + volex)).sqrt();
+                    let steric_force = steric_forces.map(|f| &f[range.start + i]);
+
+                    let m = LangevinBuilder::new(&p)
+                        .with_param(
+                            scaled_self_propulsion,
+                            s.self_propulsion * p.attributes.speed,
+                        )
+                        .with_param(convection, flow)
+                        .with_param(sedimentation, sedimentation_velocity)
+                        .with_param(
+                            magnetic_dipole_dipole_force,
+                            (param.magnetic_drag * m, &grad_b),
+                        )
+                        .with_param(external_field_force, (gradient_drag * m, &grad_b_ext))
+                        .with_param(volume_exclusion_force, volex_force)
+                        .conditional_with_param(
+                            steric_force.is_some(),
+                            steric_interaction,
+                            steric_force,
+                        )
+                        .with_param(external_field_alignment, b_ext)
+                        .with_param(magnetic_dipole_dipole_rotation, b)
+                        .with_param(gyrotaxis, up)
+                        .with_param(chemotactic_rotation, grad_c)
+                        .with_param(jeffrey_vorticity, &vortm)
+                        .with_param(jeffrey_strain, (s.shape * p.attributes.shape, &strainm))
+                        .step(&TimeStep(sim.timestep))
+                        .with_param(
+                            translational_diffusion,
+                            (
+                                if planar {
+                                    [r.x, r.y, 0.].into()
+                                } else {
+                                    [r.x, r.y, r.z].into()
+                                },
+                                diff,
+                            ),
+                        )
+                        .conditional_with_param(!planar, rotational_diffusion, Some(&dr))
+                        .conditional_with_param(
+                            planar,
+                            planar_rotational_diffusion,
+                            Some(planar_angle),
+                        )
+                        .conditional_with_param(!planar, tumble, tumbled.as_ref())
+                        .conditional_with_param(
+                            planar,
+                            planar_rotational_diffusion,
+                            planar_tumble,
+                        );
+
+                    *p = match sim.walls {
+                        Some(interaction) => m.finalize_walls(&sim.box_size, interaction),
+                        None => m.finalize_lees_edwards(&sim.box_size, offset),
+                    };
 
+                    if planar {
+                        p.project_to_plane();
+                    }
+                });
+        }
+
+        // Passive tracers only follow the flow field and diffuse.
+        let diff = (2. * sim.timestep * param.tracer_diffusion).sqrt();
         self.state
-            .particles
+            .tracers
             .par_iter_mut()
-            .zip(self.state.random_samples.par_iter())
-            .for_each(|(p, r)| {
-                let idx = get_cell_index(&p.position, &gw, &gs);
-                let flow = vector_field_at_cell_c(&flow_field.view(), idx);
-                let vortm = matrix_field_at_cell(&vorticity_mat, idx);
-                let strainm = matrix_field_at_cell(&strain_mat, idx);
-
-                // let densg = vec_to_real(interpolate_vector_field(
-                //     &p.position,
-                //     &dens_grad.view(),
-                //     &gw,
-                // )) * (-param.volume_exclusion);
-                let density = dens[[idx.0, idx.1, idx.2]];
-
-                let volex = param.volume_exclusion * density;
-
-                let b =
-                    vector_field_at_cell_c(&b, idx) * param.magnetic_dipole.magnetic_dipole_dipole;
-                let grad_b = matrix_field_at_cell(&grad_b, idx);
-
-                let dr = RotDiff {
-                    axis_angle: r.axis_angle,
-                    rotate_angle: r.rotate_angle,
-                };
+            .zip(self.state.tracer_samples.par_iter())
+            .for_each(|(t, r)| {
+                let idx = get_cell_index(&t.sheared(&sim.box_size, strain), &gw, &gs);
+                let flow = vector_field_at_cell_c(&flow_field.view(), idx)
+                    + param.background_flow.velocity(t, &sim.box_size);
 
-                let diff = (2. * sim.timestep * (param.diffusion.translational + volex)).sqrt();
-
-                *p = LangevinBuilder::new(&p)
-                    .with(self_propulsion)
+                let p = Particle::from_position_orientation(
+                    *t,
+                    Orientation::new(0., 0.),
+                    &sim.box_size,
+                );
+                let m = LangevinBuilder::new(&p)
                     .with_param(convection, flow)
-                    .with_param(
-                        magnetic_dipole_dipole_force,
-                        (param.magnetic_drag, grad_b.view()),
-                    )
-                    // .with_param(volume_exclusion_force, densg)
-                    .with_param(external_field_alignment, param.magnetic_reorientation)
-                    .with_param(magnetic_dipole_dipole_rotation, b)
-                    .with_param(jeffrey_vorticity, vortm.view())
-                    .with_param(jeffrey_strain, (param.shape, strainm.view()))
                     .step(&TimeStep(sim.timestep))
-                    .with_param(translational_diffusion, ([r.x, r.y, r.z].into(), diff))
-                    .with_param(rotational_diffusion, &dr)
-                    .finalize(&sim.box_size);
+                    .with_param(translational_diffusion, ((*r).into(), diff));
+
+                *t = match sim.walls {
+                    Some(interaction) => m.finalize_walls(&sim.box_size, interaction),
+                    None => m.finalize_lees_edwards(&sim.box_size, offset),
+                }
+                .position;
 
-                if cfg!(feature = "quasi2d") {
-                    (*p).position.z = 0.0;
-                    (*p).orientation.theta = PI / 2.;
+                if planar {
+                    t.z = 0.;
                 }
             });
 
+        // The particles produce or consume the chemical according to their
+        // density at the beginning of the timestep.
+        if let Some(s) = &mut self.chemical_solver {
+            s.step(self.state.density.view(), sim.timestep);
+        }
+
         // increment timestep counter to keep a continous identifier when resuming
         self.state.timestep += 1;
         self.state.timestep
     }
 }
 
-impl Drop for Simulation {
-    fn drop(&mut self) {
-        fft::fttw_finalize();
+/// Returns the stress of the species `s`, which is averaged with its
+/// distribution. It vanishes for polydisperse particles, because all stresses
+/// are averaged with the weighted distributions, see `weighted_stresses`.
+fn species_stress(s: Species, polydisperse: bool) -> impl Fn(Float, Float) -> Array<Float, Ix2> {
+    move |phi, theta| {
+        if polydisperse {
+            Array::zeros((3, 3))
+        } else {
+            s.stress.active * stress_active(phi, theta)
+                + s.stress.magnetic * stress_magnetic(phi, theta)
+                + s.shape * stress_magnetic_rods(phi, theta)
+        }
     }
 }
 
+/// Returns the stresses of polydisperse particles of the species `s`, which
+/// are averaged with the distributions weighted by the magnetic moment, the
+/// shape and the speed of the particles, respectively. The force dipole of a
+/// swimmer, and hence its active stress, is proportional to its speed.
+fn weighted_stresses(
+    s: Species,
+) -> (
+    impl Fn(Float, Float) -> Array<Float, Ix2>,
+    impl Fn(Float, Float) -> Array<Float, Ix2>,
+    impl Fn(Float, Float) -> Array<Float, Ix2>,
+) {
+    (
+        move |phi, theta| s.stress.magnetic * stress_magnetic(phi, theta),
+        move |phi, theta| s.shape * stress_magnetic_rods(phi, theta),
+        move |phi, theta| s.stress.active * stress_active(phi, theta),
+    )
+}
+
+/// Returns a stress meter for every species.
+fn stress_meters(sim: &SimulationSettings, species: &[Species]) -> Vec<BulkStressMeter> {
+    species
+        .iter()
+        .map(|s| {
+            BulkStressMeter::new(
+                sim.grid_size,
+                GridWidth::new(sim.grid_size, sim.box_size),
+                sim.orientation,
+                s.stress,
+                s.shape,
+            )
+        })
+        .collect()
+}
+
+/// Returns the values of all scheduled parameters at time `t`.
+fn scheduled_values(schedule: &[(String, Schedule)], t: Float) -> BTreeMap<String, Float> {
+    schedule
+        .iter()
+        .map(|(p, s)| (p.clone(), s.value_at(t)))
+        .collect()
+}
+
 fn rayleigh_pdf(sigma: Float, x: Float) -> Float {
     sigma * Float::sqrt(-2. * Float::ln(1. - x))
 }
@@ -406,9 +1333,12 @@ fn vector_field_at_cell_c(
     f.into()
 }
 
-fn matrix_field_at_cell(
-    field: &ArrayView<Complex<Float>, Ix5>,
-    idx: (usize, usize, usize),
-) -> Array<Float, Ix2> {
-    field.slice(s![.., .., idx.0, idx.1, idx.2]).map(|v| v.re)
+/// Interpolates the real part of the vector field `field` at `position`.
+fn vector_field_interpolated_c(
+    field: &ArrayView<Complex<Float>, Ix4>,
+    position: &Position,
+    gw: &GridWidth,
+) -> VectorD {
+    let v = interpolate_vector_field(position, field, gw);
+    [v[0].re, v[1].re, v[2].re].into()
 }
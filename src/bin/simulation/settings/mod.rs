@@ -3,11 +3,26 @@
 pub mod si;
 
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::prelude::*;
+use stochasticsampling::chemical_field::ChemicalParameters;
+use stochasticsampling::distribution::OrientationRepresentation;
+use stochasticsampling::flowfield::background::BackgroundFlow;
+use stochasticsampling::flowfield::regularisation::ForceRegularisation;
+use stochasticsampling::flowfield::screening::HydroScreening;
 use stochasticsampling::flowfield::stress::StressPrefactors;
+use stochasticsampling::integrators::tumbling::{TumbleAngle, Tumbling};
+use stochasticsampling::magnetic_interaction::external_field::ExternalField;
+use stochasticsampling::magnetic_interaction::near_field::NearFieldParameters;
+use stochasticsampling::mesh::fft_helper::SpectralFilter;
+use stochasticsampling::particle::WallInteraction;
+use stochasticsampling::polydispersity::Polydispersity;
+use stochasticsampling::schedule::Schedule;
+use stochasticsampling::steric::StericParameters;
+use stochasticsampling::vector::VectorD;
 use stochasticsampling::Float;
-use stochasticsampling::{BoxSize, GridSize};
+use stochasticsampling::{BoxSize, Dimensionality, GridSize};
 use toml;
 
 const DEFAULT_IO_QUEUE_SIZE: usize = 1;
@@ -43,10 +58,29 @@ pub struct DiffusionConstants {
 pub struct MagneticDipolePrefactors {
     #[serde(default)]
     pub magnetic_dipole_dipole: Float,
+    /// Prefactor of the antisymmetric stress due to the torque of the mean
+    /// dipole-dipole field on the particles. Zero switches it off.
+    #[serde(default)]
+    pub stress: Float,
+    /// Splits the dipole-dipole interaction into the mean field on the mesh
+    /// for the long range and an explicit sum over neighbours for the short
+    /// range. Without it, only the mean field is used.
+    #[serde(default)]
+    pub near_field: Option<NearFieldParameters>,
 }
 
+/// Parameters, that are fixed after the initialisation of the simulation and
+/// cannot be scheduled.
+const UNSCHEDULABLE_PARAMETERS: [&str; 5] = [
+    "steric",
+    "magnetic_dipole.near_field",
+    "chemotaxis.field",
+    "polydispersity",
+    "schedule",
+];
+
 /// Holds phyiscal parameters
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Parameters {
     #[serde(default)]
@@ -54,16 +88,334 @@ pub struct Parameters {
     #[serde(default)]
     pub shape: Float,
     #[serde(default)]
-    pub hydro_screening: Float,
-    #[serde(default)]
     pub volume_exclusion: Float,
+    /// Model of the volume exclusion with strength `volume_exclusion`
+    #[serde(default)]
+    pub volume_exclusion_model: VolumeExclusionModel,
     /// Assumes that b points in y-direction
     pub magnetic_reorientation: Float,
+    /// Translational diffusion constant of the passive tracers
+    #[serde(default)]
+    pub tracer_diffusion: Float,
     pub diffusion: DiffusionConstants,
     pub stress: StressPrefactors,
     /// Magnetic moment of one particle including magnetic field constant
     /// `\mu_0` WARNING: at the moment independend variable
     pub magnetic_dipole: MagneticDipolePrefactors,
+    /// External magnetic field, that varies in space, in addition to the
+    /// uniform field of `magnetic_reorientation`
+    #[serde(default)]
+    pub external_field_map: Option<ExternalFieldMap>,
+    /// Imposed external flow
+    #[serde(default)]
+    pub background_flow: BackgroundFlow,
+    /// Screening of the hydrodynamic interactions, e.g. by a porous medium
+    #[serde(default)]
+    pub hydro_screening: HydroScreening,
+    /// Finite size of the force distribution of a particle, which replaces
+    /// the grid width as cutoff of the flow at the particle
+    #[serde(default)]
+    pub force_regularisation: ForceRegularisation,
+    /// Run-and-tumble dynamics in addition to rotational diffusion
+    #[serde(default)]
+    pub tumbling: Tumbling,
+    /// Sedimentation and gyrotaxis of bottom-heavy particles
+    #[serde(default)]
+    pub gravity: Gravity,
+    /// Chemotaxis in a chemical field, that is produced or consumed by the
+    /// particles
+    #[serde(default)]
+    pub chemotaxis: Option<Chemotaxis>,
+    /// Short-range pairwise steric interactions of the particles
+    #[serde(default)]
+    pub steric: Option<StericParameters>,
+    /// Distributions of the speed, shape and magnetic moment of the particles
+    /// relative to the parameters of their species
+    #[serde(default)]
+    pub polydispersity: Polydispersity,
+    /// Time-dependent values of scalar parameters, which are given by their
+    /// path, e.g. `"stress.active"` or `"species.0.self_propulsion"`. The
+    /// values above are replaced by the schedule at every timestep.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub schedule: BTreeMap<String, Schedule>,
+    /// Particle species of a mixture. Without any species, all particles
+    /// belong to a single species with the parameters above.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub species: Vec<SpeciesParameters>,
+}
+
+/// Mean-field models of the steric repulsion between particles
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum VolumeExclusionModel {
+    /// Increases the translational diffusion by `volume_exclusion * density`
+    #[default]
+    Diffusive,
+    /// Pushes particles down the density gradient with the force
+    /// `-volume_exclusion * grad(density)`
+    Force,
+}
+
+/// Gravity acting on bottom-heavy particles
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Gravity {
+    /// Sedimentation velocity
+    #[serde(default)]
+    pub sedimentation: Float,
+    /// Rate of reorientation against gravity, i.e. `1 / 2B` with the
+    /// gyrotactic reorientation time `B`
+    #[serde(default)]
+    pub gyrotaxis: Float,
+    /// Weight of a particle, that pushes on the fluid. It is given relative to
+    /// the viscosity, swimming speed and mean distance of the particles.
+    #[serde(default)]
+    pub body_force: Float,
+    /// Direction of gravity, does not need to be normalised
+    #[serde(default = "default_gravity_direction")]
+    pub direction: [Float; 3],
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Gravity {
+            sedimentation: 0.,
+            gyrotaxis: 0.,
+            body_force: 0.,
+            direction: default_gravity_direction(),
+        }
+    }
+}
+
+fn default_gravity_direction() -> [Float; 3] {
+    [0., 0., -1.]
+}
+
+impl Gravity {
+    /// Returns the normalised direction of gravity.
+    pub fn unit_direction(&self) -> VectorD {
+        let d: VectorD = self.direction.into();
+        d * (1. / d.dot(&d).sqrt())
+    }
+}
+
+/// Spatially varying external magnetic field, which rotates the particles
+/// like `magnetic_reorientation` and pulls them along its gradient. Unlike
+/// the uniform field, it does not enter the magnetic stress.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalFieldMap {
+    /// Ratio of the rotational to the translational friction coefficient of
+    /// a particle in units of the squared mean distance `l` of the
+    /// particles, i.e. `4/3 (a / l)^2` for spheres with radius `a`. It
+    /// converts the gradient of the field into the velocity due to the force
+    /// `grad(m . B)`.
+    #[serde(default)]
+    pub gradient_drag: Float,
+    // tables need to come after values for the TOML serialization
+    pub field: ExternalField,
+}
+
+/// Coupling of the particles to a chemical concentration field `c`
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Chemotaxis {
+    /// Rate of rotation towards `grad c` per unit gradient
+    #[serde(default)]
+    pub alignment: Float,
+    /// Relative reduction of the tumble rate per unit gradient along the
+    /// orientation, see `Tumbling::biased_probability`
+    #[serde(default)]
+    pub tumble_bias: Float,
+    // tables need to come after values for the TOML serialization
+    pub field: ChemicalParameters,
+}
+
+/// Parameters of one particle species. Unset parameters are taken from
+/// `Parameters`.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpeciesParameters {
+    pub number_of_particles: usize,
+    /// Swimming speed relative to the unit of velocity, defaults to one
+    #[serde(default)]
+    pub self_propulsion: Option<Float>,
+    /// Magnetic moment relative to `magnetic_dipole`, defaults to one. It
+    /// scales the alignment with the external field, the magnetic drag and
+    /// the contribution to the magnetic field.
+    #[serde(default)]
+    pub magnetic_moment: Option<Float>,
+    #[serde(default)]
+    pub shape: Option<Float>,
+    // tables need to come after values for the TOML serialization
+    #[serde(default)]
+    pub diffusion: Option<DiffusionConstants>,
+    #[serde(default)]
+    pub stress: Option<StressPrefactors>,
+}
+
+/// Parameters of one particle species, where unset parameters are resolved.
+#[derive(Debug, Copy, Clone)]
+pub struct Species {
+    pub number_of_particles: usize,
+    pub self_propulsion: Float,
+    pub magnetic_moment: Float,
+    pub shape: Float,
+    pub diffusion: DiffusionConstants,
+    pub stress: StressPrefactors,
+}
+
+impl Parameters {
+    /// Returns all particle species of the simulation. The particles of
+    /// every species are stored in one contiguous block in the given order.
+    pub fn species(&self, number_of_particles: usize) -> Vec<Species> {
+        let global = SpeciesParameters {
+            number_of_particles,
+            self_propulsion: None,
+            magnetic_moment: None,
+            shape: None,
+            diffusion: None,
+            stress: None,
+        };
+        let species = if self.species.is_empty() {
+            vec![global]
+        } else {
+            self.species.clone()
+        };
+
+        species
+            .iter()
+            .map(|s| Species {
+                number_of_particles: s.number_of_particles,
+                self_propulsion: s.self_propulsion.unwrap_or(1.),
+                magnetic_moment: s.magnetic_moment.unwrap_or(1.),
+                shape: s.shape.unwrap_or(self.shape),
+                diffusion: s.diffusion.unwrap_or(self.diffusion),
+                stress: s.stress.unwrap_or(self.stress),
+            })
+            .collect()
+    }
+
+    /// Returns the parameters, where the scalar parameters given by their
+    /// paths are set to `values`, e.g. the values of the schedule at some
+    /// time. Fails, if a path does not refer to a scalar parameter.
+    pub fn with_values(&self, values: &BTreeMap<String, Float>) -> Result<Parameters> {
+        let mut parameters = self.clone();
+
+        for (path, &v) in values {
+            *parameters
+                .scalar_mut(path)
+                .ok_or_else(|| format!("'{}' is not a scalar parameter.", path))? = v;
+        }
+
+        Ok(parameters)
+    }
+
+    /// Returns the scalar parameter given by its path, e.g. `"stress.active"`
+    /// or `"gravity.direction.1"`. Unset optional parameters of a species are
+    /// inserted with the values, that the species would use otherwise.
+    fn scalar_mut(&mut self, path: &str) -> Option<&mut Float> {
+        let keys: Vec<&str> = path.split('.').collect();
+        let (shape, diffusion, stress) = (self.shape, self.diffusion, self.stress);
+
+        match keys.as_slice() {
+            ["magnetic_drag"] => Some(&mut self.magnetic_drag),
+            ["shape"] => Some(&mut self.shape),
+            ["volume_exclusion"] => Some(&mut self.volume_exclusion),
+            ["magnetic_reorientation"] => Some(&mut self.magnetic_reorientation),
+            ["tracer_diffusion"] => Some(&mut self.tracer_diffusion),
+            ["diffusion", key] => diffusion_mut(&mut self.diffusion, key),
+            ["stress", key] => stress_mut(&mut self.stress, key),
+            ["magnetic_dipole", "magnetic_dipole_dipole"] => {
+                Some(&mut self.magnetic_dipole.magnetic_dipole_dipole)
+            }
+            ["magnetic_dipole", "stress"] => Some(&mut self.magnetic_dipole.stress),
+            ["external_field_map", "gradient_drag"] => {
+                Some(&mut self.external_field_map.as_mut()?.gradient_drag)
+            }
+            ["external_field_map", "field", keys @ ..] => {
+                match (&mut self.external_field_map.as_mut()?.field, keys) {
+                    (ExternalField::Linear { field, .. }, ["field", i]) => element(field, i),
+                    (ExternalField::Linear { gradient, .. }, ["gradient", i, j]) => {
+                        element(gradient.get_mut(i.parse::<usize>().ok()?)?, j)
+                    }
+                    (ExternalField::Linear { center, .. }, ["center", i])
+                    | (ExternalField::Quadrupole { center, .. }, ["center", i]) => {
+                        element(center, i)
+                    }
+                    (ExternalField::Quadrupole { gradient, .. }, ["gradient"]) => Some(gradient),
+                    (ExternalField::Quadrupole { axis, .. }, ["axis", i]) => element(axis, i),
+                    _ => None,
+                }
+            }
+            ["background_flow", keys @ ..] => match (&mut self.background_flow, keys) {
+                (BackgroundFlow::SimpleShear { rate }, ["rate"])
+                | (BackgroundFlow::PlanarExtension { rate }, ["rate"]) => Some(rate),
+                (BackgroundFlow::Poiseuille { max_velocity }, ["max_velocity"]) => {
+                    Some(max_velocity)
+                }
+                (BackgroundFlow::FourierMode { amplitude, .. }, ["amplitude", i]) => {
+                    element(amplitude, i)
+                }
+                _ => None,
+            },
+            ["hydro_screening", key] => match (&mut self.hydro_screening, *key) {
+                (HydroScreening::Brinkman { length }, "length") => Some(length),
+                (HydroScreening::Legacy { screening }, "screening") => Some(screening),
+                _ => None,
+            },
+            ["force_regularisation", key] => match (&mut self.force_regularisation, *key) {
+                (ForceRegularisation::Gaussian { width }, "width") => Some(width),
+                (ForceRegularisation::RotnePrager { radius }, "radius") => Some(radius),
+                _ => None,
+            },
+            ["tumbling", "rate"] => Some(&mut self.tumbling.rate),
+            ["tumbling", "angle", "mean_cosine"] => match &mut self.tumbling.angle {
+                TumbleAngle::VonMisesFisher { mean_cosine } => Some(mean_cosine),
+                TumbleAngle::Uniform => None,
+            },
+            ["gravity", "sedimentation"] => Some(&mut self.gravity.sedimentation),
+            ["gravity", "gyrotaxis"] => Some(&mut self.gravity.gyrotaxis),
+            ["gravity", "body_force"] => Some(&mut self.gravity.body_force),
+            ["gravity", "direction", i] => element(&mut self.gravity.direction, i),
+            ["chemotaxis", "alignment"] => Some(&mut self.chemotaxis.as_mut()?.alignment),
+            ["chemotaxis", "tumble_bias"] => Some(&mut self.chemotaxis.as_mut()?.tumble_bias),
+            ["species", i, keys @ ..] => {
+                let s = self.species.get_mut(i.parse::<usize>().ok()?)?;
+                match keys {
+                    ["self_propulsion"] => Some(s.self_propulsion.get_or_insert(1.)),
+                    ["magnetic_moment"] => Some(s.magnetic_moment.get_or_insert(1.)),
+                    ["shape"] => Some(s.shape.get_or_insert(shape)),
+                    ["diffusion", key] => diffusion_mut(s.diffusion.get_or_insert(diffusion), key),
+                    ["stress", key] => stress_mut(s.stress.get_or_insert(stress), key),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the element of `array` with the index `key`.
+fn element<'a>(array: &'a mut [Float], key: &str) -> Option<&'a mut Float> {
+    array.get_mut(key.parse::<usize>().ok()?)
+}
+
+/// Returns the diffusion constant `key` of `diffusion`.
+fn diffusion_mut<'a>(diffusion: &'a mut DiffusionConstants, key: &str) -> Option<&'a mut Float> {
+    match key {
+        "translational" => Some(&mut diffusion.translational),
+        "rotational" => Some(&mut diffusion.rotational),
+        _ => None,
+    }
+}
+
+/// Returns the stress prefactor `key` of `stress`.
+fn stress_mut<'a>(stress: &'a mut StressPrefactors, key: &str) -> Option<&'a mut Float> {
+    match key {
+        "active" => Some(&mut stress.active),
+        "magnetic" => Some(&mut stress.magnetic),
+        _ => None,
+    }
 }
 
 /// Holds output configuration
@@ -86,6 +438,19 @@ pub struct Output {
     pub particles: Option<usize>,
     #[serde(default)]
     pub snapshot: Option<usize>,
+    /// Box averaged active, magnetic and rod stress
+    #[serde(default)]
+    pub stress: Option<usize>,
+    /// Positions of the passive tracers
+    #[serde(default)]
+    pub tracers: Option<usize>,
+    /// Concentration field of the chemical
+    #[serde(default)]
+    pub chemical: Option<usize>,
+    /// Values of the scheduled parameters, which are also written together
+    /// with every other output
+    #[serde(default)]
+    pub parameters: Option<usize>,
 }
 
 fn default_final_snapshot() -> bool {
@@ -112,9 +477,43 @@ pub struct SimulationSettings {
     #[serde(default = "default_init_distribution")]
     pub init_distribution: InitDistribution,
     pub seed: u64,
+    /// Sort particles by grid cell every given number of timesteps. This
+    /// changes the order of the particles, so `particles_head` does not
+    /// follow the same particles over time.
+    #[serde(default)]
+    pub sort_particles_every: Option<usize>,
+    /// Use Lees-Edwards boundary conditions in y direction, that are
+    /// consistent with the imposed simple shear flow. All output fields are
+    /// given in sheared coordinates `(x - strain * (y - L_y / 2), y, z)`.
+    #[serde(default)]
+    pub lees_edwards: bool,
+    /// Confine the particles by two planar no-slip walls at `z = 0` and `z =
+    /// L_z` instead of periodic boundaries in z direction. The magnetic field
+    /// remains periodic in z.
+    #[serde(default)]
+    pub walls: Option<WallInteraction>,
+    /// Dimensionality of the system. In the planar modes, particles are
+    /// confined to `z = 0` and oriented in the plane, which requires a single
+    /// grid cell in z and theta direction. For a quasi 2D film, `box_size.z`
+    /// is the thickness of the film.
+    #[serde(default)]
+    pub dimensionality: Dimensionality,
+    /// Number of passive tracers, that are advected by the flow field without
+    /// contributing to it or to the magnetic field.
+    #[serde(default)]
+    pub number_of_tracers: usize,
+    // tables need to come after values for the TOML serialization
     pub output_at_timestep: Output,
     pub box_size: BoxSize,
     pub grid_size: GridSize,
+    /// Representation of the orientational part of the distribution
+    #[serde(default)]
+    pub orientation: OrientationRepresentation,
+    /// Spectral filter of the flow field, the magnetic field and the density
+    /// gradient against aliasing and sampling noise. It is not supported in
+    /// combination with walls.
+    #[serde(default)]
+    pub spectral_filter: SpectralFilter,
 }
 
 /// Default init type
@@ -191,6 +590,220 @@ fn check_settings(s: &Settings) -> Result<()> {
         bail!("Box size is invalid. Must be bigger than 0: {:?}", bs)
     }
 
+    if let OrientationRepresentation::SphericalHarmonics { degree } = s.simulation.orientation {
+        if degree < 2 {
+            bail!(
+                "Spherical harmonics of degree {} cannot represent the active stress. Degree \
+                 must be at least 2.",
+                degree
+            )
+        }
+    }
+
+    if !s.simulation.spectral_filter.is_valid() {
+        bail!(
+            "Spectral filter {:?} is invalid. The order of the exponential filter must be \
+             positive and even, the width and cutoff of the Gaussian positive.",
+            s.simulation.spectral_filter
+        )
+    }
+
+    if s.simulation.spectral_filter != SpectralFilter::None && s.simulation.walls.is_some() {
+        bail!("The spectral filter is not supported in combination with walls.")
+    }
+
+    if s.parameters.volume_exclusion_model == VolumeExclusionModel::Force
+        && s.simulation.walls.is_some()
+    {
+        bail!("The volume exclusion force is not supported in combination with walls.")
+    }
+
+    if let Some(c) = s.parameters.chemotaxis {
+        if c.field.diffusion < 0. || c.field.decay < 0. {
+            bail!(
+                "Diffusion constant and decay rate of the chemical must not be negative: {:?}",
+                c.field
+            )
+        }
+
+        if s.simulation.lees_edwards {
+            bail!("Chemotaxis cannot be combined with Lees-Edwards boundary conditions.")
+        }
+
+        // the chemical is solved spectrally with periodic boundary conditions
+        if s.simulation.walls.is_some() {
+            bail!("Chemotaxis is not supported in combination with walls.")
+        }
+    }
+
+    if let Some(p) = s.parameters.steric {
+        if !p.is_valid() {
+            bail!(
+                "The steric interaction needs a positive diameter and a non-negative strength: {:?}",
+                p
+            )
+        }
+
+        if s.simulation.lees_edwards {
+            bail!("Steric interactions cannot be combined with Lees-Edwards boundary conditions.")
+        }
+
+        // the nearest image must be unique
+        let bs = s.simulation.box_size;
+        if 2. * p.cutoff() > bs.x.min(bs.y)
+            || (!s.simulation.dimensionality.is_planar() && 2. * p.cutoff() > bs.z)
+        {
+            bail!("The cutoff of the steric interaction must be smaller than half of the box size.")
+        }
+    }
+
+    let polydispersity = &s.parameters.polydispersity;
+    if !polydispersity.is_valid() {
+        bail!(
+            "Distributions of the particle attributes must not be negative: {:?}",
+            polydispersity
+        )
+    }
+    if let Err(e) = polydispersity.check_files(s.simulation.number_of_particles) {
+        bail!("Cannot read the particle attributes: {}", e)
+    }
+
+    let mut schedules = Vec::new();
+    for (path, schedule) in &s.parameters.schedule {
+        if UNSCHEDULABLE_PARAMETERS
+            .iter()
+            .any(|p| path == p || path.starts_with(&format!("{}.", p)))
+        {
+            bail!("Parameter '{}' cannot change during the simulation.", path)
+        }
+        let schedule = schedule
+            .load()
+            .chain_err(|| format!("Unable to load the schedule of '{}'.", path))?;
+        if !schedule.is_valid() {
+            bail!("Invalid schedule of '{}': {:?}", path, schedule)
+        }
+        schedules.push((path.clone(), schedule));
+    }
+
+    if schedules.is_empty() {
+        check_parameters(&s.parameters, &s.simulation)?;
+    }
+
+    // The scheduled parameters have to be valid at all times, so they are
+    // checked at the times, where any of the schedules is extremal.
+    let mut times: Vec<Float> = schedules
+        .iter()
+        .flat_map(|(_, schedule)| schedule.extreme_times())
+        .collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    times.dedup();
+    for t in times {
+        let values = schedules
+            .iter()
+            .map(|(path, schedule)| (path.clone(), schedule.value_at(t)))
+            .collect();
+        let parameters = s.parameters.with_values(&values)?;
+        check_parameters(&parameters, &s.simulation)
+            .chain_err(|| format!("Invalid scheduled parameters at time {}.", t))?;
+    }
+
+    if let Some(m) = &s.parameters.external_field_map {
+        m.field
+            .load(s.simulation.box_size)
+            .chain_err(|| "Unable to load the external magnetic field.")?;
+    }
+
+    if let Some(p) = s.parameters.magnetic_dipole.near_field {
+        if p.splitting <= 0. || p.cutoff <= 0. {
+            bail!(
+                "Splitting and cutoff of the magnetic near field must be positive: {:?}",
+                p
+            )
+        }
+
+        if s.simulation.lees_edwards {
+            bail!(
+                "The magnetic near field cannot be combined with Lees-Edwards boundary conditions."
+            )
+        }
+
+        let bs = s.simulation.box_size;
+        if 2. * p.cutoff > bs.x.min(bs.y)
+            || (!s.simulation.dimensionality.is_planar() && 2. * p.cutoff > bs.z)
+        {
+            bail!(
+                "The cutoff of the magnetic near field must be smaller than half of the box size."
+            )
+        }
+    }
+
+    if s.simulation.output_at_timestep.chemical.is_some() && s.parameters.chemotaxis.is_none() {
+        bail!("Cannot output the chemical field without chemotaxis.")
+    }
+
+    if s.simulation.lees_edwards
+        && !matches!(
+            s.parameters.background_flow,
+            BackgroundFlow::SimpleShear { .. }
+        )
+    {
+        bail!("Lees-Edwards boundary conditions require a simple shear background flow.")
+    }
+
+    if s.simulation.lees_edwards && s.simulation.walls.is_some() {
+        bail!("Lees-Edwards boundary conditions cannot be combined with walls.")
+    }
+
+    if s.simulation.dimensionality.is_planar()
+        && (s.simulation.grid_size.z != 1 || s.simulation.grid_size.theta != 1)
+    {
+        bail!(
+            "{:?} requires a single grid cell in z and theta direction.",
+            s.simulation.dimensionality
+        )
+    }
+
+    if s.simulation.dimensionality.is_planar() && s.simulation.walls.is_some() {
+        bail!("Walls require a three dimensional system.")
+    }
+
+    // the channel is bounded in z direction, which is projected out in planar
+    // systems
+    if s.simulation.dimensionality.is_planar()
+        && matches!(
+            s.parameters.background_flow,
+            BackgroundFlow::Poiseuille { .. }
+        )
+    {
+        bail!("Poiseuille flow requires a three dimensional system.")
+    }
+
+    if !s.parameters.species.is_empty()
+        && s.parameters
+            .species
+            .iter()
+            .map(|s| s.number_of_particles)
+            .sum::<usize>()
+            != s.simulation.number_of_particles
+    {
+        bail!(
+            "The number of particles of all species must add up to `number_of_particles` ({}).",
+            s.simulation.number_of_particles
+        )
+    }
+
+    if s.simulation.output_at_timestep.parameters.is_some() && s.parameters.schedule.is_empty() {
+        bail!("Cannot output the scheduled parameters without a schedule.")
+    }
+
+    if s.simulation.output_at_timestep.tracers.is_some() && s.simulation.number_of_tracers == 0 {
+        bail!("Cannot output tracers. Set `number_of_tracers` to a positive number.")
+    }
+
+    if s.simulation.sort_particles_every == Some(0) {
+        bail!("Particles cannot be sorted every 0 timesteps. Use a positive interval.")
+    }
+
     if s.simulation.output_at_timestep.particles_head.is_some()
         && s.simulation.number_of_particles
             < s.simulation.output_at_timestep.particles_head.unwrap()
@@ -204,11 +817,102 @@ fn check_settings(s: &Settings) -> Result<()> {
     Ok(())
 }
 
+/// Checks the parameters, that can change during the simulation.
+fn check_parameters(p: &Parameters, sim: &SimulationSettings) -> Result<()> {
+    if !p.background_flow.is_incompressible(&sim.box_size) {
+        bail!(
+            "Background flow {:?} is compressible. The amplitude of a Fourier mode must be \
+             perpendicular to its wave vector.",
+            p.background_flow
+        )
+    }
+
+    let screening = p.hydro_screening;
+    if !screening.is_valid() {
+        bail!(
+            "The hydrodynamic screening length must be positive: {:?}",
+            screening
+        )
+    }
+
+    if screening.is_brinkman()
+        && (sim.walls.is_some() || sim.dimensionality == Dimensionality::QuasiTwoD)
+    {
+        bail!("Brinkman screening is not supported for walls or quasi-2D films.")
+    }
+
+    if !p.force_regularisation.is_valid() {
+        bail!(
+            "The size of the force distribution must be positive: {:?}",
+            p.force_regularisation
+        )
+    }
+
+    let species = p.species(sim.number_of_particles);
+    if p.tracer_diffusion < 0.
+        || species
+            .iter()
+            .any(|s| s.diffusion.translational < 0. || s.diffusion.rotational < 0.)
+    {
+        bail!("Diffusion constants must not be negative.")
+    }
+
+    if p.tumbling.rate < 0. || !p.tumbling.angle.is_valid() {
+        bail!(
+            "Invalid tumbling {:?}. The rate must not be negative and the mean cosine of the \
+             tumble angle must lie in (-1, 1).",
+            p.tumbling
+        )
+    }
+
+    if p.gravity.direction.iter().all(|&d| d == 0.) {
+        bail!("The direction of gravity must not be zero.")
+    }
+
+    if p.gravity.body_force != 0. && sim.walls.is_some() {
+        bail!("The body force of the particles is not supported in combination with walls.")
+    }
+
+    if let Some(m) = &p.external_field_map {
+        if !m.field.is_valid() {
+            bail!(
+                "The axis of the quadrupole field must not vanish: {:?}",
+                m.field
+            )
+        }
+    }
+
+    Ok(())
+}
+
 impl Settings {
     pub fn set_version(&mut self, version: &str) {
         // save version to metadata
         self.environment.version = version.to_string();
     }
+
+    /// Returns the settings of a convergence study, where every spatial
+    /// direction with more than one grid cell is refined by `factor`. The
+    /// refined grid size is appended to the output prefix.
+    pub fn with_refined_grid(&self, factor: usize) -> Result<Settings> {
+        if factor == 0 {
+            bail!("The refinement factor of the grid must be positive.")
+        }
+
+        let mut settings = self.clone();
+        let refine = |n: usize| if n > 1 { n * factor } else { n };
+        let gs = &mut settings.simulation.grid_size;
+        gs.x = refine(gs.x);
+        gs.y = refine(gs.y);
+        gs.z = refine(gs.z);
+
+        settings.environment.prefix =
+            format!("{}_grid{}x{}x{}", self.environment.prefix, gs.x, gs.y, gs.z);
+
+        check_settings(&settings)?;
+        Ok(settings)
+    }
+
     /// Saves `Settings` to TOML file
     pub fn save_to_file(&self, filename: &str) -> Result<()> {
         let mut f = File::create(filename)
@@ -228,6 +932,7 @@ impl Settings {
 #[allow(clippy::float_cmp)]
 mod tests {
     use super::*;
+    use stochasticsampling::polydispersity::AttributeDistribution;
 
     #[test]
     fn read_settings() {
@@ -267,14 +972,41 @@ mod tests {
                 .magnetic_dipole_dipole,
             0.0
         );
+        assert_eq!(settings_default.parameters.magnetic_dipole.stress, 0.0);
         assert_eq!(settings.parameters.magnetic_reorientation, 1.0);
         assert_eq!(settings.parameters.magnetic_drag, 123.4);
         assert_eq!(settings_default.parameters.magnetic_drag, 0.0);
         assert_eq!(settings.parameters.shape, 44.3);
-        assert_eq!(settings_default.parameters.hydro_screening, 0.0);
-        assert_eq!(settings.parameters.hydro_screening, 1.3);
+        assert_eq!(
+            settings_default.parameters.hydro_screening,
+            HydroScreening::None
+        );
+        assert_eq!(
+            settings.parameters.hydro_screening,
+            HydroScreening::Legacy { screening: 1.3 }
+        );
+        assert_eq!(
+            settings_default.parameters.background_flow,
+            BackgroundFlow::None
+        );
+        assert!(!settings_default.parameters.tumbling.is_enabled());
+        assert_eq!(settings_default.parameters.gravity, Gravity::default());
+        assert_eq!(settings_default.parameters.chemotaxis, None);
+        assert_eq!(settings_default.parameters.steric, None);
         assert_eq!(settings_default.parameters.volume_exclusion, 0.0);
         assert_eq!(settings.parameters.volume_exclusion, 265.6);
+        assert_eq!(
+            settings_default.parameters.volume_exclusion_model,
+            VolumeExclusionModel::Diffusive
+        );
+
+        assert!(settings_default.parameters.species.is_empty());
+        let species = settings_default.parameters.species(10);
+        assert_eq!(species.len(), 1);
+        assert_eq!(species[0].number_of_particles, 10);
+        assert_eq!(species[0].self_propulsion, 1.);
+        assert_eq!(species[0].magnetic_moment, 1.);
+
         assert_eq!(
             settings.simulation.box_size,
             BoxSize {
@@ -301,6 +1033,17 @@ mod tests {
             settings_default.simulation.init_distribution,
             InitDistribution::Isotropic
         );
+        assert_eq!(
+            settings_default.simulation.orientation,
+            OrientationRepresentation::UniformGrid
+        );
+        assert_eq!(settings_default.simulation.sort_particles_every, None);
+        assert_eq!(settings_default.simulation.lees_edwards, false);
+        assert_eq!(settings_default.simulation.walls, None);
+        assert_eq!(
+            settings_default.simulation.dimensionality,
+            Dimensionality::ThreeD
+        );
         assert_eq!(settings.simulation.number_of_particles, 100);
         assert_eq!(settings.simulation.number_of_timesteps, 500);
         assert_eq!(settings.simulation.timestep, 0.1);
@@ -374,6 +1117,645 @@ mod tests {
             settings_default.simulation.output_at_timestep.snapshot,
             None
         );
+
+        assert_eq!(settings_default.simulation.output_at_timestep.stress, None);
+        assert_eq!(settings_default.simulation.output_at_timestep.tracers, None);
+        assert_eq!(
+            settings_default.simulation.output_at_timestep.chemical,
+            None
+        );
+        assert_eq!(
+            settings_default.simulation.output_at_timestep.parameters,
+            None
+        );
+        assert_eq!(settings_default.simulation.number_of_tracers, 0);
+        assert_eq!(settings_default.parameters.tracer_diffusion, 0.0);
+    }
+
+    #[test]
+    fn read_feature_settings() {
+        let settings = read_parameter_file("./test/parameter_features.toml").unwrap();
+
+        assert_eq!(settings.parameters.magnetic_dipole.stress, 0.7);
+        assert_eq!(
+            settings.parameters.background_flow,
+            BackgroundFlow::SimpleShear { rate: 0.25 }
+        );
+        assert_eq!(
+            settings.parameters.tumbling,
+            Tumbling {
+                rate: 0.8,
+                angle: TumbleAngle::VonMisesFisher { mean_cosine: 0.33 },
+            }
+        );
+        assert_eq!(
+            settings.parameters.gravity,
+            Gravity {
+                sedimentation: 0.2,
+                gyrotaxis: 0.4,
+                body_force: 0.6,
+                direction: [0., -2., 0.],
+            }
+        );
+        assert_eq!(settings.parameters.chemotaxis, None);
+        assert_eq!(
+            settings.parameters.gravity.unit_direction().v,
+            [0., -1., 0.]
+        );
+        assert_eq!(
+            settings.parameters.volume_exclusion_model,
+            VolumeExclusionModel::Force
+        );
+        let species = settings.parameters.species(100);
+        assert_eq!(species.len(), 2);
+        assert_eq!(species[0].number_of_particles, 60);
+        assert_eq!(species[0].stress.active, 1.0);
+        assert_eq!(species[0].shape, 44.3);
+        assert_eq!(species[1].number_of_particles, 40);
+        assert_eq!(species[1].self_propulsion, 0.);
+        assert_eq!(species[1].magnetic_moment, 0.);
+        assert_eq!(species[1].stress.active, 0.);
+        assert_eq!(species[1].diffusion.rotational, 0.5);
+        assert_eq!(
+            settings.simulation.orientation,
+            OrientationRepresentation::SphericalHarmonics { degree: 4 }
+        );
+        assert_eq!(settings.simulation.sort_particles_every, Some(10));
+        assert_eq!(settings.simulation.lees_edwards, true);
+        assert_eq!(settings.simulation.dimensionality, Dimensionality::ThreeD);
+        assert_eq!(settings.simulation.output_at_timestep.stress, Some(7));
+        assert_eq!(settings.simulation.output_at_timestep.tracers, Some(5));
+        assert_eq!(settings.simulation.output_at_timestep.chemical, None);
+        assert_eq!(settings.simulation.output_at_timestep.parameters, None);
+        assert_eq!(settings.simulation.number_of_tracers, 20);
+        assert_eq!(settings.parameters.tracer_diffusion, 0.1);
+    }
+
+    #[test]
+    fn settings_to_toml() {
+        // settings are saved as metadata along with the output
+        let settings = read_parameter_file("./test/parameter.toml").unwrap();
+        toml::to_string_pretty(&settings).unwrap();
+        let settings = read_parameter_file("./test/parameter_no_defaults.toml").unwrap();
+        toml::to_string_pretty(&settings).unwrap();
+        let settings = read_parameter_file("./test/parameter_features.toml").unwrap();
+        toml::to_string_pretty(&settings).unwrap();
+    }
+
+    /// Returns valid settings, in which every optional feature is disabled.
+    fn minimal_settings() -> Settings {
+        read_parameter_file("./test/parameter_no_defaults.toml").unwrap()
+    }
+
+    #[test]
+    fn walls_settings() {
+        let mut settings = minimal_settings();
+        settings.simulation.walls = Some(WallInteraction::Align);
+        assert!(check_settings(&settings).is_ok());
+
+        let saved: toml::Value = toml::Value::try_from(&settings).unwrap();
+        assert_eq!(saved["simulation"]["walls"].as_str(), Some("Align"));
+
+        settings.simulation.lees_edwards = true;
+        settings.parameters.background_flow = BackgroundFlow::SimpleShear { rate: 0.25 };
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn hydro_screening_settings() {
+        let mut settings = minimal_settings();
+        settings.parameters.hydro_screening = HydroScreening::Brinkman { length: 2. };
+        assert!(check_settings(&settings).is_ok());
+        let saved: toml::Value = toml::Value::try_from(&settings).unwrap();
+        assert_eq!(
+            saved["parameters"]["hydro_screening"]["type"].as_str(),
+            Some("Brinkman")
+        );
+
+        settings.parameters.hydro_screening = HydroScreening::Brinkman { length: 0. };
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.hydro_screening = HydroScreening::Brinkman { length: 2. };
+        settings.simulation.dimensionality = Dimensionality::QuasiTwoD;
+        settings.simulation.grid_size.z = 1;
+        settings.simulation.grid_size.theta = 1;
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.hydro_screening = HydroScreening::None;
+        assert!(check_settings(&settings).is_ok());
+    }
+
+    #[test]
+    fn force_regularisation_settings() {
+        let mut settings = minimal_settings();
+        assert_eq!(
+            settings.parameters.force_regularisation,
+            ForceRegularisation::None
+        );
+
+        settings.parameters.force_regularisation = ForceRegularisation::Gaussian { width: 0.5 };
+        assert!(check_settings(&settings).is_ok());
+        let saved: toml::Value = toml::Value::try_from(&settings).unwrap();
+        assert_eq!(
+            saved["parameters"]["force_regularisation"]["type"].as_str(),
+            Some("Gaussian")
+        );
+
+        settings.parameters.force_regularisation = ForceRegularisation::RotnePrager { radius: 0. };
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn spectral_filter_settings() {
+        let mut settings = minimal_settings();
+        assert_eq!(settings.simulation.spectral_filter, SpectralFilter::None);
+
+        // the filter is part of the metadata of the output
+        settings.simulation.spectral_filter = SpectralFilter::Exponential {
+            strength: 36.,
+            order: 8,
+        };
+        assert!(check_settings(&settings).is_ok());
+        let saved: toml::Value = toml::Value::try_from(&settings).unwrap();
+        assert_eq!(
+            saved["simulation"]["spectral_filter"]["type"].as_str(),
+            Some("Exponential")
+        );
+        let read: SpectralFilter = saved["simulation"]["spectral_filter"]
+            .clone()
+            .try_into()
+            .unwrap();
+        assert_eq!(read, settings.simulation.spectral_filter);
+
+        let filter: SpectralFilter = toml::from_str("type = \"Exponential\"").unwrap();
+        assert_eq!(filter, settings.simulation.spectral_filter);
+
+        settings.simulation.walls = Some(WallInteraction::Reflect);
+        assert!(check_settings(&settings).is_err());
+        settings.simulation.walls = None;
+
+        settings.simulation.spectral_filter = SpectralFilter::Gaussian {
+            width: 0.,
+            cutoff: 0.5,
+        };
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn refined_grid() {
+        let mut settings = minimal_settings();
+        settings.simulation.grid_size.z = 1;
+        let gs = settings.simulation.grid_size;
+
+        let refined = settings.with_refined_grid(2).unwrap();
+        let rgs = refined.simulation.grid_size;
+        assert_eq!((rgs.x, rgs.y, rgs.z), (2 * gs.x, 2 * gs.y, 1));
+        assert_eq!((rgs.phi, rgs.theta), (gs.phi, gs.theta));
+        assert_eq!(
+            refined.environment.prefix,
+            format!("{}_grid{}x{}x1", settings.environment.prefix, rgs.x, rgs.y)
+        );
+
+        assert!(settings.with_refined_grid(0).is_err());
+    }
+
+    #[test]
+    fn tumbling_settings() {
+        let mut settings = minimal_settings();
+        settings.parameters.tumbling = Tumbling {
+            rate: 0.8,
+            angle: TumbleAngle::VonMisesFisher { mean_cosine: 1.0 },
+        };
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.tumbling.angle = TumbleAngle::Uniform;
+        assert!(check_settings(&settings).is_ok());
+
+        settings.parameters.tumbling.rate = -1.;
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn gravity_settings() {
+        let mut settings = minimal_settings();
+        settings.parameters.gravity.direction = [0.; 3];
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.gravity.direction = [0., 0., -1.];
+        settings.parameters.gravity.body_force = 0.6;
+        assert!(check_settings(&settings).is_ok());
+
+        settings.simulation.walls = Some(WallInteraction::Reflect);
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.gravity.body_force = 0.;
+        assert!(check_settings(&settings).is_ok());
+    }
+
+    #[test]
+    fn volume_exclusion_settings() {
+        let mut settings = minimal_settings();
+        settings.parameters.volume_exclusion_model = VolumeExclusionModel::Force;
+        assert!(check_settings(&settings).is_ok());
+
+        settings.simulation.walls = Some(WallInteraction::Reflect);
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.volume_exclusion_model = VolumeExclusionModel::Diffusive;
+        assert!(check_settings(&settings).is_ok());
+
+        // the model is part of the metadata of the output
+        let saved: toml::Value = toml::Value::try_from(&settings).unwrap();
+        assert_eq!(
+            saved["parameters"]["volume_exclusion_model"].as_str(),
+            Some("Diffusive")
+        );
+    }
+
+    #[test]
+    fn steric_settings() {
+        let mut settings = minimal_settings();
+        let steric: StericParameters = toml::from_str(
+            r#"
+            diameter = 0.4
+            [potential]
+                type = "WCA"
+                strength = 1.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(steric.alignment, 0.);
+        settings.parameters.steric = Some(steric);
+        assert!(check_settings(&settings).is_ok());
+        toml::to_string_pretty(&settings).unwrap();
+
+        let mut sheared = settings.clone();
+        sheared.simulation.lees_edwards = true;
+        sheared.parameters.background_flow = BackgroundFlow::SimpleShear { rate: 0.25 };
+        assert!(check_settings(&sheared).is_err());
+
+        // box is only 1.0 wide in x direction
+        settings.parameters.steric = Some(StericParameters {
+            diameter: 0.5,
+            ..steric
+        });
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.steric = Some(StericParameters {
+            diameter: -0.1,
+            ..steric
+        });
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn near_field_settings() {
+        let mut settings = minimal_settings();
+        assert_eq!(settings.parameters.magnetic_dipole.near_field, None);
+
+        let near_field = NearFieldParameters {
+            splitting: 4.,
+            cutoff: 0.4,
+        };
+        settings.parameters.magnetic_dipole.near_field = Some(near_field);
+        assert!(check_settings(&settings).is_ok());
+        toml::to_string_pretty(&settings).unwrap();
+
+        let mut sheared = settings.clone();
+        sheared.simulation.lees_edwards = true;
+        sheared.parameters.background_flow = BackgroundFlow::SimpleShear { rate: 0.25 };
+        assert!(check_settings(&sheared).is_err());
+
+        settings.parameters.magnetic_dipole.near_field = Some(NearFieldParameters {
+            cutoff: 0.6,
+            ..near_field
+        });
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.magnetic_dipole.near_field = Some(NearFieldParameters {
+            splitting: 0.,
+            ..near_field
+        });
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn external_field_settings() {
+        let mut settings = minimal_settings();
+        assert_eq!(settings.parameters.external_field_map, None);
+
+        let map: ExternalFieldMap = toml::from_str(
+            r#"
+            gradient_drag = 0.01
+            [field]
+                type = "Quadrupole"
+                gradient = 0.5
+                center = [1.0, 1.0, 1.0]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            map.field,
+            ExternalField::Quadrupole {
+                gradient: 0.5,
+                center: [1., 1., 1.],
+                axis: [0., 1., 0.],
+            }
+        );
+        settings.parameters.external_field_map = Some(map);
+        assert!(check_settings(&settings).is_ok());
+        let s = toml::to_string_pretty(&settings).unwrap();
+        assert!(s.contains("[parameters.external_field_map.field]"));
+
+        settings.parameters.external_field_map = Some(ExternalFieldMap {
+            gradient_drag: 0.,
+            field: ExternalField::Quadrupole {
+                gradient: 0.5,
+                center: [1., 1., 1.],
+                axis: [0., 0., 0.],
+            },
+        });
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.external_field_map = Some(ExternalFieldMap {
+            gradient_drag: 0.,
+            field: ExternalField::File {
+                path: "./test/does_not_exist.txt".to_string(),
+            },
+        });
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn chemotaxis_settings() {
+        let mut settings = minimal_settings();
+        settings.simulation.output_at_timestep.chemical = Some(3);
+        assert!(check_settings(&settings).is_err());
+
+        let chemotaxis: Chemotaxis = toml::from_str(
+            r#"
+            alignment = 0.5
+            tumble_bias = 0.1
+            [field]
+                diffusion = 2.0
+                production = 1.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(chemotaxis.field.decay, 0.);
+        assert_eq!(chemotaxis.field.initial, 0.);
+        settings.parameters.chemotaxis = Some(chemotaxis);
+        assert!(check_settings(&settings).is_ok());
+        toml::to_string_pretty(&settings).unwrap();
+
+        let mut sheared = settings.clone();
+        sheared.simulation.lees_edwards = true;
+        sheared.parameters.background_flow = BackgroundFlow::SimpleShear { rate: 0.25 };
+        assert!(check_settings(&sheared).is_err());
+
+        let mut walled = settings.clone();
+        walled.simulation.walls = Some(WallInteraction::Reflect);
+        assert!(check_settings(&walled).is_err());
+
+        settings.parameters.chemotaxis.as_mut().unwrap().field.decay = -1.;
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn polydispersity_settings() {
+        let mut settings = minimal_settings();
+        assert!(settings.parameters.polydispersity.is_monodisperse());
+
+        let polydispersity: Polydispersity = toml::from_str(
+            r#"
+            [speed]
+                type = "Normal"
+                std = 0.2
+            [magnetic_moment]
+                type = "LogNormal"
+                sigma = 0.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(polydispersity.shape, AttributeDistribution::Constant);
+        settings.parameters.polydispersity = polydispersity;
+        assert!(check_settings(&settings).is_ok());
+        let s = toml::to_string_pretty(&settings).unwrap();
+        assert!(s.contains("[parameters.polydispersity.speed]"));
+
+        settings.parameters.polydispersity.shape = AttributeDistribution::Normal { std: -0.1 };
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.polydispersity.shape = AttributeDistribution::File {
+            path: "./test/does_not_exist.txt".to_string(),
+        };
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn schedule_settings() {
+        let mut settings = minimal_settings();
+        assert!(settings.parameters.schedule.is_empty());
+
+        settings.parameters.species = vec![
+            toml::from_str("number_of_particles = 60").unwrap(),
+            toml::from_str("number_of_particles = 40").unwrap(),
+        ];
+        settings.parameters.background_flow = BackgroundFlow::SimpleShear { rate: 0.25 };
+        settings.parameters.schedule = toml::from_str(
+            r#"
+            "stress.active" = { type = "Ramp", start = 0.0, end = 10.0, from = 0.0, to = 2.0 }
+            "species.1.self_propulsion" = { type = "Steps", times = [0.0, 5.0], values = [0.0, 1.0] }
+            "gravity.direction.1" = { type = "Sine", mean = -1.0, amplitude = 0.5, period = 4.0 }
+            "#,
+        )
+        .unwrap();
+        assert!(check_settings(&settings).is_ok());
+        let saved: toml::Value = toml::Value::try_from(&settings).unwrap();
+        assert_eq!(
+            saved["parameters"]["schedule"]["stress.active"]["type"].as_str(),
+            Some("Ramp")
+        );
+
+        // the scheduled parameters can be written without any other output
+        settings.simulation.output_at_timestep.parameters = Some(3);
+        assert!(check_settings(&settings).is_ok());
+        let mut unscheduled = settings.clone();
+        unscheduled.parameters.schedule.clear();
+        assert!(check_settings(&unscheduled).is_err());
+
+        let values = |v: &[(&str, Float)]| -> BTreeMap<String, Float> {
+            v.iter().map(|(p, x)| (p.to_string(), *x)).collect()
+        };
+        let p = settings
+            .parameters
+            .with_values(&values(&[
+                ("stress.active", 1.5),
+                ("species.1.self_propulsion", 0.5),
+                ("gravity.direction.1", -0.5),
+                ("tumbling.rate", 0.1),
+                ("background_flow.rate", 0.5),
+                ("species.0.diffusion.rotational", 0.2),
+            ]))
+            .unwrap();
+        assert_eq!(p.stress.active, 1.5);
+        assert_eq!(p.stress.magnetic, settings.parameters.stress.magnetic);
+        assert_eq!(p.species[1].self_propulsion, Some(0.5));
+        // unset parameters of a species can be scheduled as well
+        assert_eq!(p.species[0].self_propulsion, None);
+        assert_eq!(p.gravity.direction, [0., -0.5, -1.]);
+        assert_eq!(p.tumbling.rate, 0.1);
+        assert_eq!(p.background_flow, BackgroundFlow::SimpleShear { rate: 0.5 });
+        let diffusion = p.species[0].diffusion.unwrap();
+        assert_eq!(diffusion.rotational, 0.2);
+        assert_eq!(
+            diffusion.translational,
+            settings.parameters.diffusion.translational
+        );
+
+        for path in &[
+            "stress.passive",
+            "stress",
+            "species.0.number_of_particles",
+            "species.2.shape",
+            "gravity.direction.3",
+            "chemotaxis.alignment",
+            "background_flow.max_velocity",
+        ] {
+            assert!(
+                settings
+                    .parameters
+                    .with_values(&values(&[(path, 1.)]))
+                    .is_err(),
+                "{}",
+                path
+            );
+        }
+
+        settings.parameters.schedule.insert(
+            "steric.diameter".to_string(),
+            Schedule::Sine {
+                mean: 1.,
+                amplitude: 0.5,
+                period: 1.,
+                phase: 0.,
+            },
+        );
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.schedule.remove("steric.diameter");
+        settings.parameters.schedule.insert(
+            "hydro_screening.screening".to_string(),
+            Schedule::Sine {
+                mean: 1.,
+                amplitude: 0.5,
+                period: 0.,
+                phase: 0.,
+            },
+        );
+        assert!(check_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn schedule_range_settings() {
+        // scheduled parameters have to be valid at all times, not only at the
+        // start
+        let check_schedule = |settings: &Settings, schedule: &str| {
+            let mut settings = settings.clone();
+            settings.parameters.schedule = toml::from_str(schedule).unwrap();
+            check_settings(&settings)
+        };
+
+        let mut settings = minimal_settings();
+        settings.parameters.hydro_screening = HydroScreening::Brinkman { length: 1. };
+        assert!(check_schedule(
+            &settings,
+            r#""hydro_screening.length" = { type = "Ramp", start = 5.0, end = 10.0, from = 1.0, to = 0.5 }"#
+        )
+        .is_ok());
+        assert!(check_schedule(
+            &settings,
+            r#""hydro_screening.length" = { type = "Ramp", start = 5.0, end = 10.0, from = 1.0, to = -1.0 }"#
+        )
+        .is_err());
+
+        assert!(check_schedule(
+            &settings,
+            r#""diffusion.rotational" = { type = "Table", times = [0.0, 100.0], values = [0.5, -0.5] }"#
+        )
+        .is_err());
+        assert!(check_schedule(
+            &settings,
+            r#""tracer_diffusion" = { type = "Sine", mean = 1.0, amplitude = 0.5, period = 10.0 }"#
+        )
+        .is_ok());
+        assert!(check_schedule(
+            &settings,
+            r#""tracer_diffusion" = { type = "Sine", mean = 0.5, amplitude = 1.0, period = 10.0 }"#
+        )
+        .is_err());
+
+        settings.simulation.walls = Some(WallInteraction::Reflect);
+        settings.parameters.hydro_screening = HydroScreening::None;
+        assert!(check_settings(&settings).is_ok());
+        assert!(check_schedule(
+            &settings,
+            r#""gravity.body_force" = { type = "Steps", times = [0.0, 50.0], values = [0.0, 1.0] }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn species_settings() {
+        let mut settings = minimal_settings();
+        settings.parameters.species = vec![
+            toml::from_str("number_of_particles = 60").unwrap(),
+            toml::from_str("number_of_particles = 41").unwrap(),
+        ];
+        assert!(check_settings(&settings).is_err());
+
+        settings.parameters.species[1].number_of_particles = 40;
+        assert!(check_settings(&settings).is_ok());
+    }
+
+    #[test]
+    fn tracer_settings() {
+        let mut settings = minimal_settings();
+        settings.simulation.output_at_timestep.tracers = Some(5);
+        assert!(check_settings(&settings).is_err());
+
+        settings.simulation.number_of_tracers = 20;
+        assert!(check_settings(&settings).is_ok());
+    }
+
+    #[test]
+    fn planar_settings() {
+        let mut settings = minimal_settings();
+        settings.simulation.dimensionality = Dimensionality::QuasiTwoD;
+        assert!(check_settings(&settings).is_err());
+
+        settings.simulation.grid_size.z = 1;
+        settings.simulation.grid_size.theta = 1;
+        assert!(check_settings(&settings).is_ok());
+
+        settings.simulation.walls = Some(WallInteraction::Reflect);
+        assert!(check_settings(&settings).is_err());
+        settings.simulation.walls = None;
+
+        let saved: toml::Value = toml::Value::try_from(&settings).unwrap();
+        assert_eq!(
+            saved["simulation"]["dimensionality"].as_str(),
+            Some("QuasiTwoD")
+        );
+
+        // the Poiseuille flow vanishes in the plane z = 0
+        settings.parameters.background_flow = BackgroundFlow::Poiseuille { max_velocity: 1. };
+        assert!(check_settings(&settings).is_err());
+        settings.simulation.dimensionality = Dimensionality::TwoD;
+        assert!(check_settings(&settings).is_err());
+        settings.simulation.dimensionality = Dimensionality::ThreeD;
+        settings.simulation.grid_size.z = 4;
+        assert!(check_settings(&settings).is_ok());
     }
 
     #[test]
@@ -8,7 +8,11 @@ use std::f64::consts::PI;
 use serde_derive::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::prelude::*;
+use stochasticsampling::flowfield::background::BackgroundFlow;
+use stochasticsampling::flowfield::regularisation::ForceRegularisation;
+use stochasticsampling::flowfield::screening::HydroScreening;
 use stochasticsampling::flowfield::stress::StressPrefactors;
+use stochasticsampling::integrators::tumbling::{TumbleAngle, Tumbling};
 use stochasticsampling::Float;
 use toml;
 
@@ -57,19 +61,48 @@ pub struct Particle {
     pub force_dipole: Float,
     pub magnetic_dipole_moment: Float,
     pub persistance_time: Float,
+    /// Measured tumble rate in 1/s
+    #[serde(default)]
+    pub tumble_rate: Float,
+    /// Sedimentation velocity in m/s
+    #[serde(default)]
+    pub sedimentation_velocity: Float,
+    /// Gyrotactic reorientation time `B` in s, zero for no gyrotaxis
+    #[serde(default)]
+    pub gyrotactic_time: Float,
+    // tables need to come after values for the TOML serialization
+    #[serde(default)]
+    pub tumble_angle: TumbleAngle,
 }
 
 /// Holds phyiscal parameters
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Parameters {
-    pub hydro_screening: Float,
     pub volume_exclusion: Float,
+    #[serde(default)]
+    pub volume_exclusion_model: super::VolumeExclusionModel,
     pub viscocity: Float,
     pub temperature: Float,
     pub volume_fraction: Float,
     pub external_field: Float,
     pub particle: Particle,
+    /// Imposed external flow with rates in 1/s and velocities in microns/s
+    #[serde(default)]
+    pub background_flow: BackgroundFlow,
+    /// Direction of gravity
+    #[serde(default)]
+    pub gravity_direction: Option<[Float; 3]>,
+    /// Includes the stress due to the magnetic dipole-dipole interaction
+    #[serde(default)]
+    pub magnetic_dipole_stress: bool,
+    /// Screening of the hydrodynamic interactions with the screening length
+    /// in m
+    #[serde(default)]
+    pub hydro_screening: HydroScreening,
+    /// Finite size of the force distribution of a particle in m
+    #[serde(default)]
+    pub force_regularisation: ForceRegularisation,
 }
 
 /// Reads the content of a file `filename` into an string and return it.
@@ -143,6 +176,17 @@ impl SettingsSI {
                 * self.parameters.external_field,
         };
 
+        // The mean dipole-dipole field of the particles is `mu_0 m n`.
+        let magnetic_dipole_stress = if self.parameters.magnetic_dipole_stress {
+            stressf
+                * number_density
+                * 4.0e-7
+                * PI
+                * self.parameters.particle.magnetic_dipole_moment.powi(2)
+        } else {
+            0.
+        };
+
         let rotfriction =
             8. * PI * self.parameters.viscocity * self.parameters.particle.radius.powi(3);
         let transfriction = 6. * PI * self.parameters.viscocity * self.parameters.particle.radius;
@@ -152,6 +196,13 @@ impl SettingsSI {
         // DOI: 101  10.1209/0295-5075/101/20010
         let rotdiff_active = 1. / 2. / self.parameters.particle.persistance_time;
 
+        let tumbling = Tumbling {
+            rate: self.parameters.particle.tumble_rate,
+            angle: self.parameters.particle.tumble_angle,
+        };
+        let rotdiff_tumble =
+            tumbling.effective_rotational_diffusion(self.simulation.dimensionality);
+
         let transdiff_brown = BOLTZMANN * self.parameters.temperature / transfriction;
 
         let diff = super::DiffusionConstants {
@@ -159,30 +210,68 @@ impl SettingsSI {
             rotational: number_density.powf(-1. / 3.) / uc * (rotdiff_brown + rotdiff_active),
         };
 
+        // Tumbling is simulated explicitly, but decorrelates the orientation
+        // like rotational diffusion in the homogeneous state.
+        let rotdiff_eff = rotdiff_brown + rotdiff_active + rotdiff_tumble;
         let alignment_parameter = self.parameters.particle.magnetic_dipole_moment
             * self.parameters.external_field
             / rotfriction
-            / (rotdiff_brown + rotdiff_active);
+            / rotdiff_eff;
+
+        // The weight of a particle is balanced by the Stokes drag.
+        let v_sed = self.parameters.particle.sedimentation_velocity;
+        let weight = transfriction * v_sed;
+        let gravity = super::Gravity {
+            sedimentation: v_sed / uc,
+            gyrotaxis: if self.parameters.particle.gyrotactic_time > 0. {
+                tc / 2. / self.parameters.particle.gyrotactic_time
+            } else {
+                0.
+            },
+            body_force: number_density.powf(1. / 3.) / uc / self.parameters.viscocity * weight,
+            direction: self
+                .parameters
+                .gravity_direction
+                .unwrap_or(super::Gravity::default().direction),
+        };
 
         let mut res = super::Settings {
             simulation: self.simulation,
             parameters: super::Parameters {
                 diffusion: diff,
                 stress: stress,
-                magnetic_reorientation: alignment_parameter * diff.rotational,
+                magnetic_reorientation: alignment_parameter * number_density.powf(-1. / 3.) / uc
+                    * rotdiff_eff,
                 magnetic_dipole: super::MagneticDipolePrefactors {
                     magnetic_dipole_dipole: number_density.powf(2. / 3.) / uc / rotfriction
                         * 4.0e-7
                         * PI
                         * self.parameters.particle.magnetic_dipole_moment.powi(2),
+                    stress: magnetic_dipole_stress,
+                    near_field: None,
                 },
+                external_field_map: None,
                 volume_exclusion: self.parameters.volume_exclusion,
+                volume_exclusion_model: self.parameters.volume_exclusion_model,
+                tracer_diffusion: 0.,
                 shape: self.parameters.particle.shape,
-                hydro_screening: self.parameters.hydro_screening,
+                hydro_screening: self.parameters.hydro_screening.to_simulation_units(xc),
+                force_regularisation: self.parameters.force_regularisation.to_simulation_units(xc),
                 magnetic_drag: number_density / uc / transfriction
                     * 4.0e-7
                     * PI
                     * self.parameters.particle.magnetic_dipole_moment.powi(2),
+                background_flow: self.parameters.background_flow.to_simulation_units(uc, tc),
+                tumbling: Tumbling {
+                    rate: tumbling.rate * tc,
+                    ..tumbling
+                },
+                gravity,
+                chemotaxis: None,
+                steric: None,
+                polydispersity: Default::default(),
+                schedule: Default::default(),
+                species: Vec::new(),
             },
             environment: self.environment.clone(),
         };
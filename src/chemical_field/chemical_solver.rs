@@ -0,0 +1,192 @@
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./chemical_solver_test.rs"]
+mod chemical_solver_test;
+
+use super::ChemicalParameters;
+use crate::mesh::fft_helper::{get_k_mesh, spectral_gradient};
+use crate::Float;
+use crate::{BoxSize, GridSize};
+use fftw3::fft;
+use fftw3::fft::FFTPlan;
+use ndarray::{Array, ArrayView, Axis, Ix3, Ix4, Zip};
+use ndarray_parallel::prelude::*;
+use num_complex::Complex;
+use std::sync::Arc;
+
+/// Spectral solver of the reaction-diffusion equation of the concentration,
+/// see `ChemicalParameters`. The linear terms are integrated exactly in
+/// Fourier space, while the particle source is taken constant during a
+/// timestep.
+pub struct ChemicalSolver {
+    fft_plan_forward: Arc<FFTPlan>,
+    fft_plan_backward: Arc<FFTPlan>,
+    k_mesh: Array<Complex<Float>, Ix4>,
+    parameters: ChemicalParameters,
+    /// timestep, for which `decay_factor` and `source_factor` are calculated
+    timestep: Float,
+    /// `exp(-(D k^2 + decay) dt)` for every wave vector
+    decay_factor: Array<Float, Ix3>,
+    /// `(1 - exp(-(D k^2 + decay) dt)) / (D k^2 + decay)` for every wave
+    /// vector, which tends to `dt` for vanishing rates
+    source_factor: Array<Float, Ix3>,
+    concentration: Array<Float, Ix3>,
+    /// FFT of the concentration
+    concentration_k: Array<Complex<Float>, Ix3>,
+    /// FFT of the source term, also used as buffer for the inverse FFT
+    source: Array<Complex<Float>, Ix3>,
+    gradient: Array<Complex<Float>, Ix4>,
+}
+
+impl ChemicalSolver {
+    /// Returns a solver with the homogeneous initial concentration given in
+    /// `parameters`.
+    pub fn new(
+        grid_size: GridSize,
+        box_size: BoxSize,
+        parameters: ChemicalParameters,
+    ) -> ChemicalSolver {
+        let mut dummy: Array<Complex<Float>, Ix3> =
+            Array::default([grid_size.x, grid_size.y, grid_size.z]);
+        let plan_forward = FFTPlan::new_c2c_inplace_3d(
+            &mut dummy.view_mut(),
+            fft::FFTDirection::Forward,
+            fft::FFTFlags::Patient,
+        )
+        .unwrap();
+
+        let plan_backward = FFTPlan::new_c2c_inplace_3d(
+            &mut dummy.view_mut(),
+            fft::FFTDirection::Backward,
+            fft::FFTFlags::Patient,
+        )
+        .unwrap();
+
+        let sh = (grid_size.x, grid_size.y, grid_size.z);
+        let mut solver = ChemicalSolver {
+            fft_plan_forward: Arc::new(plan_forward),
+            fft_plan_backward: Arc::new(plan_backward),
+            k_mesh: get_k_mesh(grid_size, box_size),
+            parameters,
+            timestep: 0.,
+            decay_factor: Array::ones(sh),
+            source_factor: Array::zeros(sh),
+            concentration: Array::from_elem(sh, parameters.initial),
+            concentration_k: Array::zeros(sh),
+            source: Array::zeros(sh),
+            gradient: Array::zeros((3, sh.0, sh.1, sh.2)),
+        };
+        solver.fft_concentration();
+        solver.update_gradient();
+
+        solver
+    }
+
+    /// Replaces the concentration field, e.g. when resuming a simulation.
+    pub fn set_concentration(&mut self, concentration: Array<Float, Ix3>) {
+        assert_eq!(
+            concentration.dim(),
+            self.concentration.dim(),
+            "Concentration field does not match the grid size."
+        );
+        self.concentration = concentration;
+        self.fft_concentration();
+        self.update_gradient();
+    }
+
+    /// Calculates the FFT of the concentration and stores it in
+    /// `self.concentration_k`.
+    fn fft_concentration(&mut self) {
+        Zip::from(&mut self.concentration_k)
+            .and(&self.concentration)
+            .par_apply(|ck, c| *ck = Complex::from(c));
+        self.fft_plan_forward
+            .reexecute3d(&mut self.concentration_k.view_mut());
+    }
+
+    /// Recalculates the integrating factors for the timestep `timestep`.
+    fn update_factors(&mut self, timestep: Float) {
+        let ChemicalParameters {
+            diffusion, decay, ..
+        } = self.parameters;
+
+        Zip::from(&mut self.decay_factor)
+            .and(&mut self.source_factor)
+            .and(self.k_mesh.lanes(Axis(0)))
+            .par_apply(|e, s, k| {
+                let rate = diffusion * k.iter().map(|k| k.norm_sqr()).sum::<Float>() + decay;
+                *e = (-rate * timestep).exp();
+                *s = if rate == 0. {
+                    timestep
+                } else {
+                    -(-rate * timestep).exp_m1() / rate
+                };
+            });
+
+        self.timestep = timestep;
+    }
+
+    /// Propagates the concentration by one timestep `timestep`, with the
+    /// particles given by their density `density`.
+    pub fn step(&mut self, density: ArrayView<Float, Ix3>, timestep: Float) {
+        if timestep != self.timestep {
+            self.update_factors(timestep);
+        }
+
+        let ChemicalParameters {
+            production,
+            consumption,
+            ..
+        } = self.parameters;
+
+        Zip::from(&mut self.source)
+            .and(&self.concentration)
+            .and(&density)
+            .par_apply(|s, c, d| *s = Complex::from((production - consumption * c) * d));
+        self.fft_plan_forward
+            .reexecute3d(&mut self.source.view_mut());
+
+        Zip::from(&mut self.concentration_k)
+            .and(&self.source)
+            .and(&self.decay_factor)
+            .and(&self.source_factor)
+            .par_apply(|c, s, e, w| *c = *c * e + s * w);
+
+        // transform back to real space
+        self.source.assign(&self.concentration_k);
+        self.fft_plan_backward
+            .reexecute3d(&mut self.source.view_mut());
+
+        let norm = self.source.len() as Float;
+        Zip::from(&mut self.concentration)
+            .and(&self.source)
+            .par_apply(|c, s| *c = s.re / norm);
+
+        self.update_gradient();
+    }
+
+    /// Calculates the gradient of the concentration from its FFT.
+    fn update_gradient(&mut self) {
+        spectral_gradient(
+            self.concentration_k.view(),
+            self.k_mesh.view(),
+            &self.fft_plan_backward,
+            self.gradient.view_mut(),
+        );
+    }
+
+    /// Returns a view into the concentration field.
+    pub fn get_concentration(&self) -> ArrayView<'_, Float, Ix3> {
+        self.concentration.view()
+    }
+
+    /// Returns a view into the gradient of the concentration field, which is
+    /// real valued.
+    pub fn get_gradient(&self) -> ArrayView<'_, Complex<Float>, Ix4> {
+        self.gradient.view()
+    }
+
+    pub fn get_real_gradient(&self) -> Array<Float, Ix4> {
+        self.gradient.map(|v| v.re)
+    }
+}
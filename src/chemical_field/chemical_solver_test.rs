@@ -0,0 +1,87 @@
+use super::*;
+#[cfg(feature = "single")]
+use std::f32::consts::PI;
+#[cfg(not(feature = "single"))]
+use std::f64::consts::PI;
+
+const BS: BoxSize = BoxSize {
+    x: 4.,
+    y: 3.,
+    z: 2.,
+};
+
+const GS: GridSize = GridSize {
+    x: 8,
+    y: 3,
+    z: 2,
+    phi: 1,
+    theta: 1,
+};
+
+fn parameters() -> ChemicalParameters {
+    ChemicalParameters {
+        diffusion: 0.5,
+        decay: 0.,
+        production: 0.,
+        consumption: 0.,
+        initial: 0.,
+    }
+}
+
+/// A Fourier mode decays exponentially with rate `D k^2`, its gradient is
+/// given analytically.
+#[test]
+fn diffusion_of_fourier_mode() {
+    let mut s = ChemicalSolver::new(GS, BS, parameters());
+    let k = 2. * PI / BS.x;
+    let x = |ix: usize| (ix as Float + 0.5) * BS.x / GS.x as Float;
+
+    let c = Array::from_shape_fn((GS.x, GS.y, GS.z), |(ix, _, _)| (k * x(ix)).cos());
+    s.set_concentration(c);
+
+    let density = Array::zeros((GS.x, GS.y, GS.z));
+    let dt = 0.1;
+    for _ in 0..10 {
+        s.step(density.view(), dt);
+    }
+
+    let a = (-0.5 * k * k * 10. * dt).exp();
+    for ((ix, _, _), c) in s.get_concentration().indexed_iter() {
+        let expect = a * (k * x(ix)).cos();
+        assert!((c - expect).abs() < 1e-12, "{} != {}", c, expect);
+    }
+
+    for ((i, ix, _, _), g) in s.get_gradient().indexed_iter() {
+        let expect = [-a * k * (k * x(ix)).sin(), 0., 0.][i];
+        assert!((g - expect).norm() < 1e-12, "{} != {}", g, expect);
+    }
+}
+
+/// Production by a homogeneous density is balanced by the decay.
+#[test]
+fn production_and_decay() {
+    let p = ChemicalParameters {
+        decay: 2.,
+        production: 3.,
+        initial: 0.5,
+        ..parameters()
+    };
+    let mut s = ChemicalSolver::new(GS, BS, p);
+    let density = Array::from_elem((GS.x, GS.y, GS.z), 1.);
+
+    s.step(density.view(), 0.2);
+    let expect = 1.5 + (0.5 - 1.5) * (-2. * 0.2 as Float).exp();
+    for c in s.get_concentration().iter() {
+        assert!((c - expect).abs() < 1e-12, "{} != {}", c, expect);
+    }
+
+    for _ in 0..200 {
+        s.step(density.view(), 0.2);
+    }
+    for c in s.get_concentration().iter() {
+        assert!((c - 1.5).abs() < 1e-12, "{} != 1.5", c);
+    }
+    for g in s.get_gradient().iter() {
+        assert!(g.norm() < 1e-12);
+    }
+}
@@ -0,0 +1,30 @@
+//! Scalar concentration field of a chemical, e.g. oxygen or a chemoattractant,
+//! which is produced or consumed by the particles, diffuses and decays.
+pub mod chemical_solver;
+
+use crate::Float;
+use serde_derive::{Deserialize, Serialize};
+
+/// Parameters of the reaction-diffusion equation
+/// ```latex
+///     d_t c = D lap c - decay c + (production - consumption c) rho
+/// ```
+/// for the concentration `c`, where `rho` is the density of the particles.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChemicalParameters {
+    /// Diffusion constant `D`
+    pub diffusion: Float,
+    #[serde(default)]
+    pub decay: Float,
+    #[serde(default)]
+    pub production: Float,
+    /// Consumption rate per particle and unit concentration. It is integrated
+    /// explicitly, so `consumption * rho * timestep` must be small to keep the
+    /// concentration positive.
+    #[serde(default)]
+    pub consumption: Float,
+    /// Homogeneous initial concentration
+    #[serde(default)]
+    pub initial: Float,
+}
@@ -1,28 +1,36 @@
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./density_gradient_test.rs"]
+mod density_gradient_test;
+
 use super::Distribution;
-use crate::mesh::fft_helper::get_k_mesh;
-use crate::mesh::grid_width::GridWidth;
+use crate::mesh::fft_helper::{
+    get_filter_mesh, get_k_mesh, get_sheared_k_mesh, spectral_gradient, SpectralFilter,
+};
+use crate::Float;
 use crate::{BoxSize, GridSize};
 use fftw3::fft;
 use fftw3::fft::FFTPlan;
-use ndarray::{Array, ArrayView, Axis, Ix3, Ix4, Zip};
+use ndarray::{Array, ArrayView, Ix3, Ix4, Zip};
 use ndarray_parallel::prelude::*;
 use num_complex::Complex;
 use std::sync::Arc;
-use crate::Float;
 
+/// Calculates the gradient of the particle density spectrally.
 pub struct DensityGradient {
     fft_plan_forward: Arc<FFTPlan>,
     fft_plan_backward: Arc<FFTPlan>,
     k_mesh: Array<Complex<Float>, Ix4>,
     gradient: Array<Complex<Float>, Ix4>,
-    grid_width: GridWidth,
     density: Array<Complex<Float>, Ix3>,
+    /// factors of the spectral filter of every mode, see `set_filter`
+    filter: Array<Float, Ix3>,
+    grid_size: GridSize,
+    box_size: BoxSize,
 }
 
 impl DensityGradient {
     pub fn new(grid_size: GridSize, box_size: BoxSize) -> DensityGradient {
-        let grid_width = GridWidth::new(grid_size, box_size);
-
         let mesh = get_k_mesh(grid_size, box_size);
 
         let mut dummy: Array<Complex<Float>, Ix3> =
@@ -46,64 +54,58 @@ impl DensityGradient {
             fft_plan_forward: Arc::new(plan_forward),
             fft_plan_backward: Arc::new(plan_backward),
             gradient: Array::default([3, grid_size.x, grid_size.y, grid_size.z]),
-            grid_width: grid_width,
             density: Array::default([grid_size.x, grid_size.y, grid_size.z]),
+            filter: get_filter_mesh(grid_size, SpectralFilter::None),
+            grid_size,
+            box_size,
         }
     }
 
-    /// Returns vector gradient of magnetic field.
-    fn update_gradient(&mut self, dist: &Distribution) {
-        let sh = dist.dim();
-        let n = sh.0 * sh.1 * sh.2;
-
-        let dist = dist.dist.view();
-        let dist = dist.into_shape([n, sh.3 * sh.4]).unwrap();
+    /// Sets the strain of the box for Lees-Edwards boundary conditions. See
+    /// `SpectralSolver::set_strain`.
+    pub fn set_strain(&mut self, strain: Float) {
+        self.k_mesh = get_sheared_k_mesh(self.grid_size, self.box_size, strain);
+    }
 
-        // use first component of gradient to safe fourier transformation of density
-        let density = self.density.view_mut();
-        let mut density = density.into_shape([n]).unwrap();
+    /// Sets the spectral filter `filter`, which is applied to the gradient.
+    pub fn set_filter(&mut self, filter: SpectralFilter) {
+        self.filter = get_filter_mesh(self.grid_size, filter);
+    }
 
-        let dthph = self.grid_width.theta * self.grid_width.phi;
-        density
-            .iter_mut()
-            .zip(dist.axis_iter(Axis(0)))
-            .for_each(|(dens, dist)| *dens = Complex::from(dist.sum()) * dthph);
+    /// Calculates the gradient of the density `density`.
+    fn update_gradient(&mut self, density: ArrayView<Float, Ix3>) {
+        Zip::from(&mut self.density)
+            .and(&density)
+            .par_apply(|dens, d| *dens = Complex::from(d));
 
-        let mut density = density.into_shape([sh.0, sh.1, sh.2]).unwrap();
         let fft = &self.fft_plan_forward;
-        fft.reexecute3d(&mut density.view_mut());
-
-        let k = self.k_mesh.view();
-        let k = k.into_shape([3, n]).unwrap();
-
-        let g = self.gradient.view_mut();
-        let mut g = g.into_shape([3, n]).unwrap();
-
-        let density = density.into_shape([n]).unwrap();
-
-        // FFT normalization
-        let norm = n as Float;
-        let norm = Complex::new(0., 1.) / norm;
-
-        Zip::from(g.axis_iter_mut(Axis(1)))
-            .and(k.axis_iter(Axis(1)))
-            .and(density.axis_iter(Axis(0)))
-            .par_apply(|mut g, k, d| {
-                let d = unsafe { *d.as_ptr() };
-                let e = (&k * d) * norm;
-                g.assign(&e)
-            });
-
-        let mut g = g.into_shape([3, sh.0, sh.1, sh.2]).unwrap();
-
-        let fft = &self.fft_plan_backward;
-        g.outer_iter_mut()
-            .into_par_iter()
-            .for_each(|mut v| fft.reexecute3d(&mut v));
+        fft.reexecute3d(&mut self.density.view_mut());
+
+        Zip::from(&mut self.density)
+            .and(&self.filter)
+            .par_apply(|d, &f| *d *= f);
+
+        spectral_gradient(
+            self.density.view(),
+            self.k_mesh.view(),
+            &self.fft_plan_backward,
+            self.gradient.view_mut(),
+        );
     }
 
+    /// Returns the gradient of the density of the distribution `dist`.
     pub fn get_gradient(&mut self, dist: &Distribution) -> ArrayView<Complex<Float>, Ix4> {
-        self.update_gradient(dist);
+        self.update_gradient(dist.density().view());
+        self.gradient.view()
+    }
+
+    /// Returns the gradient of the density `density`, e.g. the density of
+    /// all species of a mixture.
+    pub fn get_gradient_of_density(
+        &mut self,
+        density: ArrayView<Float, Ix3>,
+    ) -> ArrayView<'_, Complex<Float>, Ix4> {
+        self.update_gradient(density);
         self.gradient.view()
     }
 
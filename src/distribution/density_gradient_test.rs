@@ -0,0 +1,87 @@
+use super::*;
+#[cfg(feature = "single")]
+use std::f32::consts::PI;
+#[cfg(not(feature = "single"))]
+use std::f64::consts::PI;
+
+const BS: BoxSize = BoxSize {
+    x: 4.,
+    y: 3.,
+    z: 2.,
+};
+
+const GS: GridSize = GridSize {
+    x: 8,
+    y: 3,
+    z: 2,
+    phi: 1,
+    theta: 1,
+};
+
+/// Compares the gradient of a Fourier mode of the density along x with its
+/// analytic gradient `k sin(k x) (-1, strain, 0)`.
+fn check_fourier_mode(g: &mut DensityGradient, strain: Float) {
+    let k = 2. * PI / BS.x;
+    let x = |ix: usize| (ix as Float + 0.5) * BS.x / GS.x as Float;
+
+    let density = Array::from_shape_fn((GS.x, GS.y, GS.z), |(ix, _, _)| 2. + (k * x(ix)).cos());
+    let gradient = g.get_gradient_of_density(density.view());
+
+    for ((i, ix, _, _), g) in gradient.indexed_iter() {
+        let expect = [-1., strain, 0.][i] * k * (k * x(ix)).sin();
+        assert!((g - expect).norm() < 1e-12, "{} != {}", g, expect);
+    }
+}
+
+#[test]
+fn gradient_of_fourier_mode() {
+    let mut g = DensityGradient::new(GS, BS);
+    check_fourier_mode(&mut g, 0.);
+
+    let real = g.get_real_gradient();
+    assert_eq!(real.dim(), (3, GS.x, GS.y, GS.z));
+}
+
+/// In sheared coordinates a mode along x also varies along y.
+#[test]
+fn gradient_in_sheared_coordinates() {
+    let mut g = DensityGradient::new(GS, BS);
+    g.set_strain(0.3);
+    check_fourier_mode(&mut g, 0.3);
+
+    g.set_strain(0.);
+    check_fourier_mode(&mut g, 0.);
+}
+
+#[test]
+fn homogeneous_density() {
+    let mut g = DensityGradient::new(GS, BS);
+    let density = Array::from_elem((GS.x, GS.y, GS.z), 3.);
+
+    for v in g.get_gradient_of_density(density.view()).iter() {
+        assert!(v.norm() < 1e-12);
+    }
+}
+
+/// The 2/3 rule removes the mode `m = 3` of the eight cells in x direction
+/// and keeps the mode `m = 1`.
+#[test]
+fn filtered_gradient() {
+    let mut g = DensityGradient::new(GS, BS);
+    g.set_filter(SpectralFilter::TwoThirds);
+
+    let k = 2. * PI / BS.x;
+    let x = |ix: usize| (ix as Float + 0.5) * BS.x / GS.x as Float;
+    let density = Array::from_shape_fn((GS.x, GS.y, GS.z), |(ix, _, _)| {
+        2. + (k * x(ix)).cos() + (3. * k * x(ix)).cos()
+    });
+    let gradient = g.get_gradient_of_density(density.view());
+
+    for ((i, ix, _, _), g) in gradient.indexed_iter() {
+        let expect = [-1., 0., 0.][i] * k * (k * x(ix)).sin();
+        assert!((g - expect).norm() < 1e-12, "{} != {}", g, expect);
+    }
+
+    g.set_filter(SpectralFilter::None);
+    check_fourier_mode(&mut g, 0.);
+}
@@ -2,7 +2,7 @@
 use super::*;
 use crate::mesh::grid_width::GridWidth;
 use crate::particle::Particle;
-use crate::test_helper::equal_floats;
+use crate::test_helper::{equal_floats, equal_floats_eps};
 use crate::Float;
 use crate::{BoxSize, GridSize};
 #[cfg(feature = "single")]
@@ -207,3 +207,246 @@ fn index() {
     assert_eq!(d[[3, -1, 0, 1, 0]], 42.);
     assert_eq!(d[[3, 5, 0, 1, 0]], 42.);
 }
+
+#[test]
+fn sample_from_spherical_harmonics() {
+    let box_size = BoxSize {
+        x: 1.,
+        y: 1.,
+        z: 1.,
+    };
+    let grid_size = GridSize {
+        x: 5,
+        y: 5,
+        z: 1,
+        phi: 2,
+        theta: 2,
+    };
+    let orientation = OrientationRepresentation::SphericalHarmonics { degree: 3 };
+    let mut d = Distribution::with_orientation(grid_size, box_size, orientation);
+    assert_eq!(d.dim(), (5, 5, 1, 16, 1));
+
+    let p = Particle::create_isotropic(1000, &box_size, 1);
+    d.sample_from(&p);
+
+    // Integral of density over space has to be normalised
+    let GridWidth { x, y, z, .. } = d.get_grid_width();
+    let sum = d.density().scalar_sum() * x * y * z;
+    assert!(
+        equal_floats_eps(sum, 1., 1e-14),
+        "Density sum is: {}, but expected: {}. Should be normalised.",
+        sum,
+        1.
+    );
+
+    // The density does not depend on the orientation of a single particle
+    let p2 = vec![Particle::new(0.7, 0.3, 0., 1., 2., &box_size)];
+    d.sample_from(&p2);
+
+    let dens = d.density();
+    assert!(equal_floats_eps(dens[[3, 1, 0]] * x * y * z, 1., 1e-14));
+    assert_eq!(dens.scalar_sum(), dens[[3, 1, 0]]);
+}
+
+#[test]
+fn equal_area_grid() {
+    let box_size = BoxSize {
+        x: 1.,
+        y: 1.,
+        z: 1.,
+    };
+    let grid_size = GridSize {
+        x: 1,
+        y: 1,
+        z: 1,
+        phi: 4,
+        theta: 8,
+    };
+    let orientation = OrientationRepresentation::EqualAreaGrid;
+    let mut d = Distribution::with_orientation(grid_size, box_size, orientation);
+
+    // bins are uniform in cos(theta)
+    for &(theta, expect) in &[(0., 0), ((0.1 as Float).acos(), 3), (2., 5), (PI, 7)] {
+        let p = Particle::new(0.5, 0.5, 0.5, 1., theta, &box_size);
+        assert_eq!(d.coord_to_grid(&p), [0, 0, 0, 0, expect]);
+    }
+
+    // all bins are equally populated for an isotropic distribution
+    let n = 32_000;
+    let p = Particle::create_isotropic(n, &box_size, 1);
+    d.histogram_from(&p);
+    let expect = n as Float / 32.;
+    for c in d.dist.iter() {
+        assert!(
+            (c - expect).abs() < 0.15 * expect,
+            "Bin contains {} particles, but expected about {}.",
+            c,
+            expect
+        );
+    }
+
+    d.sample_from(&p);
+    let sum = d.dist.scalar_sum() * d.orientation_measure();
+    assert!(
+        equal_floats(sum, 1.),
+        "Step function sum is: {}, but expected: {}. Should be normalised.",
+        sum,
+        1.
+    );
+    assert!(equal_floats(d.density()[[0, 0, 0]], 1.));
+}
+
+#[test]
+fn histogram_matches_serial() {
+    let box_size = BoxSize {
+        x: 1.,
+        y: 2.,
+        z: 3.,
+    };
+    let grid_size = GridSize {
+        x: 7,
+        y: 5,
+        z: 3,
+        phi: 6,
+        theta: 4,
+    };
+    let p = Particle::create_isotropic(10_000, &box_size, 3);
+    let mut d = Distribution::new(grid_size, box_size);
+
+    let mut expect = Array::<Float, _>::zeros(d.dim());
+    for p in &p {
+        expect[d.coord_to_grid(p)] += 1.;
+    }
+
+    // sample twice, to check the reuse of the buffers
+    for p in &[&p[..5000], &p[..]] {
+        d.histogram_from(p);
+    }
+    assert_eq!(d.dist, expect);
+
+    // scaling is fused into the sampling
+    let mut scaled = Distribution::new(grid_size, box_size);
+    scaled.sample_scaled_from(&p, 6.);
+    d.sample_from(&p);
+    for (a, b) in scaled.dist.iter().zip(d.dist.iter()) {
+        assert!(equal_floats(*a, 6. * b), "{} != {}", a, 6. * b);
+    }
+}
+
+/// The coefficients, which are summed in parallel, are the serial sum of the
+/// spherical harmonics of the particles in every cell.
+#[test]
+fn parallel_harmonics() {
+    let box_size = BoxSize {
+        x: 1.,
+        y: 2.,
+        z: 3.,
+    };
+    let grid_size = GridSize {
+        x: 4,
+        y: 3,
+        z: 2,
+        phi: 2,
+        theta: 2,
+    };
+    let degree = 4;
+    let orientation = OrientationRepresentation::SphericalHarmonics { degree };
+    let p = Particle::create_isotropic(10_000, &box_size, 3);
+    let mut d = Distribution::with_orientation(grid_size, box_size, orientation);
+
+    let mut expect = Array::<Float, _>::zeros(d.dim());
+    let mut ylm = vec![0.; number_of_coefficients(degree)];
+    for p in &p {
+        let (ix, iy, iz) = get_cell_index(&d.grid_position(p), &d.grid_width, &d.grid_size);
+        real_spherical_harmonics(degree, p.orientation.phi, p.orientation.theta, &mut ylm);
+        for (l, y) in ylm.iter().enumerate() {
+            expect[[ix, iy, iz, l, 0]] += y * p.position.x;
+        }
+    }
+
+    // sample twice, to check that the coefficients are reset
+    for p in &[&p[..5000], &p[..]] {
+        d.harmonics_from(p, degree, |p| p.position.x);
+    }
+    for (a, b) in d.dist.iter().zip(expect.iter()) {
+        assert!(equal_floats_eps(*a, *b, 1e-9), "{} != {}", a, b);
+    }
+}
+
+#[test]
+fn weighted_histogram() {
+    let box_size = BoxSize {
+        x: 1.,
+        y: 2.,
+        z: 3.,
+    };
+    let grid_size = GridSize {
+        x: 4,
+        y: 3,
+        z: 2,
+        phi: 6,
+        theta: 4,
+    };
+    let mut p = Particle::create_isotropic(5000, &box_size, 4);
+    for (i, p) in p.iter_mut().enumerate() {
+        p.attributes.magnetic_moment = (i % 7) as Float;
+    }
+
+    let mut d = Distribution::new(grid_size, box_size);
+    let mut expect = Array::<Float, _>::zeros(d.dim());
+    for p in &p {
+        expect[d.coord_to_grid(p)] += p.attributes.magnetic_moment;
+    }
+
+    d.sample_weighted_from(&p, 1., |p| p.attributes.magnetic_moment);
+    let mut counted = Distribution::new(grid_size, box_size);
+    counted.sample_from(&p);
+    // the normalisation is given by the number of particles
+    let norm = counted.dist.sum() / 5000.;
+    for (a, b) in d.dist.iter().zip(expect.iter()) {
+        assert!(equal_floats(*a, b * norm), "{} != {}", a, b * norm);
+    }
+
+    // the spherical harmonics are weighted as well
+    let orientation = OrientationRepresentation::SphericalHarmonics { degree: 2 };
+    let mut weighted = Distribution::with_orientation(grid_size, box_size, orientation);
+    let mut d = Distribution::with_orientation(grid_size, box_size, orientation);
+    weighted.sample_weighted_from(&p, 1., |_| 2.5);
+    d.sample_from(&p);
+    for (a, b) in weighted.dist.iter().zip(d.dist.iter()) {
+        assert!(equal_floats_eps(*a, 2.5 * b, 1e-12), "{} != {}", a, 2.5 * b);
+    }
+}
+
+#[test]
+fn sheared_coord_to_grid() {
+    let box_size = BoxSize {
+        x: 4.,
+        y: 2.,
+        z: 1.,
+    };
+    let grid_size = GridSize {
+        x: 4,
+        y: 2,
+        z: 1,
+        phi: 1,
+        theta: 1,
+    };
+    let mut d = Distribution::new(grid_size, box_size);
+    d.set_strain(0.5);
+
+    // x is shifted by -strain * (y - L_y / 2)
+    for &(x, y, expect) in &[(0.5, 1.0, [0, 1]), (0.2, 1.9, [3, 1]), (1.6, 0.1, [2, 0])] {
+        let p = Particle::new(x, y, 0.5, 1., 1., &box_size);
+        assert_eq!(d.coord_to_grid(&p), [expect[0], expect[1], 0, 0, 0]);
+    }
+
+    // a particle and its Lees-Edwards image are binned into the same cell
+    let mut p = Particle::new(1.5, 1.9, 0.5, 1., 1., &box_size);
+    let cell = d.coord_to_grid(&p);
+    let offset = 0.5 * box_size.y;
+    p.position.x += offset;
+    p.position.y += box_size.y;
+    p.lees_edwards_pbc(&box_size, offset);
+    assert_eq!(d.coord_to_grid(&p), cell);
+}
@@ -1,24 +1,167 @@
 //! A representation for the probability distribution function.
 
 pub mod density_gradient;
+pub mod spherical_harmonics;
 
 // Move unit test into own file
 #[cfg(test)]
 #[path = "./distribution_test.rs"]
 mod distribution_test;
 
+use self::spherical_harmonics::{
+    number_of_coefficients, real_spherical_harmonics, sphere_quadrature,
+};
+use crate::consts::TWOPI;
+use crate::mesh::get_cell_index;
 use crate::mesh::grid_width::GridWidth;
-use crate::particle::Particle;
+use crate::particle::{Particle, Position};
 use crate::Float;
 use crate::{BoxSize, GridSize};
-use ndarray::{Array, Ix, Ix5};
+use ndarray::{s, Array, ArrayViewMut, Axis, Ix, Ix1, Ix3, Ix5, Zip};
+use ndarray_parallel::prelude::*;
+use rayon::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 #[cfg(feature = "single")]
 use std::f32::consts::PI;
 #[cfg(not(feature = "single"))]
 use std::f64::consts::PI;
+use std::fmt;
+use std::mem;
 use std::ops::Index;
 
+/// Describes how the orientational part of the distribution function is
+/// represented in the last two axes of `Distribution::dist`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum OrientationRepresentation {
+    /// Histogram on a uniform `(phi, theta)` grid with `grid_size.phi x
+    /// grid_size.theta` cells.
+    #[default]
+    UniformGrid,
+    /// Histogram with `grid_size.phi` uniform bins in `phi` and
+    /// `grid_size.theta` uniform bins in `cos(theta)`. All bins cover the same
+    /// area on the unit sphere, hence they are equally populated for an
+    /// isotropic distribution.
+    EqualAreaGrid,
+    /// Coefficients of real spherical harmonics up to (and including) the
+    /// given degree, estimated directly from the particle orientations. The
+    /// coefficients are stored along the fourth axis, the fifth axis has
+    /// length one.
+    SphericalHarmonics { degree: usize },
+}
+
+impl OrientationRepresentation {
+    /// Returns the length of the two orientation axes of the distribution.
+    pub fn shape(self, grid_size: GridSize) -> (usize, usize) {
+        match self {
+            OrientationRepresentation::UniformGrid | OrientationRepresentation::EqualAreaGrid => {
+                (grid_size.phi, grid_size.theta)
+            }
+            OrientationRepresentation::SphericalHarmonics { degree } => {
+                (number_of_coefficients(degree), 1)
+            }
+        }
+    }
+
+    /// Returns the integration measure of one orientation entry. The
+    /// orientational integral of a function `f` over the distribution is given
+    /// by `sum(kernel(f) * dist) * measure`.
+    pub fn measure(self, grid_size: GridSize, grid_width: GridWidth) -> Float {
+        match self {
+            // sin(theta) is already included by the cell average of the histogram
+            OrientationRepresentation::UniformGrid => grid_width.phi * grid_width.theta,
+            OrientationRepresentation::EqualAreaGrid => grid_width.phi * cos_theta_width(grid_size),
+            // quadrature weights are already included in the kernel
+            OrientationRepresentation::SphericalHarmonics { .. } => 1.,
+        }
+    }
+
+    /// Calculates the discrete kernel of a vector valued function `f` of the
+    /// polar angles `phi` and `theta` with `n` components. It returns an array
+    /// with the components along the first axis, followed by the orientation
+    /// axes of the distribution.
+    ///
+    /// For the histograms `f` is sampled at the bin centres. For spherical
+    /// harmonics `f` is projected onto the basis functions by a quadrature
+    /// that is exact for `f` band limited up to degree `degree + 3`.
+    pub fn kernel<F>(
+        self,
+        grid_size: GridSize,
+        grid_width: GridWidth,
+        n: usize,
+        f: F,
+    ) -> Array<Float, Ix3>
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix1>,
+    {
+        let (a, b) = self.shape(grid_size);
+        let mut k = Array::<Float, _>::zeros((n, a, b));
+
+        match self {
+            OrientationRepresentation::UniformGrid | OrientationRepresentation::EqualAreaGrid => {
+                // Calculate discrete angles, considering the cell centered sample points
+                // of the distribution
+                let gw_half_phi = grid_width.phi / 2.;
+                let gw_half_theta = grid_width.theta / 2.;
+                let angles_phi =
+                    Array::linspace(0. + gw_half_phi, TWOPI - gw_half_phi, grid_size.phi);
+                let angles_theta = if self == OrientationRepresentation::EqualAreaGrid {
+                    let dmu = cos_theta_width(grid_size);
+                    Array::linspace(1. - dmu / 2., -1. + dmu / 2., grid_size.theta)
+                        .mapv(Float::acos)
+                } else {
+                    Array::linspace(0. + gw_half_theta, PI - gw_half_theta, grid_size.theta)
+                };
+
+                for (mut ax1, phi) in k.axis_iter_mut(Axis(1)).zip(&angles_phi) {
+                    for (mut e, theta) in ax1.axis_iter_mut(Axis(1)).zip(&angles_theta) {
+                        e.assign(&f(*phi, *theta));
+                    }
+                }
+            }
+            OrientationRepresentation::SphericalHarmonics { degree } => {
+                let mut ylm = vec![0.; a];
+
+                for (phi, theta, w) in sphere_quadrature(degree + 2) {
+                    real_spherical_harmonics(degree, phi, theta, &mut ylm);
+                    let v = f(phi, theta);
+
+                    for (mut kc, vc) in k.outer_iter_mut().zip(v.iter()) {
+                        for (kcl, y) in kc.iter_mut().zip(&ylm) {
+                            *kcl += w * vc * y;
+                        }
+                    }
+                }
+            }
+        }
+
+        k
+    }
+}
+
+/// Returns the width of one bin in `cos(theta)` for the equal area grid.
+fn cos_theta_width(grid_size: GridSize) -> Float {
+    2. / grid_size.theta as Float
+}
+
+/// Scratch space for the flat grid indices and weights of the particles, that
+/// is reused between samplings in order to avoid allocations. It is neither
+/// serialized nor cloned.
+#[derive(Default)]
+struct CellIndexBuffer(Vec<(usize, Float)>);
+
+impl Clone for CellIndexBuffer {
+    fn clone(&self) -> Self {
+        CellIndexBuffer::default()
+    }
+}
+
+impl fmt::Debug for CellIndexBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CellIndexBuffer({})", self.0.len())
+    }
+}
+
 /// Holds a normalised sampled distribution function on a grid, assuming the
 /// sampling points to be centered in a grid cell. This means, that the value
 /// at position `x_j` (for `j=0,...,N-1`, on a grid with `N` cells and `x_0 =
@@ -33,6 +176,14 @@ pub struct Distribution {
     grid_width: GridWidth,
     box_size: BoxSize,
     grid_size: GridSize,
+    #[serde(default)]
+    orientation: OrientationRepresentation,
+    /// Strain of the box for Lees-Edwards boundary conditions. The positions
+    /// are binned in sheared coordinates, see `Position::sheared`.
+    #[serde(default)]
+    strain: Float,
+    #[serde(skip)]
+    cell_index: CellIndexBuffer,
 }
 
 type GridCoordinate = [Ix; 5];
@@ -40,13 +191,18 @@ type GridCoordinate = [Ix; 5];
 impl Distribution {
     /// Returns a zero initialised instance of Distribution.
     pub fn new(grid_size: GridSize, box_size: BoxSize) -> Distribution {
-        let grid = [
-            grid_size.x,
-            grid_size.y,
-            grid_size.z,
-            grid_size.phi,
-            grid_size.theta,
-        ];
+        Distribution::with_orientation(grid_size, box_size, OrientationRepresentation::UniformGrid)
+    }
+
+    /// Returns a zero initialised instance of Distribution, that represents
+    /// the orientations as given by `orientation`.
+    pub fn with_orientation(
+        grid_size: GridSize,
+        box_size: BoxSize,
+        orientation: OrientationRepresentation,
+    ) -> Distribution {
+        let (a, b) = orientation.shape(grid_size);
+        let grid = [grid_size.x, grid_size.y, grid_size.z, a, b];
         let grid_width = GridWidth::new(grid_size, box_size);
 
         Distribution {
@@ -54,6 +210,9 @@ impl Distribution {
             grid_width: grid_width,
             box_size: box_size,
             grid_size: grid_size,
+            orientation,
+            strain: 0.,
+            cell_index: CellIndexBuffer::default(),
         }
     }
 
@@ -72,6 +231,33 @@ impl Distribution {
         self.grid_size
     }
 
+    /// Returns the representation of the orientations
+    pub fn get_orientation(&self) -> OrientationRepresentation {
+        self.orientation
+    }
+
+    /// Sets the strain of the box for Lees-Edwards boundary conditions. All
+    /// following samplings bin the particles in sheared coordinates.
+    pub fn set_strain(&mut self, strain: Float) {
+        self.strain = strain;
+    }
+
+    /// Returns the strain of the box
+    pub fn get_strain(&self) -> Float {
+        self.strain
+    }
+
+    /// Returns the position of `p` in the coordinates of the grid, i.e.
+    /// sheared for a non-zero strain.
+    fn grid_position(&self, p: &Particle) -> Position {
+        p.position.sheared(&self.box_size, self.strain)
+    }
+
+    /// Returns the integration measure of one orientation entry
+    pub fn orientation_measure(&self) -> Float {
+        self.orientation.measure(self.grid_size, self.grid_width)
+    }
+
     pub fn dim(&self) -> (Ix, Ix, Ix, Ix, Ix) {
         self.dist.dim()
     }
@@ -109,11 +295,17 @@ impl Distribution {
         );
         debug_assert!(p.orientation.theta <= PI, "Theta is not in range> {:?}", p);
 
-        let mut gx = (p.position.x / self.grid_width.x).floor() as Ix;
-        let mut gy = (p.position.y / self.grid_width.y).floor() as Ix;
-        let mut gz = (p.position.z / self.grid_width.z).floor() as Ix;
+        let pos = self.grid_position(p);
+        let mut gx = (pos.x / self.grid_width.x).floor() as Ix;
+        let mut gy = (pos.y / self.grid_width.y).floor() as Ix;
+        let mut gz = (pos.z / self.grid_width.z).floor() as Ix;
         let mut gphi = (p.orientation.phi / self.grid_width.phi).floor() as Ix;
-        let mut gtheta = (p.orientation.theta / self.grid_width.theta).floor() as Ix;
+        let mut gtheta = match self.orientation {
+            OrientationRepresentation::EqualAreaGrid => {
+                ((1. - p.orientation.theta.cos()) / cos_theta_width(self.grid_size)).floor() as Ix
+            }
+            _ => (p.orientation.theta / self.grid_width.theta).floor() as Ix,
+        };
 
         // In some case positions at the right border are possible due to floating
         // point roundoff-errors in the modulo calculation.
@@ -143,18 +335,125 @@ impl Distribution {
     /// Initialises the distribution with a number histogram. It counts the
     /// `particles` inside a bin of the grid. Returns the overall number of
     /// particles counted.
+    #[cfg(test)]
     fn histogram_from(&mut self, particles: &[Particle]) -> usize {
-        // zero out distribution
-        for i in self.dist.iter_mut() {
-            *i = 0.0;
-        }
+        self.normalised_histogram_from(particles, |_| 1., 1.)
+    }
 
-        // build histogram
-        for p in particles {
-            let c = self.coord_to_grid(p);
-            // WARNING: Does not check boundaries at compile time!
-            self.dist[c] += 1.;
-        }
+    /// Initialises the distribution with a histogram, where every particle
+    /// counts with its `weight`, divided by `norm`. Returns the overall number
+    /// of particles counted.
+    ///
+    /// The flat grid indices of all particles are calculated and sorted in
+    /// parallel. Afterwards every bin is filled in parallel by summing the
+    /// weights of the run of its index in the sorted list. Apart from the
+    /// first call, no memory is allocated as long as the number of particles
+    /// does not grow.
+    fn normalised_histogram_from<F>(
+        &mut self,
+        particles: &[Particle],
+        weight: F,
+        norm: Float,
+    ) -> usize
+    where
+        F: Fn(&Particle) -> Float + Sync,
+    {
+        let (_, sy, sz, sphi, stheta) = self.dim();
+        let mut cells = mem::take(&mut self.cell_index).0;
+
+        cells.resize(particles.len(), (0, 0.));
+        cells
+            .par_iter_mut()
+            .zip(particles.par_iter())
+            .for_each(|(c, p)| {
+                let [gx, gy, gz, gphi, gtheta] = self.coord_to_grid(p);
+                *c = (
+                    (((gx * sy + gy) * sz + gz) * sphi + gphi) * stheta + gtheta,
+                    weight(p),
+                );
+            });
+        cells.par_sort_unstable_by_key(|c| c.0);
+
+        let dist = self
+            .dist
+            .as_slice_mut()
+            .expect("Distribution is not contiguous.");
+        let chunk_size = dist.len() / rayon::current_num_threads() + 1;
+
+        dist.par_chunks_mut(chunk_size)
+            .enumerate()
+            .for_each(|(k, chunk)| {
+                let offset = k * chunk_size;
+                let mut j = cells.partition_point(|c| c.0 < offset);
+
+                for (i, d) in chunk.iter_mut().enumerate() {
+                    let mut sum = 0.;
+                    while j < cells.len() && cells[j].0 == offset + i {
+                        sum += cells[j].1;
+                        j += 1;
+                    }
+                    *d = sum / norm;
+                }
+            });
+
+        self.cell_index = CellIndexBuffer(cells);
+
+        particles.len()
+    }
+
+    /// Sums the spherical harmonics of all `particles` inside a spatial grid
+    /// cell, each multiplied by its `weight`. Returns the overall number of
+    /// particles counted.
+    ///
+    /// Every thread sums the coefficients of its share of the particles into
+    /// its own partial distribution. The partial distributions are added up
+    /// at the end.
+    fn harmonics_from<F>(&mut self, particles: &[Particle], degree: usize, weight: F) -> usize
+    where
+        F: Fn(&Particle) -> Float + Sync,
+    {
+        let (sx, sy, sz, sc, _) = self.dim();
+        let n = sx * sy * sz * sc;
+        let min_len = particles.len() / rayon::current_num_threads() + 1;
+
+        let coefficients = particles
+            .par_iter()
+            .with_min_len(min_len)
+            .fold(
+                || (vec![0.; n], vec![0.; sc]),
+                |(mut c, mut ylm), p| {
+                    let (ix, iy, iz) =
+                        get_cell_index(&self.grid_position(p), &self.grid_width, &self.grid_size);
+                    real_spherical_harmonics(
+                        degree,
+                        p.orientation.phi,
+                        p.orientation.theta,
+                        &mut ylm,
+                    );
+                    let w = weight(p);
+
+                    let offset = ((ix * sy + iy) * sz + iz) * sc;
+                    for (c, y) in c[offset..offset + sc].iter_mut().zip(&ylm) {
+                        *c += y * w;
+                    }
+                    (c, ylm)
+                },
+            )
+            .map(|(c, _)| c)
+            .reduce(
+                || vec![0.; n],
+                |mut a, b| {
+                    for (a, b) in a.iter_mut().zip(&b) {
+                        *a += b;
+                    }
+                    a
+                },
+            );
+
+        self.dist
+            .as_slice_mut()
+            .expect("Distribution is not contiguous.")
+            .copy_from_slice(&coefficients);
 
         particles.len()
     }
@@ -162,7 +461,32 @@ impl Distribution {
     /// Estimates the approximate values for the distribution function at the
     /// grid points using grid cell averages.
     pub fn sample_from(&mut self, particles: &[Particle]) {
-        let n = self.histogram_from(particles) as Float;
+        self.sample_scaled_from(particles, 1.)
+    }
+
+    /// Same as `sample_from`, but the distribution is additionally multiplied
+    /// by `scale`. For example, scaling by the box volume results in a mean
+    /// number density of one.
+    pub fn sample_scaled_from(&mut self, particles: &[Particle], scale: Float) {
+        self.sample_weighted_from(particles, scale, |_| 1.)
+    }
+
+    /// Same as `sample_scaled_from`, but every particle contributes with its
+    /// `weight`, e.g. an attribute of a polydisperse particle. The
+    /// normalisation does not depend on the weights, i.e. the result is the
+    /// number density times the local mean of the weight.
+    pub fn sample_weighted_from<F>(&mut self, particles: &[Particle], scale: Float, weight: F)
+    where
+        F: Fn(&Particle) -> Float + Sync,
+    {
+        let n = particles.len() as Float;
+
+        if let OrientationRepresentation::SphericalHarmonics { degree } = self.orientation {
+            self.harmonics_from(particles, degree, weight);
+            let GridWidth { x, y, z, .. } = self.grid_width;
+            self.dist /= x * y * z * n / scale;
+            return;
+        }
 
         // Scale by grid cell volume, in order to arrive at a sampled function,
         // averaged over a grid cell. Missing this would result into the
@@ -177,10 +501,49 @@ impl Distribution {
             theta: gtheta,
         } = self.grid_width;
 
-        // WARNING: In principle the scaling goes with sin(theta), since the volume on
-        // the sphere surface shrinks. But this is canceld by integration over the
-        // orientation in the flow-field calculation, so skipped here
-        self.dist /= gx * gy * gz * gphi * gtheta * n;
+        // WARNING: For the uniform grid the scaling goes in principle with
+        // sin(theta), since the volume on the sphere surface shrinks. But this is
+        // canceld by integration over the orientation in the flow-field
+        // calculation, so skipped here. The bins of the equal area grid are
+        // uniform in cos(theta) and need no correction.
+        let gtheta = match self.orientation {
+            OrientationRepresentation::EqualAreaGrid => cos_theta_width(self.grid_size),
+            _ => gtheta,
+        };
+        self.normalised_histogram_from(particles, weight, gx * gy * gz * gphi * gtheta * n / scale);
+    }
+
+    /// Returns the density field, i.e. the distribution integrated over all
+    /// orientations.
+    pub fn density(&self) -> Array<Float, Ix3> {
+        let sh = self.dim();
+        let mut density = Array::zeros((sh.0, sh.1, sh.2));
+        self.density_into(density.view_mut());
+        density
+    }
+
+    /// Writes the density field into the preallocated `density`.
+    pub fn density_into(&self, density: ArrayViewMut<Float, Ix3>) {
+        match self.orientation {
+            OrientationRepresentation::UniformGrid | OrientationRepresentation::EqualAreaGrid => {
+                let sh = self.dim();
+                let n = sh.0 * sh.1 * sh.2;
+                let dist = self.dist.view().into_shape([n, sh.3 * sh.4]).unwrap();
+                let density = density.into_shape([n]).unwrap();
+                let measure = self.orientation_measure();
+
+                Zip::from(density)
+                    .and(dist.genrows())
+                    .par_apply(|d, o| *d = o.scalar_sum() * measure);
+            }
+            // Only Y_0^0 = 1 / sqrt(4 pi) contributes to the integral
+            OrientationRepresentation::SphericalHarmonics { .. } => {
+                let norm = (2. * TWOPI).sqrt();
+                Zip::from(density)
+                    .and(self.dist.slice(s![.., .., .., 0, 0]))
+                    .par_apply(|d, c| *d = c * norm);
+            }
+        }
     }
 }
 
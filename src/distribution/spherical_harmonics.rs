@@ -0,0 +1,147 @@
+//! Real, orthonormal spherical harmonics used to represent the orientational
+//! part of the distribution function.
+//!
+//! The coefficients of degree `l` and order `m` (`-l <= m <= l`) are stored in
+//! a flat array at index `l^2 + l + m`. The basis is chosen without the
+//! Condon-Shortley phase, such that the orientation vector is given by
+//! `n = sqrt(4 pi / 3) [Y_1^1, Y_1^-1, Y_1^0]`.
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./spherical_harmonics_test.rs"]
+mod spherical_harmonics_test;
+
+use crate::consts::TWOPI;
+use crate::Float;
+#[cfg(feature = "single")]
+use std::f32::consts::PI;
+#[cfg(not(feature = "single"))]
+use std::f64::consts::PI;
+
+/// Returns the number of coefficients needed for an expansion up to (and
+/// including) degree `degree`.
+pub fn number_of_coefficients(degree: usize) -> usize {
+    (degree + 1) * (degree + 1)
+}
+
+/// Returns the position of the coefficient of degree `l` and order `m` in the
+/// flat coefficient array.
+pub fn index(l: usize, m: isize) -> usize {
+    debug_assert!(m.abs() <= l as isize, "Order {} exceeds degree {}.", m, l);
+    ((l * l + l) as isize + m) as usize
+}
+
+/// Evaluates all real spherical harmonics up to degree `degree` for the polar
+/// angles `phi` and `theta` and writes them into `out`.
+///
+/// `out` must hold at least `number_of_coefficients(degree)` elements. The
+/// fully normalised associated Legendre functions are calculated by the
+/// standard three term recursion, which is stable for high degrees.
+pub fn real_spherical_harmonics(degree: usize, phi: Float, theta: Float, out: &mut [Float]) {
+    debug_assert!(out.len() >= number_of_coefficients(degree));
+
+    let (sin_theta, cos_theta) = theta.sin_cos();
+    let sqrt2 = (2. as Float).sqrt();
+
+    // recursion coefficient for the associated Legendre functions
+    let a = |l: usize, m: usize| {
+        let (l, m) = (l as Float, m as Float);
+        ((4. * l * l - 1.) / (l * l - m * m)).sqrt()
+    };
+
+    // normalised P_m^m, starting with P_0^0 = 1 / sqrt(4 pi)
+    let mut pmm = 1. / (2. * TWOPI).sqrt();
+
+    for m in 0..=degree {
+        if m > 0 {
+            pmm *= ((2 * m + 1) as Float / (2 * m) as Float).sqrt() * sin_theta;
+        }
+
+        let (sin_mphi, cos_mphi) = (m as Float * phi).sin_cos();
+        let mut store = |l: usize, p: Float| {
+            if m == 0 {
+                out[index(l, 0)] = p;
+            } else {
+                out[index(l, m as isize)] = sqrt2 * p * cos_mphi;
+                out[index(l, -(m as isize))] = sqrt2 * p * sin_mphi;
+            }
+        };
+
+        store(m, pmm);
+
+        if m == degree {
+            break;
+        }
+
+        // P_{m+1}^m
+        let mut p_lm2 = pmm;
+        let mut p_lm1 = ((2 * m + 3) as Float).sqrt() * cos_theta * pmm;
+        store(m + 1, p_lm1);
+
+        for l in (m + 2)..=degree {
+            let p = a(l, m) * (cos_theta * p_lm1 - p_lm2 / a(l - 1, m));
+            store(l, p);
+            p_lm2 = p_lm1;
+            p_lm1 = p;
+        }
+    }
+}
+
+/// Returns nodes and weights of the Gauss-Legendre quadrature with `n` points
+/// on the interval `[-1, 1]`.
+pub fn gauss_legendre(n: usize) -> (Vec<Float>, Vec<Float>) {
+    let mut nodes = vec![0.; n];
+    let mut weights = vec![0.; n];
+
+    for i in 0..n - n / 2 {
+        // initial guess by the asymptotic formula for the roots
+        let mut x = (PI * (i as Float + 0.75) / (n as Float + 0.5)).cos();
+        let mut dp = 0.;
+
+        // Newton iteration on P_n(x)
+        for _ in 0..100 {
+            let mut p0 = 1.;
+            let mut p1 = 0.;
+            for j in 0..n {
+                let p2 = p1;
+                p1 = p0;
+                p0 = ((2 * j + 1) as Float * x * p1 - j as Float * p2) / (j + 1) as Float;
+            }
+            dp = n as Float * (x * p0 - p1) / (x * x - 1.);
+
+            let dx = p0 / dp;
+            x -= dx;
+
+            if dx.abs() <= 4. * Float::EPSILON {
+                break;
+            }
+        }
+
+        nodes[i] = -x;
+        nodes[n - 1 - i] = x;
+        weights[i] = 2. / ((1. - x * x) * dp * dp);
+        weights[n - 1 - i] = weights[i];
+    }
+
+    (nodes, weights)
+}
+
+/// Quadrature on the unit sphere, that is exact for all band limited
+/// functions up to degree `2 * n_theta - 1`.
+///
+/// Returns a list of `(phi, theta, weight)` triples.
+pub fn sphere_quadrature(n_theta: usize) -> Vec<(Float, Float, Float)> {
+    let n_phi = 2 * n_theta;
+    let (nodes, weights) = gauss_legendre(n_theta);
+    let dphi = TWOPI / n_phi as Float;
+
+    let mut q = Vec::with_capacity(n_phi * n_theta);
+    for (mu, w) in nodes.iter().zip(&weights) {
+        let theta = mu.acos();
+        for j in 0..n_phi {
+            q.push((j as Float * dphi, theta, w * dphi));
+        }
+    }
+
+    q
+}
@@ -0,0 +1,77 @@
+use super::*;
+use crate::test_helper::equal_floats_eps;
+
+#[test]
+fn gauss_legendre_polynomials() {
+    let (nodes, weights) = gauss_legendre(5);
+
+    // exact for polynomials up to degree 9
+    for k in 0..10 {
+        let int: Float = nodes.iter().zip(&weights).map(|(x, w)| w * x.powi(k)).sum();
+        let expect = if k % 2 == 0 {
+            2. / (k + 1) as Float
+        } else {
+            0.
+        };
+
+        assert!(
+            equal_floats_eps(int, expect, 1e-13),
+            "Integral of x^{} is {}, but expected {}.",
+            k,
+            int,
+            expect
+        );
+    }
+}
+
+#[test]
+fn orthonormality() {
+    let degree = 6;
+    let n = number_of_coefficients(degree);
+    let mut ylm = vec![0.; n];
+    let mut overlap = vec![0.; n * n];
+
+    for (phi, theta, w) in sphere_quadrature(degree + 1) {
+        real_spherical_harmonics(degree, phi, theta, &mut ylm);
+        for i in 0..n {
+            for j in 0..n {
+                overlap[i * n + j] += w * ylm[i] * ylm[j];
+            }
+        }
+    }
+
+    for i in 0..n {
+        for j in 0..n {
+            let expect = if i == j { 1. } else { 0. };
+            assert!(
+                (overlap[i * n + j] - expect).abs() < 1e-12,
+                "<Y_{}, Y_{}> = {}, but expected {}.",
+                i,
+                j,
+                overlap[i * n + j],
+                expect
+            );
+        }
+    }
+}
+
+#[test]
+fn orientation_vector() {
+    let mut ylm = vec![0.; number_of_coefficients(1)];
+    let f = (4. * PI / 3.).sqrt();
+
+    for &(phi, theta) in &[(0., 0.), (0.3, 1.2), (4.0, 2.9), (PI, PI / 2.)] {
+        real_spherical_harmonics(1, phi, theta, &mut ylm);
+
+        let n = [
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        ];
+        let y = [ylm[index(1, 1)], ylm[index(1, -1)], ylm[index(1, 0)]];
+
+        for (n, y) in n.iter().zip(&y) {
+            assert!((n - f * y).abs() < 1e-14, "{} != {}", n, f * y);
+        }
+    }
+}
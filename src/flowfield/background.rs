@@ -0,0 +1,191 @@
+//! Imposed external flows, that are added to the self-generated mean flow
+//! field of the particles.
+//!
+//! All flows are given by their velocity `u` and vector gradient
+//! `g_ij = d_i u_j` at a particle position, matching the convention of the
+//! gradient in `SpectralSolver`.
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./background_test.rs"]
+mod background_test;
+
+use crate::consts::TWOPI;
+use crate::particle::{modulo, Position};
+use crate::vector::{Matrix3, VectorD};
+use crate::{BoxSize, Float};
+use serde_derive::{Deserialize, Serialize};
+
+/// Imposed background flow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BackgroundFlow {
+    /// No external flow.
+    #[default]
+    None,
+    /// Simple shear flow in `x` direction with gradient in `y` direction,
+    /// `u = (rate * (y - L_y / 2), 0, 0)`.
+    SimpleShear { rate: Float },
+    /// Planar extensional flow, stretching along `x` and compressing along
+    /// `y`, `u = rate * (x - L_x / 2, -(y - L_y / 2), 0)`.
+    PlanarExtension { rate: Float },
+    /// Poiseuille flow in `x` direction in a channel bounded by the planes
+    /// `z = 0` and `z = L_z`, `u = (4 max_velocity z (L_z - z) / L_z^2, 0,
+    /// 0)`. These are the planes of the no-slip walls.
+    Poiseuille { max_velocity: Float },
+    /// Single Fourier mode `u = amplitude * cos(k . x)` with
+    /// `k = 2 pi (n_x / L_x, n_y / L_y, n_z / L_z)`. The amplitude must be
+    /// perpendicular to `k`, for the flow to be incompressible.
+    FourierMode {
+        amplitude: [Float; 3],
+        wave_numbers: [i32; 3],
+    },
+}
+
+impl BackgroundFlow {
+    /// Returns the wave vector of a Fourier mode in a box of size `box_size`.
+    fn wave_vector(wave_numbers: [i32; 3], box_size: &BoxSize) -> [Float; 3] {
+        [
+            TWOPI * wave_numbers[0] as Float / box_size.x,
+            TWOPI * wave_numbers[1] as Float / box_size.y,
+            TWOPI * wave_numbers[2] as Float / box_size.z,
+        ]
+    }
+
+    /// Returns the velocity of the flow at `p`.
+    pub fn velocity(&self, p: &Position, box_size: &BoxSize) -> VectorD {
+        match *self {
+            BackgroundFlow::None => VectorD::zero(),
+            BackgroundFlow::SimpleShear { rate } => [rate * (p.y - box_size.y / 2.), 0., 0.].into(),
+            BackgroundFlow::PlanarExtension { rate } => [
+                rate * (p.x - box_size.x / 2.),
+                -rate * (p.y - box_size.y / 2.),
+                0.,
+            ]
+            .into(),
+            BackgroundFlow::Poiseuille { max_velocity } => {
+                let lz = box_size.z;
+                [4. * max_velocity * p.z * (lz - p.z) / (lz * lz), 0., 0.].into()
+            }
+            BackgroundFlow::FourierMode {
+                amplitude,
+                wave_numbers,
+            } => {
+                let k = BackgroundFlow::wave_vector(wave_numbers, box_size);
+                let c = (k[0] * p.x + k[1] * p.y + k[2] * p.z).cos();
+                [amplitude[0] * c, amplitude[1] * c, amplitude[2] * c].into()
+            }
+        }
+    }
+
+    /// Returns the vector gradient `g_ij = d_i u_j` of the flow at `p`.
+    pub fn gradient(&self, p: &Position, box_size: &BoxSize) -> Matrix3 {
+        let mut g = Matrix3::default();
+
+        match *self {
+            BackgroundFlow::None => {}
+            BackgroundFlow::SimpleShear { rate } => g[1][0] = rate,
+            BackgroundFlow::PlanarExtension { rate } => {
+                g[0][0] = rate;
+                g[1][1] = -rate;
+            }
+            BackgroundFlow::Poiseuille { max_velocity } => {
+                let lz = box_size.z;
+                g[2][0] = 4. * max_velocity * (lz - 2. * p.z) / (lz * lz);
+            }
+            BackgroundFlow::FourierMode {
+                amplitude,
+                wave_numbers,
+            } => {
+                let k = BackgroundFlow::wave_vector(wave_numbers, box_size);
+                let s = -(k[0] * p.x + k[1] * p.y + k[2] * p.z).sin();
+                for (gi, ki) in g.iter_mut().zip(&k) {
+                    for (gij, aj) in gi.iter_mut().zip(&amplitude) {
+                        *gij = ki * aj * s;
+                    }
+                }
+            }
+        }
+
+        g
+    }
+
+    /// Returns the strain rate and vorticity tensor of the flow at `p`, i.e.
+    /// the symmetric and anti-symmetric part of the vector gradient.
+    pub fn strain_vorticity(&self, p: &Position, box_size: &BoxSize) -> (Matrix3, Matrix3) {
+        let g = self.gradient(p, box_size);
+        let mut strain = Matrix3::default();
+        let mut vorticity = Matrix3::default();
+
+        for i in 0..3 {
+            for j in 0..3 {
+                strain[i][j] = (g[i][j] + g[j][i]) * 0.5;
+                vorticity[i][j] = (g[i][j] - g[j][i]) * 0.5;
+            }
+        }
+
+        (strain, vorticity)
+    }
+
+    /// Returns `true`, if the flow is incompressible in a box of size
+    /// `box_size`. Only a Fourier mode with an amplitude not perpendicular to
+    /// its wave vector is compressible.
+    pub fn is_incompressible(&self, box_size: &BoxSize) -> bool {
+        match *self {
+            BackgroundFlow::FourierMode {
+                amplitude,
+                wave_numbers,
+            } => {
+                let k = BackgroundFlow::wave_vector(wave_numbers, box_size);
+                let norm = |v: &[Float; 3]| v.iter().map(|x| x * x).sum::<Float>().sqrt();
+                let kdota: Float = k.iter().zip(&amplitude).map(|(k, a)| k * a).sum();
+                kdota.abs() <= 1e-10 * norm(&k) * norm(&amplitude)
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the strain `rate * time` of a simple shear flow for Lees-Edwards
+    /// boundary conditions, i.e. the images above the box are shifted by
+    /// `strain * L_y` in x direction. Shifting by `L_x` results in the same
+    /// periodic lattice, so the strain is wrapped into `[-L_x / (2 L_y), L_x /
+    /// (2 L_y))` to keep the sheared grid as little distorted as possible.
+    /// Returns zero for all other flows.
+    pub fn lees_edwards_strain(&self, time: Float, box_size: &BoxSize) -> Float {
+        match *self {
+            BackgroundFlow::SimpleShear { rate } => {
+                let period = box_size.x / box_size.y;
+                modulo(rate * time + period / 2., period) - period / 2.
+            }
+            _ => 0.,
+        }
+    }
+
+    /// Returns the flow in simulation units, given the characteristic
+    /// `velocity` and `time` of the simulation.
+    pub fn to_simulation_units(self, velocity: Float, time: Float) -> BackgroundFlow {
+        match self {
+            BackgroundFlow::None => BackgroundFlow::None,
+            BackgroundFlow::SimpleShear { rate } => {
+                BackgroundFlow::SimpleShear { rate: rate * time }
+            }
+            BackgroundFlow::PlanarExtension { rate } => {
+                BackgroundFlow::PlanarExtension { rate: rate * time }
+            }
+            BackgroundFlow::Poiseuille { max_velocity } => BackgroundFlow::Poiseuille {
+                max_velocity: max_velocity / velocity,
+            },
+            BackgroundFlow::FourierMode {
+                amplitude,
+                wave_numbers,
+            } => BackgroundFlow::FourierMode {
+                amplitude: [
+                    amplitude[0] / velocity,
+                    amplitude[1] / velocity,
+                    amplitude[2] / velocity,
+                ],
+                wave_numbers,
+            },
+        }
+    }
+}
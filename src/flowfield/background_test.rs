@@ -0,0 +1,153 @@
+use super::*;
+use crate::test_helper::equal_floats_eps;
+
+const BS: BoxSize = BoxSize {
+    x: 2.,
+    y: 3.,
+    z: 4.,
+};
+
+/// Compares the gradient with central finite differences of the velocity.
+fn check_gradient(flow: BackgroundFlow, p: Position) {
+    let h = 1e-6;
+    let g = flow.gradient(&p, &BS);
+
+    for (i, gi) in g.iter().enumerate() {
+        let mut pp = p;
+        let mut pm = p;
+        match i {
+            0 => {
+                pp.x += h;
+                pm.x -= h;
+            }
+            1 => {
+                pp.y += h;
+                pm.y -= h;
+            }
+            _ => {
+                pp.z += h;
+                pm.z -= h;
+            }
+        }
+        let up = flow.velocity(&pp, &BS);
+        let um = flow.velocity(&pm, &BS);
+
+        for j in 0..3 {
+            let fd = (up[j] - um[j]) / (2. * h);
+            assert!(
+                (fd - gi[j]).abs() < 1e-6,
+                "d_{} u_{} of {:?} is {}, but finite differences give {}.",
+                i,
+                j,
+                flow,
+                gi[j],
+                fd
+            );
+        }
+    }
+}
+
+#[test]
+fn gradients() {
+    let flows = [
+        BackgroundFlow::None,
+        BackgroundFlow::SimpleShear { rate: 0.3 },
+        BackgroundFlow::PlanarExtension { rate: 0.7 },
+        BackgroundFlow::Poiseuille { max_velocity: 2. },
+        BackgroundFlow::FourierMode {
+            amplitude: [0., 0.5, 1.],
+            wave_numbers: [1, 0, 0],
+        },
+        BackgroundFlow::FourierMode {
+            amplitude: [2., -3., 0.],
+            wave_numbers: [1, 1, 3],
+        },
+    ];
+    let positions = [
+        Position::new(0.1, 0.2, 0.3, &BS),
+        Position::new(1.3, 2.9, 3.5, &BS),
+    ];
+
+    for flow in &flows {
+        assert!(flow.is_incompressible(&BS), "{:?}", flow);
+        for p in &positions {
+            check_gradient(*flow, *p);
+        }
+    }
+}
+
+#[test]
+fn poiseuille() {
+    let flow = BackgroundFlow::Poiseuille { max_velocity: 2. };
+
+    // no slip at the channel walls and maximal velocity in the middle
+    assert_eq!(
+        flow.velocity(
+            &Position {
+                x: 1.,
+                y: 1.,
+                z: 0.
+            },
+            &BS
+        )[0],
+        0.
+    );
+    assert_eq!(
+        flow.velocity(
+            &Position {
+                x: 1.,
+                y: 1.,
+                z: 4.
+            },
+            &BS
+        )[0],
+        0.
+    );
+    assert!(equal_floats_eps(
+        flow.velocity(
+            &Position {
+                x: 1.,
+                y: 1.,
+                z: 2.
+            },
+            &BS
+        )[0],
+        2.,
+        1e-15
+    ));
+}
+
+#[test]
+fn strain_vorticity() {
+    let flow = BackgroundFlow::SimpleShear { rate: 2. };
+    let p = Position::new(0.1, 0.2, 0.3, &BS);
+    let (e, w) = flow.strain_vorticity(&p, &BS);
+
+    assert_eq!(e, [[0., 1., 0.], [1., 0., 0.], [0., 0., 0.]]);
+    assert_eq!(w, [[0., -1., 0.], [1., 0., 0.], [0., 0., 0.]]);
+}
+
+#[test]
+fn compressible_fourier_mode() {
+    let flow = BackgroundFlow::FourierMode {
+        amplitude: [1., 0., 0.],
+        wave_numbers: [1, 0, 0],
+    };
+    assert!(!flow.is_incompressible(&BS));
+}
+
+#[test]
+fn lees_edwards_strain() {
+    let flow = BackgroundFlow::SimpleShear { rate: 0.5 };
+    // one period of the strain is L_x / L_y = 2 / 3
+    let input = [0., 0.5, 1., 1.6];
+    let expect = [0., 0.25, -1. / 6., 0.8 - 2. / 3.];
+
+    for (t, e) in input.iter().zip(&expect) {
+        let s = flow.lees_edwards_strain(*t, &BS);
+        assert!(equal_floats_eps(s, *e, 1e-14), "{} != {}", s, e);
+    }
+
+    let flow = BackgroundFlow::PlanarExtension { rate: 0.5 };
+    assert_eq!(flow.lees_edwards_strain(1., &BS), 0.);
+}
@@ -3,5 +3,9 @@ use ndarray::{Array, Ix4};
 
 pub type FlowField3D = Array<Float, Ix4>;
 
+pub mod background;
+pub mod regularisation;
+pub mod screening;
 pub mod spectral_solver;
 pub mod stress;
+pub mod wall_solver;
@@ -0,0 +1,74 @@
+//! Regularisation of the forces, that the particles exert on the fluid.
+//!
+//! The flow of a point force, the Oseen tensor, diverges at the position of
+//! the force. On the grid, it is only cut off by the grid width, so the flow
+//! at a particle depends on the resolution, see `integrators::langevin`. A
+//! finite size of the force distribution replaces this cutoff by a length,
+//! that is independent of the grid. The regularisation is a filter in
+//! Fourier space, which is applied to the stress field and the body force.
+
+use crate::Float;
+use serde_derive::{Deserialize, Serialize};
+
+/// Distribution of the force of a particle, which multiplies the Fourier
+/// modes of the force density with `filter(k^2)`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ForceRegularisation {
+    /// Point forces, which are only regularised by the grid.
+    #[default]
+    None,
+    /// The force is spread over a Gaussian with the standard deviation
+    /// `width` and the flow is averaged over the same Gaussian, i.e. the
+    /// filter is `exp(-k^2 width^2)`.
+    Gaussian { width: Float },
+    /// The force is spread over the surface of a sphere with radius `radius`
+    /// and the flow is averaged over the same surface, i.e. the filter is
+    /// `(sin(k radius) / (k radius))^2`. Like the Rotne-Prager tensor, the
+    /// flow of distant particles is the Oseen tensor with its finite size
+    /// correction.
+    RotnePrager { radius: Float },
+}
+
+impl ForceRegularisation {
+    /// Returns true, if the size of the force distribution is positive.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            ForceRegularisation::None => true,
+            ForceRegularisation::Gaussian { width } => width > 0.,
+            ForceRegularisation::RotnePrager { radius } => radius > 0.,
+        }
+    }
+
+    /// Returns the factor of the Fourier mode with the squared wave number
+    /// `k2`, which is one for `k = 0`.
+    pub fn filter(&self, k2: Float) -> Float {
+        match *self {
+            ForceRegularisation::None => 1.,
+            ForceRegularisation::Gaussian { width } => (-k2 * width * width).exp(),
+            ForceRegularisation::RotnePrager { radius } => {
+                let x = k2.sqrt() * radius;
+                if x == 0. {
+                    1.
+                } else {
+                    let sinc = x.sin() / x;
+                    sinc * sinc
+                }
+            }
+        }
+    }
+
+    /// Returns the regularisation in simulation units, given the
+    /// characteristic `length` of the simulation.
+    pub fn to_simulation_units(self, length: Float) -> ForceRegularisation {
+        match self {
+            ForceRegularisation::None => ForceRegularisation::None,
+            ForceRegularisation::Gaussian { width } => ForceRegularisation::Gaussian {
+                width: width / length,
+            },
+            ForceRegularisation::RotnePrager { radius } => ForceRegularisation::RotnePrager {
+                radius: radius / length,
+            },
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Screening of the hydrodynamic interactions, e.g. by a porous medium, in
+//! which the particles swim.
+
+use crate::Float;
+use serde_derive::{Deserialize, Serialize};
+
+/// Screening of the Green's function `1 / k^2` of the flow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum HydroScreening {
+    /// Unscreened Stokes flow.
+    #[default]
+    None,
+    /// Flow through a porous medium, which obeys the Brinkman equation
+    /// `grad p = lap u - u / length^2 + f`. The Green's function becomes
+    /// `1 / (k^2 + 1 / length^2)`, i.e. hydrodynamic interactions are
+    /// screened beyond the screening length `length`.
+    Brinkman { length: Float },
+    /// Adds the constant `screening` to the Green's function `1 / k^2`. This
+    /// is the former `hydro_screening`, which does not correspond to a
+    /// physical screening and is only kept to reproduce old simulations.
+    Legacy { screening: Float },
+}
+
+impl HydroScreening {
+    /// Returns true, if the screening length is positive.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            HydroScreening::Brinkman { length } => length > 0.,
+            _ => true,
+        }
+    }
+
+    /// Returns true for the Brinkman equation, which is not supported by
+    /// every solver.
+    pub fn is_brinkman(&self) -> bool {
+        matches!(self, HydroScreening::Brinkman { .. })
+    }
+
+    /// Returns the screened Green's function for the unscreened one `green`
+    /// and the inverse squared wave number `ik2 = 1 / k^2`. By convention,
+    /// `ik2` vanishes for `k = 0`, so the screened Green's function does not
+    /// drive a mean flow.
+    pub fn green(&self, green: Float, ik2: Float) -> Float {
+        match *self {
+            HydroScreening::None => green,
+            HydroScreening::Brinkman { length } => green / (1. + ik2 / (length * length)),
+            HydroScreening::Legacy { screening } => green + screening,
+        }
+    }
+
+    /// Returns the screening in simulation units, given the characteristic
+    /// `length` of the simulation.
+    pub fn to_simulation_units(self, length: Float) -> HydroScreening {
+        match self {
+            HydroScreening::Brinkman { length: l } => {
+                HydroScreening::Brinkman { length: l / length }
+            }
+            s => s,
+        }
+    }
+}
@@ -3,18 +3,28 @@
 #[path = "./spectral_solver_test.rs"]
 mod spectral_solver_test;
 
-use crate::distribution::Distribution;
-use crate::flowfield::stress::{average_stress, stress_kernel};
+use crate::distribution::{Distribution, OrientationRepresentation};
+use crate::flowfield::regularisation::ForceRegularisation;
+use crate::flowfield::screening::HydroScreening;
+use crate::flowfield::stress::{
+    add_average_stress_of_species, average_stress, average_stress_of_species, stress_kernel,
+    MagneticDipoleStress,
+};
 use crate::flowfield::FlowField3D;
-use crate::mesh::fft_helper::{get_inverse_norm_squared, get_k_mesh, get_norm_k_mesh};
+use crate::mesh::fft_helper::{
+    get_filter_mesh, get_inverse_norm_squared, get_k_mesh, get_norm_k_mesh, get_sheared_k_mesh,
+    get_sheared_norm_k_mesh, SpectralFilter,
+};
 use crate::mesh::grid_width::GridWidth;
+use crate::vector::Matrix3;
 use crate::Float;
-use crate::{BoxSize, GridSize};
+use crate::{BoxSize, Dimensionality, GridSize};
 use fftw3::fft;
 use fftw3::fft::FFTPlan;
-use ndarray::{Array, ArrayView, Axis, Ix2, Ix3, Ix4, Ix5, Zip};
+use ndarray::{Array, ArrayView, ArrayViewMut, Axis, Ix2, Ix3, Ix4, Ix5, Zip};
 use ndarray_parallel::prelude::*;
 use num_complex::Complex;
+use std::slice;
 use std::sync::Arc;
 
 pub struct SpectralSolver {
@@ -24,13 +34,55 @@ pub struct SpectralSolver {
     k_invnormsquared: Array<Complex<Float>, Ix3>,
     k_mesh: Array<Complex<Float>, Ix4>,
     k_normed_mesh: Array<Complex<Float>, Ix4>,
-    stress_kernel: Array<Float, Ix4>,
+    /// stress kernel of every particle species
+    stress_kernels: Vec<Array<Float, Ix4>>,
+    /// stress kernels, that are averaged with weighted distributions, see
+    /// `add_weighted_stress`
+    weighted_stress_kernels: Vec<Array<Float, Ix4>>,
     stress_field: Array<Complex<Float>, Ix5>,
+    /// stress of the magnetic dipole-dipole interaction, if switched on
+    magnetic_dipole_stress: Option<MagneticDipoleStress>,
+    /// force per particle acting on the fluid, e.g. the weight of sedimenting
+    /// particles
+    body_force: [Float; 3],
+    /// density of a single species and FFT of the density of all species,
+    /// only used with a body force
+    species_density: Array<Float, Ix3>,
+    density: Array<Complex<Float>, Ix3>,
+    /// finite size of the force distribution of a particle
+    regularisation: ForceRegularisation,
+    /// factors of the spectral filter of every mode, see `set_filter`
+    filter: Array<Float, Ix3>,
     gradient_meanf: Array<Complex<Float>, Ix5>,
+    strain: Array<Matrix3, Ix3>,
+    vorticity: Array<Matrix3, Ix3>,
+    grid_size: GridSize,
+    box_size: BoxSize,
+    orientation: OrientationRepresentation,
+    dimensionality: Dimensionality,
 }
 
 impl SpectralSolver {
     pub fn new<F>(grid_size: GridSize, box_size: BoxSize, stress: F) -> SpectralSolver
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        SpectralSolver::with_orientation(
+            grid_size,
+            box_size,
+            OrientationRepresentation::UniformGrid,
+            stress,
+        )
+    }
+
+    /// Returns a solver for distributions, that represent the orientations as
+    /// given by `orientation`.
+    pub fn with_orientation<F>(
+        grid_size: GridSize,
+        box_size: BoxSize,
+        orientation: OrientationRepresentation,
+        stress: F,
+    ) -> SpectralSolver
     where
         F: Fn(Float, Float) -> Array<Float, Ix2>,
     {
@@ -59,12 +111,162 @@ impl SpectralSolver {
             k_mesh: mesh,
             k_normed_mesh: get_norm_k_mesh(grid_size, box_size),
             flow_field: Array::zeros((3, grid_size.x, grid_size.y, grid_size.z)),
-            stress_kernel: stress_kernel(grid_size, grid_width, stress),
+            stress_kernels: vec![stress_kernel(grid_size, grid_width, orientation, stress)],
             fft_plan_forward: Arc::new(plan_stress),
             fft_plan_backward: Arc::new(plan_ff),
             stress_field: Array::zeros((3, 3, grid_size.x, grid_size.y, grid_size.z)),
+            weighted_stress_kernels: Vec::new(),
+            magnetic_dipole_stress: None,
+            body_force: [0.; 3],
+            species_density: Array::zeros((grid_size.x, grid_size.y, grid_size.z)),
+            density: Array::zeros((grid_size.x, grid_size.y, grid_size.z)),
+            regularisation: ForceRegularisation::None,
+            filter: get_filter_mesh(grid_size, SpectralFilter::None),
             gradient_meanf: Array::default([3, 3, grid_size.x, grid_size.y, grid_size.z]),
+            strain: Array::default([grid_size.x, grid_size.y, grid_size.z]),
+            vorticity: Array::default([grid_size.x, grid_size.y, grid_size.z]),
+            grid_size,
+            box_size,
+            orientation,
+            dimensionality: Dimensionality::ThreeD,
+        }
+    }
+
+    /// Adds another particle species with the stress `stress`. The stress of
+    /// the species given on construction comes first. Use
+    /// `mean_flow_field_of_species` to obtain the flow field of the mixture.
+    pub fn add_species<F>(&mut self, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.stress_kernels.push(stress_kernel(
+            self.grid_size,
+            grid_width,
+            self.orientation,
+            stress,
+        ));
+    }
+
+    /// Replaces the stress of the species with index `species`, e.g. for
+    /// time-dependent parameters.
+    pub fn set_species_stress<F>(&mut self, species: usize, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.stress_kernels[species] =
+            stress_kernel(self.grid_size, grid_width, self.orientation, stress);
+    }
+
+    /// Adds another stress `stress`, which is averaged with its own
+    /// distribution instead of the distribution of a species. The
+    /// distributions are passed to `mean_flow_field_of_weighted_species` in
+    /// the order of the stresses. They are weighted by a property, that varies
+    /// between the particles of a species, e.g. the magnetic moment of
+    /// polydisperse particles. Weighted distributions do not contribute to
+    /// the density.
+    pub fn add_weighted_stress<F>(&mut self, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.weighted_stress_kernels.push(stress_kernel(
+            self.grid_size,
+            grid_width,
+            self.orientation,
+            stress,
+        ));
+    }
+
+    /// Replaces the weighted stress with index `index`, see
+    /// `add_weighted_stress`.
+    pub fn set_weighted_stress<F>(&mut self, index: usize, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.weighted_stress_kernels[index] =
+            stress_kernel(self.grid_size, grid_width, self.orientation, stress);
+    }
+
+    /// Adds the antisymmetric stress of the torque, that the mean magnetic
+    /// field of the dipole-dipole interaction exerts on the particles, with
+    /// the prefactor `prefactor`. The relative magnetic moments `moments` of
+    /// the species are the same as for
+    /// `MagneticSolver::mean_magnetic_field_of_species`, whose field has to
+    /// be passed to `set_magnetic_field` before every flow field calculation.
+    /// Calling it again only changes the prefactor and the moments.
+    pub fn set_magnetic_dipole_stress(&mut self, prefactor: Float, moments: Vec<Float>) {
+        if let Some(m) = &mut self.magnetic_dipole_stress {
+            m.set_prefactor(prefactor, moments);
+            return;
         }
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.magnetic_dipole_stress = Some(MagneticDipoleStress::new(
+            self.grid_size,
+            grid_width,
+            self.orientation,
+            prefactor,
+            moments,
+        ));
+    }
+
+    /// Sets the mean magnetic field in real space and the distributions of
+    /// the species, whose magnetisation enters the stress of the magnetic
+    /// dipole-dipole interaction. Does nothing, if the stress is switched
+    /// off.
+    pub fn set_magnetic_field(
+        &mut self,
+        field: ArrayView<Complex<Float>, Ix4>,
+        dists: &[Distribution],
+    ) {
+        if let Some(m) = &mut self.magnetic_dipole_stress {
+            m.set_field(field, dists);
+        }
+    }
+
+    /// Sets the dimensionality of the system. For `TwoD` and `QuasiTwoD` the
+    /// grid has to consist of a single cell in z direction.
+    ///
+    /// A quasi-2D film is embedded in a 3D fluid. Hence, the in-plane Green's
+    /// function is the Oseen tensor integrated over `k_z`, i.e. `(1 - k k /
+    /// 2k^2) / 2k`. The box size in z direction is taken as the thickness of
+    /// the film, which converts the stress per volume into a stress per area.
+    pub fn set_dimensionality(&mut self, dimensionality: Dimensionality) {
+        self.dimensionality = dimensionality;
+    }
+
+    /// Sets the force `force` per particle, that acts on the fluid in addition
+    /// to the stress of the particles. The force density is given by the
+    /// density of all species times `force`. Its mean is balanced by a
+    /// pressure gradient, e.g. due to the bottom of the container for
+    /// sedimenting particles, so it does not drive a mean flow.
+    pub fn set_body_force(&mut self, force: [Float; 3]) {
+        self.body_force = force;
+    }
+
+    /// Sets the finite size of the force distribution of a particle, which
+    /// filters the stress field and the body force in Fourier space.
+    pub fn set_regularisation(&mut self, regularisation: ForceRegularisation) {
+        self.regularisation = regularisation;
+    }
+
+    /// Sets the spectral filter `filter`, which is applied to the flow field
+    /// and hence to its gradient.
+    pub fn set_filter(&mut self, filter: SpectralFilter) {
+        self.filter = get_filter_mesh(self.grid_size, filter);
+    }
+
+    /// Sets the strain of the box for Lees-Edwards boundary conditions. The
+    /// distribution is expected to be sampled in sheared coordinates with the
+    /// same strain, see `Distribution::set_strain`. All fields are returned in
+    /// sheared coordinates as well.
+    pub fn set_strain(&mut self, strain: Float) {
+        let mesh = get_sheared_k_mesh(self.grid_size, self.box_size, strain);
+        self.k_invnormsquared = get_inverse_norm_squared(mesh.view());
+        self.k_mesh = mesh;
+        self.k_normed_mesh = get_sheared_norm_k_mesh(self.grid_size, self.box_size, strain);
     }
 
     /// Calculate flow field by convolving the Green's function of the stokes
@@ -85,11 +287,11 @@ impl SpectralSolver {
     #[deprecated(since = "1.3.0", note = "please use `update_flow_field` instead")]
     pub fn solve_flow_field(&mut self, dist: &Distribution) -> FlowField3D {
         let dist_sh = dist.dim();
-        let stress_sh = self.stress_kernel.dim();
+        let stress_sh = self.stress_kernels[0].dim();
         let n_stress = stress_sh.0 * stress_sh.1;
 
         let mut stress_field = self.stress_field.view_mut();
-        stress_field = average_stress(stress_field, &self.stress_kernel.view(), dist);
+        stress_field = average_stress(stress_field, &self.stress_kernels[0].view(), dist);
 
         // calculate FFT of averaged stress field
         let mut stress_field = stress_field
@@ -130,18 +332,74 @@ impl SpectralSolver {
         u.map(|v| v.re / norm)
     }
 
-    pub fn fft_mean_flow_field(&mut self, screening: Float, dist: &Distribution) {
-        let dist_sh = dist.dim();
-        let stress_sh = self.stress_kernel.dim();
-        let n_stress = stress_sh.0 * stress_sh.1;
-        let n = dist_sh.0 * dist_sh.1 * dist_sh.2;
+    pub fn fft_mean_flow_field(&mut self, screening: HydroScreening, dist: &Distribution) {
+        self.fft_mean_flow_field_of_species(screening, slice::from_ref(dist));
+    }
 
-        let mut stress_field = self.stress_field.view_mut();
-        stress_field = average_stress(stress_field, &self.stress_kernel.view(), dist);
+    /// Same as `fft_mean_flow_field`, but for the distributions of all
+    /// particle species in the order of their stress kernels.
+    pub fn fft_mean_flow_field_of_species(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+    ) {
+        self.fft_mean_flow_field_of_weighted_species(screening, dists, &[]);
+    }
+
+    /// Same as `fft_mean_flow_field_of_species`, but with the distributions
+    /// `weighted` of the weighted stresses, see `add_weighted_stress`.
+    pub fn fft_mean_flow_field_of_weighted_species(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+        weighted: &[Distribution],
+    ) {
+        average_stress_of_species(self.stress_field.view_mut(), &self.stress_kernels, dists);
+        add_average_stress_of_species(
+            self.stress_field.view_mut(),
+            &self.weighted_stress_kernels,
+            weighted,
+        );
+        if let Some(m) = &self.magnetic_dipole_stress {
+            m.add_to(self.stress_field.view_mut());
+        }
+        if self.body_force != [0.; 3] {
+            self.fft_density(dists);
+        }
+        self.solve_stress(screening);
+    }
+
+    /// Calculates the FFT of the density of all species and stores it in
+    /// `self.density`.
+    fn fft_density(&mut self, dists: &[Distribution]) {
+        self.density.fill(Complex::new(0., 0.));
+        for d in dists {
+            d.density_into(self.species_density.view_mut());
+            Zip::from(&mut self.density)
+                .and(&self.species_density)
+                .par_apply(|d, s| *d += s);
+        }
+
+        self.fft_plan_forward
+            .reexecute3d(&mut self.density.view_mut());
+    }
+
+    /// Calculates the FFT of the flow field for the stress field, which is
+    /// stored in `self.stress_field`.
+    ///
+    /// Panics for the Brinkman screening of a quasi-2D film, which is not
+    /// implemented.
+    fn solve_stress(&mut self, screening: HydroScreening) {
+        let gs = self.grid_size;
+        let stress_sh = self.stress_kernels[0].dim();
+        let n_stress = stress_sh.0 * stress_sh.1;
+        let n = gs.x * gs.y * gs.z;
 
         // calculate FFT of averaged stress field
-        let mut stress_field = stress_field
-            .into_shape((n_stress, dist_sh.0, dist_sh.1, dist_sh.2))
+        let mut stress_field = self
+            .stress_field
+            .view_mut()
+            .into_shape((n_stress, gs.x, gs.y, gs.z))
             .unwrap();
 
         let fft = &self.fft_plan_forward;
@@ -168,24 +426,67 @@ impl SpectralSolver {
 
         let norm = n as Float;
 
+        let density = self.density.view();
+        let density = density.into_shape([n]).unwrap();
+        let force = self.body_force;
+        let regularisation = self.regularisation;
+
+        let quasi2d = self.dimensionality == Dimensionality::QuasiTwoD;
+        let thickness = self.box_size.z;
+        assert!(
+            !(quasi2d && screening.is_brinkman()),
+            "Brinkman screening is not implemented for quasi-2D films."
+        );
+
         Zip::from(ff.axis_iter_mut(Axis(1)))
             .and(stress_field.axis_iter(Axis(2)))
             .and(k.axis_iter(Axis(1)))
             .and(knormed.axis_iter(Axis(1)))
             .and(ik2.outer_iter())
-            .par_apply(|mut ff, s, k, kn, ik2| {
+            .and(density.outer_iter())
+            .par_apply(|mut ff, s, k, kn, ik2, density| {
                 // trick needed, because Zip cannot iterate over scalar array yet
                 let ik2 = unsafe { *ik2.as_ptr() };
-
-                let mut sigmak = s.dot(&k);
-                let ksigmak = kn.dot(&sigmak);
-
-                let kksigmak = &kn * ksigmak;
-                sigmak -= &kksigmak;
-                sigmak *= (ik2 + screening) / norm;
-                sigmak *= Complex::new(0., 1.);
-                ff.assign(&sigmak)
+                let density = unsafe { *density.as_ptr() };
+
+                // work on the stack to avoid allocations for every grid point
+                let mut sigmak = [Complex::new(0., 0.); 3];
+                for (i, sk) in sigmak.iter_mut().enumerate() {
+                    for j in 0..3 {
+                        *sk += s[[i, j]] * k[j];
+                    }
+                }
+
+                // The body force enters like the stress divergence `i k .
+                // sigma`. Its mean (k = 0) is balanced by a pressure gradient.
+                if ik2.re != 0. {
+                    for (sk, f) in sigmak.iter_mut().zip(&force) {
+                        *sk -= density * f * Complex::new(0., 1.);
+                    }
+                }
+
+                let mut ksigmak = Complex::new(0., 0.);
+                for (kn, sk) in kn.iter().zip(&sigmak) {
+                    ksigmak += kn * sk;
+                }
+
+                let (green, projection) = if quasi2d {
+                    (ik2.re.sqrt() * thickness / 2., 0.5)
+                } else {
+                    (ik2.re, 1.)
+                };
+                let k2: Float = k.iter().map(|k| k.norm_sqr()).sum();
+                let green = screening.green(green, ik2.re) * regularisation.filter(k2);
+
+                for ((ff, sk), kn) in ff.iter_mut().zip(&sigmak).zip(kn.iter()) {
+                    *ff = (sk - kn * ksigmak * projection) * (green / norm) * Complex::new(0., 1.);
+                }
             });
+
+        // The flow is linear in the stress and the body force, so filtering
+        // it is the same as filtering both.
+        let filter = self.filter.view().into_shape([n]).unwrap();
+        ff.zip_mut_with(&filter, |u, f| *u *= f);
     }
 
     /// Returns vector gradient of flow field.
@@ -193,12 +494,8 @@ impl SpectralSolver {
         let sh = self.flow_field.dim();
         let n = sh.1 * sh.2 * sh.3;
 
-        // Construct an outer product by making use of broadcasting
+        // Construct the outer product ab_ij = a_i b_j of
         // a = [a1, a2, a3], b = [b1, b2, b3]
-        // ab =
-        // [[a1, a1, a1],    [[b1, b2, b3]
-        //  [a2, a2, a2], .*  [b1, b2, b3]
-        //  [a3, a3, a3]]     [b1, b2, b3]]
 
         let k = self.k_mesh.view();
         let k = k.into_shape([3, n]).unwrap();
@@ -214,10 +511,9 @@ impl SpectralSolver {
             .and(k.axis_iter(Axis(1)))
             .and(b.axis_iter(Axis(1)))
             .par_apply(|mut g, k, b| {
-                let k = k.broadcast([3, 3]).unwrap();
-                let k = k.t();
-                let e = (&k * &b) * Complex::new(0., 1.);
-                g.assign(&e)
+                for ((i, j), g) in g.indexed_iter_mut() {
+                    *g = k[i] * b[j] * Complex::new(0., 1.);
+                }
             });
 
         let mut g = g.into_shape([9, sh.1, sh.2, sh.3]).unwrap();
@@ -228,18 +524,55 @@ impl SpectralSolver {
             .for_each(|mut v| fft.reexecute3d(&mut v));
     }
 
+    /// Splits the real part of the vector gradient into its symmetric (strain
+    /// rate) and anti-symmetric (vorticity) part.
+    fn update_strain_vorticity(&mut self) {
+        split_gradient(
+            self.gradient_meanf.view(),
+            self.strain.view_mut(),
+            self.vorticity.view_mut(),
+        );
+    }
+
     /// Given a distribution `d`, it returns a view into the mean magnetic
     /// field and the (flattened) vector gradient field of it.
     pub fn mean_flow_field(
         &mut self,
-        screening: Float,
+        screening: HydroScreening,
         d: &Distribution,
     ) -> (
         ArrayView<Complex<Float>, Ix4>,
         ArrayView<Complex<Float>, Ix5>,
+    ) {
+        self.mean_flow_field_of_species(screening, slice::from_ref(d))
+    }
+
+    /// Same as `mean_flow_field`, but for a mixture of particle species with
+    /// the distributions `dists`, see `add_species`.
+    pub fn mean_flow_field_of_species(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+    ) -> (
+        ArrayView<'_, Complex<Float>, Ix4>,
+        ArrayView<'_, Complex<Float>, Ix5>,
+    ) {
+        self.mean_flow_field_of_weighted_species(screening, dists, &[])
+    }
+
+    /// Same as `mean_flow_field_of_species`, but with the distributions
+    /// `weighted` of the weighted stresses, see `add_weighted_stress`.
+    pub fn mean_flow_field_of_weighted_species(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+        weighted: &[Distribution],
+    ) -> (
+        ArrayView<'_, Complex<Float>, Ix4>,
+        ArrayView<'_, Complex<Float>, Ix5>,
     ) {
         // calculate FFT of of flow field, which is stored in self.flow_field
-        self.fft_mean_flow_field(screening, d);
+        self.fft_mean_flow_field_of_weighted_species(screening, dists, weighted);
         // use stored value of FFT of magnetic field to calculate vector gradient and
         // store it in self.gradient_meanb
         self.update_gradient();
@@ -250,10 +583,56 @@ impl SpectralSolver {
             .into_par_iter()
             .for_each(|mut v| fft.reexecute3d(&mut v));
 
+        self.update_strain_vorticity();
+
         (self.flow_field.view(), self.gradient_meanf.view())
     }
 
+    /// Returns a view into the flow field, as calculated by the last call of
+    /// `mean_flow_field`.
+    pub fn get_flow_field(&self) -> ArrayView<'_, Complex<Float>, Ix4> {
+        self.flow_field.view()
+    }
+
+    /// Returns views into the strain rate and vorticity tensor fields of the
+    /// flow field, as calculated by the last call of `mean_flow_field`.
+    pub fn get_strain_vorticity(
+        &self,
+    ) -> (ArrayView<'_, Matrix3, Ix3>, ArrayView<'_, Matrix3, Ix3>) {
+        (self.strain.view(), self.vorticity.view())
+    }
+
     pub fn get_real_flow_field(&self) -> Array<Float, Ix4> {
         self.flow_field.map(|v| v.re)
     }
 }
+
+/// Splits the real part of the vector gradient `g` into its symmetric (strain
+/// rate) and anti-symmetric (vorticity) part. The results are stored in
+/// fixed-size matrices per grid cell.
+pub(crate) fn split_gradient(
+    g: ArrayView<Complex<Float>, Ix5>,
+    strain: ArrayViewMut<Matrix3, Ix3>,
+    vorticity: ArrayViewMut<Matrix3, Ix3>,
+) {
+    let sh = g.dim();
+    let n = sh.2 * sh.3 * sh.4;
+
+    let g = g.into_shape([9, n]).unwrap();
+    let strain = strain.into_shape([n]).unwrap();
+    let vorticity = vorticity.into_shape([n]).unwrap();
+
+    Zip::from(strain)
+        .and(vorticity)
+        .and(g.axis_iter(Axis(1)))
+        .par_apply(|e, w, g| {
+            for i in 0..3 {
+                for j in 0..3 {
+                    let gij = g[3 * i + j].re;
+                    let gji = g[3 * j + i].re;
+                    e[i][j] = (gij + gji) * 0.5;
+                    w[i][j] = (gij - gji) * 0.5;
+                }
+            }
+        });
+}
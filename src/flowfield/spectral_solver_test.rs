@@ -4,6 +4,7 @@ use super::*;
 
 use crate::distribution::Distribution;
 use crate::particle::Particle;
+use itertools::iproduct;
 // use test::Bencher;
 use crate::test_helper::equal_floats;
 use crate::Float;
@@ -45,7 +46,7 @@ fn test_calculate_flow_field_against_cache() {
     d.sample_from(&p);
     d.dist *= bs.x * bs.y * bs.z;
 
-    let (ff, _) = ff_s.mean_flow_field(1., &d);
+    let (ff, _) = ff_s.mean_flow_field(HydroScreening::Legacy { screening: 1. }, &d);
     let ff = ff.map(|v| v.re);
 
     // let mut f = File::create("test/flowfield/ff_test.bincode").unwrap();
@@ -173,7 +174,7 @@ fn test_compare_implementations() {
     d.sample_from(&p);
 
     let ff = ff_s.solve_flow_field(&d);
-    let (ff_new, _) = ff_s.mean_flow_field(0., &d);
+    let (ff_new, _) = ff_s.mean_flow_field(HydroScreening::None, &d);
     let ff_new = ff_new.map(|v| v.re);
 
     for (a, b) in ff.indexed_iter().zip(ff_new.indexed_iter()) {
@@ -189,6 +190,457 @@ fn test_compare_implementations() {
     }
 }
 
+#[test]
+fn test_strain_vorticity() {
+    let bs = BoxSize {
+        x: 5.,
+        y: 5.,
+        z: 5.,
+    };
+    let gs = GridSize {
+        x: 6,
+        y: 6,
+        z: 6,
+        phi: 6,
+        theta: 6,
+    };
+
+    let mut ff_s = SpectralSolver::new(gs, bs, stress_active);
+
+    let p = Particle::create_isotropic(1000, &bs, 1);
+    let mut d = Distribution::new(gs, bs);
+    d.sample_from(&p);
+
+    let grad = ff_s
+        .mean_flow_field(HydroScreening::None, &d)
+        .1
+        .map(|v| v.re);
+    let (strain, vort) = ff_s.get_strain_vorticity();
+
+    for ((x, y, z), e) in strain.indexed_iter() {
+        let w = &vort[[x, y, z]];
+        for i in 0..3 {
+            for j in 0..3 {
+                assert_eq!(e[i][j], e[j][i]);
+                assert_eq!(w[i][j], -w[j][i]);
+                // symmetric and anti-symmetric part add up to the gradient
+                let (gij, gji) = (grad[[i, j, x, y, z]], grad[[j, i, x, y, z]]);
+                let diff = (e[i][j] + w[i][j] - gij).abs();
+                assert!(
+                    diff <= 1e-15 * (gij.abs() + gji.abs()),
+                    "{} != {}",
+                    e[i][j] + w[i][j],
+                    gij
+                );
+            }
+        }
+    }
+}
+
+/// Compares the flow of a planar stress mode `cos(k x)` in 2D and in a
+/// quasi-2D film, for which the force is partly longitudinal.
+#[test]
+fn test_planar_dimensionality() {
+    let bs = BoxSize {
+        x: 4.,
+        y: 3.,
+        z: 0.5,
+    };
+    let gs = GridSize {
+        x: 8,
+        y: 3,
+        z: 1,
+        phi: 6,
+        theta: 1,
+    };
+    let k = 2. * PI / bs.x;
+
+    for &dim in &[Dimensionality::TwoD, Dimensionality::QuasiTwoD] {
+        let mut ff_s = SpectralSolver::new(gs, bs, stress_active);
+        ff_s.set_dimensionality(dim);
+
+        for ((i, j, ix, _, _), s) in ff_s.stress_field.indexed_iter_mut() {
+            let x = (ix as Float + 0.5) * bs.x / gs.x as Float;
+            *s = match (i, j) {
+                (0, 0) | (0, 1) | (1, 0) => Complex::new((k * x).cos(), 0.),
+                _ => Complex::new(0., 0.),
+            };
+        }
+        ff_s.solve_stress(HydroScreening::None);
+        let fft = &ff_s.fft_plan_backward;
+        for mut v in ff_s.flow_field.outer_iter_mut() {
+            fft.reexecute3d(&mut v);
+        }
+
+        // force density f = -k sin(k x) (1, 1, 0)
+        let (ux, uy) = match dim {
+            Dimensionality::QuasiTwoD => (-bs.z / 4., -bs.z / 2.),
+            _ => (0., -1. / k),
+        };
+        for ((c, ix, _, _), u) in ff_s.flow_field.indexed_iter() {
+            let x = (ix as Float + 0.5) * bs.x / gs.x as Float;
+            let expect = [ux, uy, 0.][c] * (k * x).sin();
+            assert!(
+                (u - expect).norm() < 1e-12,
+                "{:?}: u_{} = {} != {}",
+                dim,
+                c,
+                u,
+                expect
+            );
+        }
+    }
+}
+
+/// Solves the flow of the body force `-force e_z` acting on the density
+/// `1 + cos(k x)` in a 4x3x2 box on a 8x3x2 grid, with `k = 2 pi / L_x`. The
+/// mean force does not drive any flow, while the density modulation drives the
+/// shear flow `-force green(k) cos(k x) e_z` along the force, where `green` is
+/// the response of the solver to a single mode.
+fn check_body_force_mode(
+    screening: HydroScreening,
+    regularisation: ForceRegularisation,
+    filter: SpectralFilter,
+    green: impl Fn(Float) -> Float,
+) {
+    let bs = BoxSize {
+        x: 4.,
+        y: 3.,
+        z: 2.,
+    };
+    let gs = GridSize {
+        x: 8,
+        y: 3,
+        z: 2,
+        phi: 2,
+        theta: 2,
+    };
+    let k = 2. * PI / bs.x;
+    let force = 0.3;
+
+    let mut ff_s = SpectralSolver::new(gs, bs, stress_active);
+    ff_s.set_body_force([0., 0., -force]);
+    ff_s.set_regularisation(regularisation);
+    ff_s.set_filter(filter);
+
+    for ((ix, _, _), d) in ff_s.density.indexed_iter_mut() {
+        let x = (ix as Float + 0.5) * bs.x / gs.x as Float;
+        *d = Complex::new(1. + (k * x).cos(), 0.);
+    }
+    ff_s.fft_plan_forward
+        .reexecute3d(&mut ff_s.density.view_mut());
+    ff_s.solve_stress(screening);
+    let fft = &ff_s.fft_plan_backward;
+    for mut v in ff_s.flow_field.outer_iter_mut() {
+        fft.reexecute3d(&mut v);
+    }
+
+    for ((c, ix, _, _), u) in ff_s.flow_field.indexed_iter() {
+        let x = (ix as Float + 0.5) * bs.x / gs.x as Float;
+        let expect = [0., 0., -force * green(k)][c] * (k * x).cos();
+        assert!(
+            (u - expect).norm() < 1e-12,
+            "{:?}, {:?}, {:?}: u_{} = {} != {}",
+            screening,
+            regularisation,
+            filter,
+            c,
+            u,
+            expect
+        );
+    }
+}
+
+#[test]
+fn test_body_force() {
+    check_body_force_mode(
+        HydroScreening::None,
+        ForceRegularisation::None,
+        SpectralFilter::None,
+        |k| 1. / (k * k),
+    );
+}
+
+/// The shear flow of a single mode is screened by `1 / (k^2 + 1 / length^2)`
+/// in a Brinkman medium.
+#[test]
+fn test_brinkman_screening_of_mode() {
+    check_body_force_mode(
+        HydroScreening::Brinkman { length: 0.5 },
+        ForceRegularisation::None,
+        SpectralFilter::None,
+        |k| 1. / (k * k + 4.),
+    );
+    check_body_force_mode(
+        HydroScreening::Legacy { screening: 0.1 },
+        ForceRegularisation::None,
+        SpectralFilter::None,
+        |k| 1. / (k * k) + 0.1,
+    );
+}
+
+/// Compares the flow of a point force in a Brinkman medium with the screened
+/// Stokeslet `(A(r / l) 1 / r + B(r / l) r r / r^3) / 8 pi`, where the images
+/// of the periodic box are summed up to the second shell.
+#[test]
+fn test_brinkman_point_force() {
+    let n = 32;
+    let bs = BoxSize {
+        x: 8.,
+        y: 8.,
+        z: 8.,
+    };
+    let gs = GridSize {
+        x: n,
+        y: n,
+        z: n,
+        phi: 2,
+        theta: 2,
+    };
+    let h = bs.x / n as Float;
+    let volume = bs.x * bs.y * bs.z;
+    let length = 0.5;
+
+    let mut ff_s = SpectralSolver::new(gs, bs, stress_active);
+    ff_s.set_body_force([0., 0., 1.]);
+    ff_s.density.fill(Complex::new(0., 0.));
+    ff_s.density[[0, 0, 0]] = Complex::new(1. / (h * h * h), 0.);
+    ff_s.fft_plan_forward
+        .reexecute3d(&mut ff_s.density.view_mut());
+    ff_s.solve_stress(HydroScreening::Brinkman { length });
+    let fft = &ff_s.fft_plan_backward;
+    for mut v in ff_s.flow_field.outer_iter_mut() {
+        fft.reexecute3d(&mut v);
+    }
+
+    let stokeslet = |r: [Float; 3]| {
+        let d = (r[0] * r[0] + r[1] * r[1] + r[2] * r[2]).sqrt();
+        let x = d / length;
+        let e = (-x).exp();
+        let a = 2. * e * (1. + 1. / x + 1. / (x * x)) - 2. / (x * x);
+        let b = -2. * e * (1. + 3. / x + 3. / (x * x)) + 6. / (x * x);
+        let mut u = [0.; 3];
+        for (i, u) in u.iter_mut().enumerate() {
+            *u = b * r[i] * r[2] / (d * d * d) / (8. * PI);
+        }
+        u[2] += a / d / (8. * PI);
+        u
+    };
+
+    // The mean force does not drive a flow. It is the mean of the screened
+    // Stokeslets, which is `2/3 l^2` per volume for a spherical sum.
+    let mean = 2. / 3. * length * length / volume;
+
+    // The cutoff of the wave vectors at the faces of a cube rings along the
+    // axes of the grid, so the flow is only compared off the axes.
+    for &idx in &[[3, 3, 3], [2, 4, 5], [5, 2, 3], [4, 4, 2], [6, 3, 5]] {
+        let mut expect = [0., 0., -mean];
+        for image in iproduct!(-2..3, -2..3, -2..3) {
+            if image.0 * image.0 + image.1 * image.1 + image.2 * image.2 > 4 {
+                continue;
+            }
+            let r = [
+                (idx[0] as i32 + image.0 * n as i32) as Float * h,
+                (idx[1] as i32 + image.1 * n as i32) as Float * h,
+                (idx[2] as i32 + image.2 * n as i32) as Float * h,
+            ];
+            for (e, u) in expect.iter_mut().zip(&stokeslet(r)) {
+                *e += u;
+            }
+        }
+
+        let norm = expect.iter().map(|e| e * e).sum::<Float>().sqrt();
+        for (c, e) in expect.iter().enumerate() {
+            let u = ff_s.flow_field[[c, idx[0], idx[1], idx[2]]];
+            assert!(
+                (u - e).norm() < 1e-2 * norm,
+                "{:?}: u_{} = {} != {}",
+                idx,
+                c,
+                u,
+                e
+            );
+        }
+    }
+}
+
+/// The regularisation filters the response to a single mode of the body
+/// force.
+#[test]
+fn test_regularised_mode() {
+    check_body_force_mode(
+        HydroScreening::None,
+        ForceRegularisation::Gaussian { width: 0.5 },
+        SpectralFilter::None,
+        |k| (-k * k / 4.).exp() / (k * k),
+    );
+    check_body_force_mode(
+        HydroScreening::None,
+        ForceRegularisation::RotnePrager { radius: 0.5 },
+        SpectralFilter::None,
+        |k| ((k / 2.).sin() / (k / 2.)).powi(2) / (k * k),
+    );
+}
+
+/// The spectral filter damps the response to the mode `m = 1` of the eight
+/// cells in x direction by `exp(-strength (2 m / 8)^order)`.
+#[test]
+fn test_filtered_mode() {
+    check_body_force_mode(
+        HydroScreening::None,
+        ForceRegularisation::None,
+        SpectralFilter::Exponential {
+            strength: 2.,
+            order: 2,
+        },
+        |k| (-2. / 16. as Float).exp() / (k * k),
+    );
+}
+
+/// With a Gaussian regularisation, that is wider than the grid width, the
+/// flow of a point force does not depend on the resolution.
+#[test]
+fn test_regularised_point_force_converges() {
+    let bs = BoxSize {
+        x: 8.,
+        y: 8.,
+        z: 8.,
+    };
+
+    // The point force sits at the center `(0.5, 0.5, 0.5)` of the cell `i`
+    // and the flow is sampled at the centers of the cells `3 i + 1` of the
+    // grid refined by three.
+    let flow = |n: usize, i: usize| {
+        let gs = GridSize {
+            x: n,
+            y: n,
+            z: n,
+            phi: 2,
+            theta: 2,
+        };
+        let h = bs.x / n as Float;
+        let mut ff_s = SpectralSolver::new(gs, bs, stress_active);
+        ff_s.set_body_force([0., 0., 1.]);
+        ff_s.set_regularisation(ForceRegularisation::Gaussian { width: 1. });
+        ff_s.density.fill(Complex::new(0., 0.));
+        ff_s.density[[i, i, i]] = Complex::new(1. / (h * h * h), 0.);
+        ff_s.fft_plan_forward
+            .reexecute3d(&mut ff_s.density.view_mut());
+        ff_s.solve_stress(HydroScreening::None);
+        let fft = &ff_s.fft_plan_backward;
+        for mut v in ff_s.flow_field.outer_iter_mut() {
+            fft.reexecute3d(&mut v);
+        }
+        ff_s.flow_field.map(|v| v.re)
+    };
+
+    let coarse = flow(8, 0);
+    let fine = flow(24, 1);
+
+    let max = coarse.fold(0., |m: Float, v| m.max(v.abs()));
+    for ((c, x, y, z), u) in coarse.indexed_iter() {
+        let v = fine[[c, 3 * x + 1, 3 * y + 1, 3 * z + 1]];
+        assert!(
+            (u - v).abs() < 1e-3 * max,
+            "u_{} at {:?}: {} != {}",
+            c,
+            (x, y, z),
+            u,
+            v
+        );
+    }
+}
+
+/// The flow field is linear in the stress, so a mixture of two species
+/// results in the same flow as a single species with the summed stress.
+#[test]
+fn test_species_add_up() {
+    let bs = BoxSize {
+        x: 5.,
+        y: 4.,
+        z: 3.,
+    };
+    let gs = GridSize {
+        x: 5,
+        y: 4,
+        z: 3,
+        phi: 6,
+        theta: 4,
+    };
+
+    let p = Particle::create_isotropic(500, &bs, 1);
+    let mut d1 = Distribution::new(gs, bs);
+    let mut d2 = Distribution::new(gs, bs);
+    d1.sample_from(&p[..200]);
+    d2.sample_from(&p[200..]);
+
+    let mut mixture = SpectralSolver::new(gs, bs, stress_active);
+    mixture.add_species(|phi, theta| -2. * stress_active(phi, theta));
+    let ff = mixture
+        .mean_flow_field_of_species(HydroScreening::None, &[d1.clone(), d2.clone()])
+        .0
+        .to_owned();
+
+    // the second species contributes with the opposite sign and twice the
+    // magnitude
+    let mut expected = d1.clone();
+    expected.dist = &d1.dist - &(&d2.dist * 2.);
+    let mut single = SpectralSolver::new(gs, bs, stress_active);
+    let ff_single = single.mean_flow_field(HydroScreening::None, &expected).0;
+
+    for (a, b) in ff.iter().zip(ff_single.iter()) {
+        assert!((a - b).norm() < 1e-12, "{} != {}", a, b);
+    }
+}
+
+/// A weighted stress adds to the stress of the species like a species of its
+/// own.
+#[test]
+fn test_weighted_stress() {
+    let bs = BoxSize {
+        x: 5.,
+        y: 4.,
+        z: 3.,
+    };
+    let gs = GridSize {
+        x: 5,
+        y: 4,
+        z: 3,
+        phi: 6,
+        theta: 4,
+    };
+
+    let mut p = Particle::create_isotropic(500, &bs, 2);
+    for (i, p) in p.iter_mut().enumerate() {
+        p.attributes.magnetic_moment = 0.5 + (i % 3) as Float;
+    }
+    let mut d = Distribution::new(gs, bs);
+    let mut weighted = Distribution::new(gs, bs);
+    d.sample_from(&p);
+    weighted.sample_weighted_from(&p, 1., |p| p.attributes.magnetic_moment);
+
+    let mut s = SpectralSolver::new(gs, bs, stress_active);
+    s.add_weighted_stress(stress_magnetic);
+    let ff = s
+        .mean_flow_field_of_weighted_species(
+            HydroScreening::None,
+            &[d.clone()],
+            &[weighted.clone()],
+        )
+        .0
+        .to_owned();
+
+    let mut mixture = SpectralSolver::new(gs, bs, stress_active);
+    mixture.add_species(stress_magnetic);
+    let ff_mixture = mixture
+        .mean_flow_field_of_species(HydroScreening::None, &[d, weighted])
+        .0;
+
+    for (a, b) in ff.iter().zip(ff_mixture.iter()) {
+        assert!((a - b).norm() < 1e-12, "{} != {}", a, b);
+    }
+}
+
 // #[bench]
 // fn bench_calculate_flow(b: &mut Bencher) {
 //     let bs = BoxSize {
@@ -3,21 +3,21 @@
 #[path = "./stress_test.rs"]
 mod langevin_test;
 
-use crate::consts::TWOPI;
-use crate::distribution::Distribution;
+use crate::distribution::{Distribution, OrientationRepresentation};
 use crate::mesh::grid_width::GridWidth;
+use crate::polarization::director::DirectorField;
+use crate::vector::{mat_add, Matrix3};
 use crate::Float;
 use crate::GridSize;
-use ndarray::{Array, ArrayView, ArrayViewMut, Axis, Ix2, Ix4, Ix5};
+use ndarray::{s, Array, ArrayBase, ArrayView, ArrayViewMut, Axis, Data, Ix2, Ix4, Ix5, Zip};
+use ndarray_parallel::prelude::*;
 use num_complex::Complex;
 use serde_derive::{Deserialize, Serialize};
-#[cfg(feature = "single")]
-use std::f32::consts::PI;
-#[cfg(not(feature = "single"))]
-use std::f64::consts::PI;
+use std::ops::Add;
+use std::slice;
 
 /// Holds prefactors for active and magnetic stress
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct StressPrefactors {
     pub active: Float,
@@ -26,33 +26,23 @@ pub struct StressPrefactors {
 
 /// Calculates approximation of discretized stress kernel, to be used in ///
 /// the expectation value to obtain the stress tensor.
-pub fn stress_kernel<F>(grid_size: GridSize, grid_width: GridWidth, stress: F) -> Array<Float, Ix4>
+pub fn stress_kernel<F>(
+    grid_size: GridSize,
+    grid_width: GridWidth,
+    orientation: OrientationRepresentation,
+    stress: F,
+) -> Array<Float, Ix4>
 where
     F: Fn(Float, Float) -> Array<Float, Ix2>,
 {
-    let mut s = Array::<Float, _>::zeros((3, 3, grid_size.phi, grid_size.theta));
-    // Calculate discrete angles, considering the cell centered sample points of
-    // the distribution
-    let gw_half_phi = grid_width.phi / 2.;
-    let gw_half_theta = grid_width.theta / 2.;
-    let angles_phi = Array::linspace(0. + gw_half_phi, TWOPI - gw_half_phi, grid_size.phi);
-    let angles_theta = Array::linspace(0. + gw_half_theta, PI - gw_half_theta, grid_size.theta);
-
-    // TODO implement stress due to magnetic dipole interaction
-
-    for (mut ax1, phi) in s.axis_iter_mut(Axis(2)).zip(&angles_phi) {
-        for (mut e, theta) in ax1.axis_iter_mut(Axis(2)).zip(&angles_theta) {
-            // let s = stress_active(*phi, *theta) * a + stress_magnetic(*phi, *theta) * b;
-            let s = stress(*phi, *theta);
-
-            e.assign(&s);
-
-            // Already taken care of by the modified cell average in the distribution code
-            // e *= theta.sin();
-        }
-    }
-
-    s
+    // Sin(theta) of the uniform grid is already taken care of by the modified
+    // cell average in the distribution code
+    let s = orientation.kernel(grid_size, grid_width, 9, |phi, theta| {
+        stress(phi, theta).into_shape(9).unwrap()
+    });
+
+    let (_, a, b) = s.dim();
+    s.into_shape((3, 3, a, b)).unwrap()
 }
 
 /// It consumes `stress_field` and updates it given a stress kernel `kernel`
@@ -62,31 +52,68 @@ pub fn average_stress<'a>(
     kernel: &ArrayView<Float, Ix4>,
     dist: &Distribution,
 ) -> ArrayViewMut<'a, Complex<Float>, Ix5> {
-    let dist_sh = dist.dim();
-    let stress_sh = kernel.dim();
+    average_stress_of_species(stress_field, slice::from_ref(kernel), slice::from_ref(dist))
+}
+
+/// Same as `average_stress`, but for a mixture of particle species. The
+/// stress field is the sum of the stresses of every species, given by its
+/// stress kernel in `kernels` and its distribution in `dists`.
+pub fn average_stress_of_species<'a, S>(
+    mut stress_field: ArrayViewMut<'a, Complex<Float>, Ix5>,
+    kernels: &[ArrayBase<S, Ix4>],
+    dists: &[Distribution],
+) -> ArrayViewMut<'a, Complex<Float>, Ix5>
+where
+    S: Data<Elem = Float>,
+{
+    stress_field.fill(Complex::new(0., 0.));
+    add_average_stress_of_species(stress_field, kernels, dists)
+}
+
+/// Same as `average_stress_of_species`, but adds the stresses to
+/// `stress_field` instead of overwriting it.
+pub fn add_average_stress_of_species<'a, S>(
+    stress_field: ArrayViewMut<'a, Complex<Float>, Ix5>,
+    kernels: &[ArrayBase<S, Ix4>],
+    dists: &[Distribution],
+) -> ArrayViewMut<'a, Complex<Float>, Ix5>
+where
+    S: Data<Elem = Float>,
+{
+    assert_eq!(
+        kernels.len(),
+        dists.len(),
+        "Every species needs a stress kernel and a distribution."
+    );
+    if dists.is_empty() {
+        return stress_field;
+    }
+
+    let dist_sh = dists[0].dim();
+    let stress_sh = kernels[0].dim();
 
     let n_angle = dist_sh.3 * dist_sh.4;
     let n_stress = stress_sh.0 * stress_sh.1;
     let n_dist = dist_sh.0 * dist_sh.1 * dist_sh.2;
 
-    let gw = dist.get_grid_width();
+    let mut stress_field = stress_field.into_shape((n_stress, n_dist)).unwrap();
 
-    // Put axis in order, so that components fields are continuous in memory,
-    // so it can be passed to FFTW easily
-    let stress = kernel.into_shape([n_stress, n_angle]).unwrap();
+    for (kernel, dist) in kernels.iter().zip(dists) {
+        // integration measures and FFT normalization
+        let measure = dist.orientation_measure();
 
-    let dist = dist.dist.view().into_shape([n_dist, n_angle]).unwrap();
-
-    let mut stress_field = stress_field.into_shape((n_stress, n_dist)).unwrap();
+        // Put axis in order, so that components fields are continuous in memory,
+        // so it can be passed to FFTW easily
+        let stress = kernel.view().into_shape([n_stress, n_angle]).unwrap();
 
-    // integration measures and FFT normalization
-    let measure = gw.phi * gw.theta;
+        let dist = dist.dist.view().into_shape([n_dist, n_angle]).unwrap();
 
-    // Calculating the integral over the orientation. `norm` includes weights for
-    // integration and normalisation of DFT
-    for (s, mut o1) in stress.outer_iter().zip(stress_field.outer_iter_mut()) {
-        for (d, o2) in dist.outer_iter().zip(o1.iter_mut()) {
-            *o2 = Complex::from(s.dot(&d) * measure)
+        // Calculating the integral over the orientation. `norm` includes weights
+        // for integration and normalisation of DFT
+        for (s, mut o1) in stress.outer_iter().zip(stress_field.outer_iter_mut()) {
+            for (d, o2) in dist.outer_iter().zip(o1.iter_mut()) {
+                *o2 += Complex::from(s.dot(&d) * measure)
+            }
         }
     }
 
@@ -95,6 +122,207 @@ pub fn average_stress<'a>(
         .unwrap()
 }
 
+/// Antisymmetric stress of the torque density, that the mean magnetic field of
+/// the dipole-dipole interaction exerts on the particles. In contrast to the
+/// external field, the field varies in space, so the stress cannot be given by
+/// a stress kernel. Instead, it is calculated from the local magnetisation
+/// `M` and field `B` as `0.5 prefactor (M B - B M)`.
+pub struct MagneticDipoleStress {
+    prefactor: Float,
+    /// magnetic moment of every species relative to the moment, which enters
+    /// the dipole-dipole coupling
+    moments: Vec<Float>,
+    magnetisation: DirectorField,
+    field: Array<Complex<Float>, Ix4>,
+}
+
+impl MagneticDipoleStress {
+    pub fn new(
+        grid_size: GridSize,
+        grid_width: GridWidth,
+        orientation: OrientationRepresentation,
+        prefactor: Float,
+        moments: Vec<Float>,
+    ) -> MagneticDipoleStress {
+        MagneticDipoleStress {
+            prefactor,
+            moments,
+            magnetisation: DirectorField::with_orientation(grid_size, grid_width, orientation),
+            field: Array::zeros([3, grid_size.x, grid_size.y, grid_size.z]),
+        }
+    }
+
+    /// Changes the prefactor and the magnetic moments of the species, e.g.
+    /// for time-dependent parameters.
+    pub fn set_prefactor(&mut self, prefactor: Float, moments: Vec<Float>) {
+        self.prefactor = prefactor;
+        self.moments = moments;
+    }
+
+    /// Sets the mean magnetic field, which acts on the dipoles, e.g. as
+    /// calculated by `MagneticSolver::mean_magnetic_field_of_species`, and
+    /// the magnetisation of the species with the distributions `dists`.
+    pub fn set_field(&mut self, field: ArrayView<Complex<Float>, Ix4>, dists: &[Distribution]) {
+        self.field.assign(&field);
+        self.magnetisation.from_distributions(dists, &self.moments);
+    }
+
+    /// Adds the stress to `stress_field`.
+    pub fn add_to(&self, stress_field: ArrayViewMut<Complex<Float>, Ix5>) {
+        let sh = self.field.dim();
+        let n = sh.1 * sh.2 * sh.3;
+        let m = self.magnetisation.field.view().into_shape([3, n]).unwrap();
+        let b = self.field.view().into_shape([3, n]).unwrap();
+        let mut stress_field = stress_field.into_shape([3, 3, n]).unwrap();
+
+        let c = 0.5 * self.prefactor;
+        Zip::from(stress_field.axis_iter_mut(Axis(2)))
+            .and(m.axis_iter(Axis(1)))
+            .and(b.axis_iter(Axis(1)))
+            .par_apply(|mut s, m, b| {
+                for ((i, j), s) in s.indexed_iter_mut() {
+                    *s += (m[i].re * b[j].re - b[i].re * m[j].re) * c;
+                }
+            });
+    }
+}
+
+/// Returns the box average of the stress tensor, given a stress kernel
+/// `kernel` and a distribution `dist`.
+pub fn mean_stress(kernel: &ArrayView<Float, Ix4>, dist: &Distribution) -> Matrix3 {
+    let dist_sh = dist.dim();
+    let n_angle = dist_sh.3 * dist_sh.4;
+    let n_dist = dist_sh.0 * dist_sh.1 * dist_sh.2;
+
+    // integrate over orientations and average over grid cells
+    let norm = dist.orientation_measure() / n_dist as Float;
+
+    // sum over all grid cells first, the kernel does not depend on position
+    let dist = dist.dist.view().into_shape([n_dist, n_angle]).unwrap();
+    let dist = dist.sum_axis(Axis(0));
+
+    let mut s = Matrix3::default();
+    for (i, si) in s.iter_mut().enumerate() {
+        for (j, sij) in si.iter_mut().enumerate() {
+            let k = kernel.slice(s![i, j, .., ..]);
+            let k = k.into_shape([n_angle]).unwrap();
+            *sij = k.dot(&dist) * norm;
+        }
+    }
+
+    s
+}
+
+/// Box averaged stress tensors of the different contributions, including
+/// their prefactors. In simulation units the solvent viscosity is one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BulkStress {
+    pub active: Matrix3,
+    pub magnetic: Matrix3,
+    pub rods: Matrix3,
+}
+
+impl BulkStress {
+    /// Returns the sum of all particle stress contributions.
+    pub fn total(&self) -> Matrix3 {
+        let mut s = self.active;
+        for ((si, mi), ri) in s.iter_mut().zip(&self.magnetic).zip(&self.rods) {
+            for ((sij, mij), rij) in si.iter_mut().zip(mi).zip(ri) {
+                *sij += mij + rij;
+            }
+        }
+        s
+    }
+
+    /// Returns the effective viscosity relative to the solvent viscosity for
+    /// a simple shear flow `u = (shear_rate * y, 0, 0)`, i.e. `1 + s_xy /
+    /// shear_rate` with the symmetric part `s_xy` of the total particle
+    /// stress.
+    pub fn effective_viscosity(&self, shear_rate: Float) -> Float {
+        let s = self.total();
+        1. + 0.5 * (s[0][1] + s[1][0]) / shear_rate
+    }
+
+    /// Returns the first and second normal stress difference `(s_xx - s_yy,
+    /// s_yy - s_zz)` of the total particle stress for a simple shear flow in x
+    /// direction with gradient in y direction.
+    pub fn normal_stress_differences(&self) -> (Float, Float) {
+        let s = self.total();
+        (s[0][0] - s[1][1], s[1][1] - s[2][2])
+    }
+}
+
+impl Add for BulkStress {
+    type Output = BulkStress;
+
+    /// Sums the stresses of two particle species.
+    fn add(self, other: BulkStress) -> BulkStress {
+        BulkStress {
+            active: mat_add(&self.active, &other.active),
+            magnetic: mat_add(&self.magnetic, &other.magnetic),
+            rods: mat_add(&self.rods, &other.rods),
+        }
+    }
+}
+
+/// Measures the bulk stress of a distribution for every contribution
+/// separately.
+pub struct BulkStressMeter {
+    active: Array<Float, Ix4>,
+    magnetic: Array<Float, Ix4>,
+    rods: Array<Float, Ix4>,
+}
+
+impl BulkStressMeter {
+    /// Returns a meter with kernels for the active, magnetic and rod stress,
+    /// weighted by `prefactors` and the particle shape `shape`.
+    pub fn new(
+        grid_size: GridSize,
+        grid_width: GridWidth,
+        orientation: OrientationRepresentation,
+        prefactors: StressPrefactors,
+        shape: Float,
+    ) -> BulkStressMeter {
+        let kernel = |f: fn(Float, Float) -> Array<Float, Ix2>, prefactor: Float| {
+            stress_kernel(grid_size, grid_width, orientation, |phi, theta| {
+                f(phi, theta) * prefactor
+            })
+        };
+
+        BulkStressMeter {
+            active: kernel(stresses::stress_active, prefactors.active),
+            magnetic: kernel(stresses::stress_magnetic, prefactors.magnetic),
+            rods: kernel(stresses::stress_magnetic_rods, shape),
+        }
+    }
+
+    /// Returns the box averaged stresses of the distribution `dist`.
+    pub fn measure(&self, dist: &Distribution) -> BulkStress {
+        BulkStress {
+            active: mean_stress(&self.active.view(), dist),
+            magnetic: mean_stress(&self.magnetic.view(), dist),
+            rods: mean_stress(&self.rods.view(), dist),
+        }
+    }
+
+    /// Same as `measure`, but the active, magnetic and rod stress are
+    /// measured with the distributions `active`, `magnetic` and `rods`, which
+    /// are weighted by the speed, the magnetic moment and the shape of
+    /// polydisperse particles.
+    pub fn measure_weighted(
+        &self,
+        active: &Distribution,
+        magnetic: &Distribution,
+        rods: &Distribution,
+    ) -> BulkStress {
+        BulkStress {
+            active: mean_stress(&self.active.view(), active),
+            magnetic: mean_stress(&self.magnetic.view(), magnetic),
+            rods: mean_stress(&self.rods.view(), rods),
+        }
+    }
+}
+
 pub mod stresses {
     use crate::Float;
     use ndarray::{Array, Ix2};
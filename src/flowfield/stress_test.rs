@@ -98,7 +98,7 @@ fn stress_kernel_test() {
 
     let s = |phi, theta| 1. * stress_active(phi, theta) + 1. * stress_magnetic(phi, theta);
 
-    let sk = stress_kernel(gs, gw, s);
+    let sk = stress_kernel(gs, gw, OrientationRepresentation::UniformGrid, s);
 
     let cf = "test/control/stress_kernel.bincode";
 
@@ -120,3 +120,194 @@ fn stress_kernel_test() {
         assert!(equal_floats(a, b), "left: {} != right: {}", a, b);
     }
 }
+
+#[test]
+fn average_stress_spherical_harmonics() {
+    use crate::distribution::Distribution;
+    use crate::particle::Particle;
+    use num_complex::Complex;
+
+    let bs = BoxSize {
+        x: 1.,
+        y: 1.,
+        z: 1.,
+    };
+    let gs = GridSize {
+        x: 1,
+        y: 1,
+        z: 1,
+        phi: 1,
+        theta: 1,
+    };
+    let gw = GridWidth::new(gs, bs);
+    let orientation = OrientationRepresentation::SphericalHarmonics { degree: 3 };
+
+    let s = |phi, theta| {
+        stress_active(phi, theta)
+            + 2. * stress_magnetic(phi, theta)
+            + 3. * stress_magnetic_rods(phi, theta)
+    };
+    let sk = stress_kernel(gs, gw, orientation, s);
+
+    // The stresses are band limited to degree 3, so the expectation value for
+    // a single particle is exact.
+    let (phi, theta) = (0.7, 0.05);
+    let p = vec![Particle::new(0.5, 0.5, 0.5, phi, theta, &bs)];
+    let mut d = Distribution::with_orientation(gs, bs, orientation);
+    d.sample_from(&p);
+
+    let mut field = Array::<Complex<Float>, _>::zeros((3, 3, 1, 1, 1));
+    let field = average_stress(field.view_mut(), &sk.view(), &d);
+
+    let expect = s(phi, theta);
+    for (a, b) in field.iter().zip(expect.iter()) {
+        assert!((a.re - b).abs() < 1e-13, "left: {} != right: {}", a.re, b);
+    }
+}
+
+#[test]
+fn bulk_stress() {
+    use crate::distribution::Distribution;
+    use crate::particle::Particle;
+
+    let bs = BoxSize {
+        x: 2.,
+        y: 1.,
+        z: 1.,
+    };
+    let gs = GridSize {
+        x: 2,
+        y: 2,
+        z: 1,
+        phi: 1,
+        theta: 1,
+    };
+    let gw = GridWidth::new(gs, bs);
+    let orientation = OrientationRepresentation::SphericalHarmonics { degree: 3 };
+    let prefactors = StressPrefactors {
+        active: 2.,
+        magnetic: 3.,
+    };
+    let meter = BulkStressMeter::new(gs, gw, orientation, prefactors, 4.);
+
+    // particles in different cells, but all pointing along the same direction
+    let (phi, theta) = (0.3, 1.1);
+    let p: Vec<_> = [[0.2, 0.2], [1.7, 0.2], [0.6, 0.9]]
+        .iter()
+        .map(|x| Particle::new(x[0], x[1], 0.5, phi, theta, &bs))
+        .collect();
+    let mut d = Distribution::with_orientation(gs, bs, orientation);
+    d.sample_from(&p);
+
+    let stress = meter.measure(&d);
+
+    // the distribution is normalised to one, so the mean is divided by the
+    // box volume
+    let volume = bs.x * bs.y * bs.z;
+    let expect = [
+        stress_active(phi, theta) * 2. / volume,
+        stress_magnetic(phi, theta) * 3. / volume,
+        stress_magnetic_rods(phi, theta) * 4. / volume,
+    ];
+
+    for (s, e) in [stress.active, stress.magnetic, stress.rods]
+        .iter()
+        .zip(expect.iter())
+    {
+        for (a, b) in s.iter().flat_map(|r| r.iter()).zip(e.iter()) {
+            assert!((a - b).abs() < 1e-13, "left: {} != right: {}", a, b);
+        }
+    }
+
+    let total = stress.total();
+    let expect = &expect[0] + &expect[1] + &expect[2];
+    for (a, b) in total.iter().flat_map(|r| r.iter()).zip(expect.iter()) {
+        assert!((a - b).abs() < 1e-13, "left: {} != right: {}", a, b);
+    }
+
+    // weighted distributions for the active, magnetic and rod stress
+    let mut active = d.clone();
+    active.sample_weighted_from(&p, 1., |_| 3.);
+    let mut magnetic = d.clone();
+    magnetic.sample_weighted_from(&p, 1., |_| 0.5);
+    let mut rods = d.clone();
+    rods.sample_weighted_from(&p, 1., |_| 2.);
+    let weighted = meter.measure_weighted(&active, &magnetic, &rods);
+    let scale = [3., 0.5, 2.];
+    for ((w, s), c) in [weighted.active, weighted.magnetic, weighted.rods]
+        .iter()
+        .zip([stress.active, stress.magnetic, stress.rods].iter())
+        .zip(scale.iter())
+    {
+        for (a, b) in w
+            .iter()
+            .flat_map(|r| r.iter())
+            .zip(s.iter().flat_map(|r| r.iter()))
+        {
+            assert!((a - b * c).abs() < 1e-13, "left: {} != right: {}", a, b * c);
+        }
+    }
+}
+
+#[test]
+fn effective_viscosity() {
+    let mut stress = BulkStress::default();
+    stress.active[0][1] = 0.5;
+    stress.active[1][0] = 0.5;
+    stress.magnetic[0][1] = 1.;
+    stress.magnetic[1][0] = -1.;
+    stress.rods[0][0] = 3.;
+    stress.rods[1][1] = 1.;
+    stress.rods[2][2] = -4.;
+
+    // anti-symmetric magnetic stress does not contribute
+    assert!(equal_floats(stress.effective_viscosity(2.), 1.25));
+    assert_eq!(stress.normal_stress_differences(), (2., 5.));
+}
+
+#[test]
+fn magnetic_dipole_stress() {
+    use crate::distribution::Distribution;
+    use crate::particle::Particle;
+    use crate::vector::VectorD;
+    use num_complex::Complex;
+
+    let bs = BoxSize {
+        x: 1.,
+        y: 1.,
+        z: 1.,
+    };
+    let gs = GridSize {
+        x: 1,
+        y: 1,
+        z: 1,
+        phi: 1,
+        theta: 1,
+    };
+    let gw = GridWidth::new(gs, bs);
+    let orientation = OrientationRepresentation::SphericalHarmonics { degree: 3 };
+
+    let p = vec![Particle::new(0.5, 0.5, 0.5, 0.7, 1.2, &bs)];
+    let n: VectorD = p[0].orientation.to_vector().to();
+    let mut d = Distribution::with_orientation(gs, bs, orientation);
+    d.sample_from(&p);
+
+    let mut m = MagneticDipoleStress::new(gs, gw, orientation, 2., vec![0.5, 0.]);
+    let mut field = Array::<Complex<Float>, _>::zeros((3, 1, 1, 1));
+    field[[1, 0, 0, 0]] = Complex::new(3., 0.);
+    // The second species is not magnetic.
+    m.set_field(field.view(), &[d.clone(), d]);
+
+    // The stress is added to the stress of the other contributions.
+    let mut stress = Array::<Complex<Float>, _>::from_elem((3, 3, 1, 1, 1), Complex::new(1., 0.));
+    m.add_to(stress.view_mut());
+
+    let b = [0., 3., 0.];
+    for i in 0..3 {
+        for j in 0..3 {
+            let expect = 1. + 0.5 * 2. * 0.5 * (n[i] * b[j] - b[i] * n[j]);
+            let s = stress[[i, j, 0, 0, 0]].re;
+            assert!((s - expect).abs() < 1e-13, "{} != {}", s, expect);
+        }
+    }
+}
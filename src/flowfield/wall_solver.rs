@@ -0,0 +1,720 @@
+//! Solver of the Stokes equation between two planar no-slip walls at `z = 0`
+//! and `z = L_z`, which is periodic in x and y direction.
+//!
+//! The flow field is split into a particular solution, which fulfills only
+//! the free-slip boundary condition, and a homogeneous solution, which
+//! corrects for the tangential velocity at the walls.
+//!
+//! The particular solution is obtained by expanding the stress field into
+//! Fourier modes in x and y and into cosine and sine modes `cos(q_m z)`,
+//! `sin(q_m z)` with `q_m = pi m / L_z` in z direction. The parity of every
+//! component is chosen such that the normal velocity and the tangential
+//! stress vanish at the walls, i.e. tangential velocities, pressure and the
+//! stress components `sigma_ij` with `i, j` both tangential or both normal
+//! are expanded in cosine modes, all others in sine modes.
+//!
+//! For every wave vector `k = (k_x, k_y)`, the homogeneous solution is given
+//! analytically in the mixed representation `u(k, z)`. The component
+//! perpendicular to `k` is a superposition of `exp(-k z)` and `exp(-k (L_z -
+//! z))`, the velocity in z direction a superposition of `(a + b z) exp(-k
+//! z)` and `(c + d (L_z - z)) exp(-k (L_z - z))` and the component parallel to
+//! `k` is given by incompressibility. For `k = 0` the correction is a linear
+//! shear flow.
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./wall_solver_test.rs"]
+mod wall_solver_test;
+
+use crate::distribution::{Distribution, OrientationRepresentation};
+use crate::flowfield::regularisation::ForceRegularisation;
+use crate::flowfield::screening::HydroScreening;
+use crate::flowfield::spectral_solver::split_gradient;
+use crate::flowfield::stress::{
+    add_average_stress_of_species, average_stress_of_species, stress_kernel, MagneticDipoleStress,
+};
+use crate::mesh::fft_helper::get_k_mesh;
+use crate::mesh::grid_width::GridWidth;
+use crate::vector::Matrix3;
+use crate::Float;
+use crate::{BoxSize, GridSize};
+use fftw3::fft;
+use fftw3::fft::{FFTPlan, R2RKind};
+use ndarray::{s, Array, ArrayView, Axis, Ix1, Ix2, Ix3, Ix4, Ix5, Zip};
+use ndarray_parallel::prelude::*;
+use num_complex::Complex;
+use rayon::prelude::*;
+#[cfg(feature = "single")]
+use std::f32::consts::PI;
+#[cfg(not(feature = "single"))]
+use std::f64::consts::PI;
+use std::slice;
+use std::sync::Arc;
+
+/// Returns true, if the `(i, j)` component of a tensor field is expanded in
+/// cosine modes in z direction. Vectors are treated as `i = 0`.
+fn is_cosine(i: usize, j: usize) -> bool {
+    (i == 2) == (j == 2)
+}
+
+pub struct WallSpectralSolver {
+    flow_field: Array<Complex<Float>, Ix4>,
+    fft_plan_forward: Arc<FFTPlan>,
+    fft_plan_backward: Arc<FFTPlan>,
+    dct: Arc<FFTPlan>,
+    idct: Arc<FFTPlan>,
+    dst: Arc<FFTPlan>,
+    idst: Arc<FFTPlan>,
+    /// x and y components of the wave vectors, flattened over the grid
+    k_mesh: Array<Float, Ix2>,
+    /// tangential velocity of the particular solution at the walls
+    wall_velocity: Array<Complex<Float>, Ix3>,
+    /// stress kernel of every particle species
+    stress_kernels: Vec<Array<Float, Ix4>>,
+    /// stress kernels, that are averaged with weighted distributions, see
+    /// `add_weighted_stress`
+    weighted_stress_kernels: Vec<Array<Float, Ix4>>,
+    stress_field: Array<Complex<Float>, Ix5>,
+    /// stress of the magnetic dipole-dipole interaction, if switched on
+    magnetic_dipole_stress: Option<MagneticDipoleStress>,
+    /// finite size of the force distribution of a particle
+    regularisation: ForceRegularisation,
+    gradient_meanf: Array<Complex<Float>, Ix5>,
+    strain: Array<Matrix3, Ix3>,
+    vorticity: Array<Matrix3, Ix3>,
+    grid_size: GridSize,
+    box_size: BoxSize,
+    orientation: OrientationRepresentation,
+}
+
+impl WallSpectralSolver {
+    /// Returns a solver for distributions, that represent the orientations as
+    /// given by `orientation`.
+    pub fn with_orientation<F>(
+        grid_size: GridSize,
+        box_size: BoxSize,
+        orientation: OrientationRepresentation,
+        stress: F,
+    ) -> WallSpectralSolver
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(grid_size, box_size);
+        let n = grid_size.x * grid_size.y;
+
+        let mesh = get_k_mesh(grid_size, box_size);
+        let k_mesh = mesh
+            .slice(s![..2, .., .., 0])
+            .map(|v| v.re)
+            .into_shape([2, n])
+            .unwrap();
+
+        let mut dummy: Array<Complex<Float>, Ix3> =
+            Array::default([grid_size.x, grid_size.y, grid_size.z]);
+        let mut xy_plan = |direction| {
+            Arc::new(
+                FFTPlan::new_c2c_inplace_3d_xy(
+                    &mut dummy.view_mut(),
+                    direction,
+                    fft::FFTFlags::Patient,
+                )
+                .unwrap(),
+            )
+        };
+        let fft_plan_forward = xy_plan(fft::FFTDirection::Forward);
+        let fft_plan_backward = xy_plan(fft::FFTDirection::Backward);
+
+        let mut dummy: Array<Complex<Float>, Ix3> =
+            Array::default([grid_size.x, grid_size.y, grid_size.z]);
+        let mut z_plan = |kind| {
+            Arc::new(
+                FFTPlan::new_r2r_inplace_3d_z(&mut dummy.view_mut(), kind, fft::FFTFlags::Patient)
+                    .unwrap(),
+            )
+        };
+
+        WallSpectralSolver {
+            flow_field: Array::zeros((3, grid_size.x, grid_size.y, grid_size.z)),
+            fft_plan_forward,
+            fft_plan_backward,
+            dct: z_plan(R2RKind::DCT2),
+            idct: z_plan(R2RKind::DCT3),
+            dst: z_plan(R2RKind::DST2),
+            idst: z_plan(R2RKind::DST3),
+            k_mesh,
+            wall_velocity: Array::zeros((2, 2, n)),
+            stress_kernels: vec![stress_kernel(grid_size, grid_width, orientation, stress)],
+            stress_field: Array::zeros((3, 3, grid_size.x, grid_size.y, grid_size.z)),
+            weighted_stress_kernels: Vec::new(),
+            magnetic_dipole_stress: None,
+            regularisation: ForceRegularisation::None,
+            gradient_meanf: Array::zeros((3, 3, grid_size.x, grid_size.y, grid_size.z)),
+            strain: Array::default([grid_size.x, grid_size.y, grid_size.z]),
+            vorticity: Array::default([grid_size.x, grid_size.y, grid_size.z]),
+            grid_size,
+            box_size,
+            orientation,
+        }
+    }
+
+    /// Adds another particle species with the stress `stress`, see
+    /// `SpectralSolver::add_species`.
+    pub fn add_species<F>(&mut self, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.stress_kernels.push(stress_kernel(
+            self.grid_size,
+            grid_width,
+            self.orientation,
+            stress,
+        ));
+    }
+
+    /// Replaces the stress of a species, see
+    /// `SpectralSolver::set_species_stress`.
+    pub fn set_species_stress<F>(&mut self, species: usize, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.stress_kernels[species] =
+            stress_kernel(self.grid_size, grid_width, self.orientation, stress);
+    }
+
+    /// Adds another stress, which is averaged with its own distribution, see
+    /// `SpectralSolver::add_weighted_stress`.
+    pub fn add_weighted_stress<F>(&mut self, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.weighted_stress_kernels.push(stress_kernel(
+            self.grid_size,
+            grid_width,
+            self.orientation,
+            stress,
+        ));
+    }
+
+    /// Replaces a weighted stress, see `SpectralSolver::set_weighted_stress`.
+    pub fn set_weighted_stress<F>(&mut self, index: usize, stress: F)
+    where
+        F: Fn(Float, Float) -> Array<Float, Ix2>,
+    {
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.weighted_stress_kernels[index] =
+            stress_kernel(self.grid_size, grid_width, self.orientation, stress);
+    }
+
+    /// Adds the stress of the magnetic dipole-dipole interaction, see
+    /// `SpectralSolver::set_magnetic_dipole_stress`.
+    pub fn set_magnetic_dipole_stress(&mut self, prefactor: Float, moments: Vec<Float>) {
+        if let Some(m) = &mut self.magnetic_dipole_stress {
+            m.set_prefactor(prefactor, moments);
+            return;
+        }
+        let grid_width = GridWidth::new(self.grid_size, self.box_size);
+        self.magnetic_dipole_stress = Some(MagneticDipoleStress::new(
+            self.grid_size,
+            grid_width,
+            self.orientation,
+            prefactor,
+            moments,
+        ));
+    }
+
+    /// Sets the mean magnetic field, see `SpectralSolver::set_magnetic_field`.
+    pub fn set_magnetic_field(
+        &mut self,
+        field: ArrayView<Complex<Float>, Ix4>,
+        dists: &[Distribution],
+    ) {
+        if let Some(m) = &mut self.magnetic_dipole_stress {
+            m.set_field(field, dists);
+        }
+    }
+
+    /// Sets the finite size of the force distribution of a particle, see
+    /// `SpectralSolver::set_regularisation`. The filter applies to the cosine
+    /// and sine modes like to Fourier modes, so the no-slip correction is the
+    /// exact solution for the regularised forces.
+    pub fn set_regularisation(&mut self, regularisation: ForceRegularisation) {
+        self.regularisation = regularisation;
+    }
+
+    /// Transforms the stress field into Fourier modes in x and y and into
+    /// cosine or sine modes in z.
+    fn fft_stress(&mut self) {
+        let gs = self.grid_size;
+        let mut stress_field = self
+            .stress_field
+            .view_mut()
+            .into_shape([9, gs.x, gs.y, gs.z])
+            .unwrap();
+
+        let fft = &self.fft_plan_forward;
+        let (dct, dst) = (&self.dct, &self.dst);
+        stress_field
+            .outer_iter_mut()
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(c, mut v)| {
+                fft.reexecute3d(&mut v);
+                if is_cosine(c / 3, c % 3) {
+                    dct.reexecute3d_r2r(&mut v);
+                } else {
+                    dst.reexecute3d_r2r(&mut v);
+                }
+            });
+    }
+
+    /// Calculates the free-slip solution of the Stokes equation and its
+    /// gradient in the representation of cosine and sine modes in z. The
+    /// tangential velocities of it at the walls are stored as well.
+    ///
+    /// All coefficients are given in the normalization of FFTW, i.e.
+    /// `f(z) = 1 / 2N (X_0 + 2 sum_m X_m cos(q_m z))` for cosine modes and
+    /// `f(z) = 1 / N sum_m X_m sin(q_m z)` for sine modes, where the highest
+    /// sine mode `m = N` is dropped. Since the weights of the coupled modes
+    /// coincide, the linear equations can be solved directly for the raw
+    /// coefficients.
+    fn free_slip_flow_field(&mut self, screening: HydroScreening) {
+        let gs = self.grid_size;
+        let n = gs.x * gs.y;
+        let nz = gs.z;
+        let lz = self.box_size.z;
+        let norm = 1. / (2 * nz * n) as Float;
+        let regularisation = self.regularisation;
+
+        let stress_field = self.stress_field.view().into_shape([3, 3, n, nz]).unwrap();
+        let mut ff = self.flow_field.view_mut().into_shape([3, n, nz]).unwrap();
+        let mut g = self
+            .gradient_meanf
+            .view_mut()
+            .into_shape([3, 3, n, nz])
+            .unwrap();
+
+        Zip::from(ff.axis_iter_mut(Axis(1)))
+            .and(g.axis_iter_mut(Axis(2)))
+            .and(self.wall_velocity.axis_iter_mut(Axis(2)))
+            .and(stress_field.axis_iter(Axis(2)))
+            .and(self.k_mesh.axis_iter(Axis(1)))
+            .par_apply(|mut ff, mut g, mut wall, s, k| {
+                let (kx, ky) = (k[0], k[1]);
+                let i = Complex::new(0., 1.);
+
+                // coefficients of the modes `cos(q_m z)` and `sin(q_m z)`, the
+                // highest sine mode `m = N` is dropped
+                let c = |a, b| s.slice(s![a, b, ..]);
+                let sin = |f: ArrayView<Complex<Float>, Ix1>, m: usize| {
+                    if m > 0 {
+                        f[m - 1]
+                    } else {
+                        Complex::new(0., 0.)
+                    }
+                };
+
+                wall.fill(Complex::new(0., 0.));
+
+                for m in 0..nz {
+                    let q = m as Float * PI / lz;
+                    let k2 = kx * kx + ky * ky + q * q;
+
+                    // force density in x and y (cosine) and z (sine) direction
+                    let f = [
+                        i * kx * c(0, 0)[m] + i * ky * c(0, 1)[m] + q * sin(c(0, 2), m),
+                        i * kx * c(1, 0)[m] + i * ky * c(1, 1)[m] + q * sin(c(1, 2), m),
+                        i * kx * sin(c(2, 0), m) + i * ky * sin(c(2, 1), m) - q * c(2, 2)[m],
+                    ];
+
+                    // With `K = (k_x, k_y, q)`, `u_z -> -i u_z` and `f_z -> -i f_z`
+                    // the equations take the form of the periodic problem.
+                    let mut u = [Complex::new(0., 0.); 3];
+                    if k2 > 0. {
+                        let green = screening.green(1. / k2, 1. / k2) * regularisation.filter(k2);
+                        let kv = [kx, ky, q];
+                        let f = [f[0], f[1], -i * f[2]];
+                        let kf = (f[0] * kv[0] + f[1] * kv[1] + f[2] * kv[2]) / k2;
+                        for ((u, f), k) in u.iter_mut().zip(&f).zip(&kv) {
+                            *u = (f - kf * k) * green;
+                        }
+                        u[2] *= i;
+                    }
+
+                    let weight = if m == 0 { norm } else { 2. * norm };
+                    let sign = if m % 2 == 0 { 1. } else { -1. };
+                    for j in 0..2 {
+                        ff[[j, m]] = u[j];
+                        g[[0, j, m]] = i * kx * u[j];
+                        g[[1, j, m]] = i * ky * u[j];
+                        wall[[0, j]] += u[j] * weight;
+                        wall[[1, j]] += u[j] * weight * sign;
+                    }
+                    g[[2, 2, m]] = u[2] * q;
+
+                    if m > 0 {
+                        ff[[2, m - 1]] = u[2];
+                        g[[0, 2, m - 1]] = i * kx * u[2];
+                        g[[1, 2, m - 1]] = i * ky * u[2];
+                        g[[2, 0, m - 1]] = -u[0] * q;
+                        g[[2, 1, m - 1]] = -u[1] * q;
+                    }
+                }
+
+                let zero = Complex::new(0., 0.);
+                ff[[2, nz - 1]] = zero;
+                g[[0, 2, nz - 1]] = zero;
+                g[[1, 2, nz - 1]] = zero;
+                g[[2, 0, nz - 1]] = zero;
+                g[[2, 1, nz - 1]] = zero;
+            });
+    }
+
+    /// Transforms the flow field and its gradient back from cosine and sine
+    /// modes in z into the mixed representation `u(k_x, k_y, z)`.
+    fn ifft_z(&mut self) {
+        let gs = self.grid_size;
+        let norm = 1. / (2 * gs.x * gs.y * gs.z) as Float;
+        let (idct, idst) = (&self.idct, &self.idst);
+
+        let mut gradient = self
+            .gradient_meanf
+            .view_mut()
+            .into_shape([9, gs.x, gs.y, gs.z])
+            .unwrap();
+
+        // velocities are treated as the first row of a tensor
+        let kinds = (0..3).map(|j| (0, j)).chain((0..9).map(|c| (c / 3, c % 3)));
+
+        self.flow_field
+            .outer_iter_mut()
+            .chain(gradient.outer_iter_mut())
+            .zip(kinds)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .for_each(|(mut v, (i, j))| {
+                if is_cosine(i, j) {
+                    idct.reexecute3d_r2r(&mut v);
+                } else {
+                    idst.reexecute3d_r2r(&mut v);
+                }
+                v.mapv_inplace(|v| v * norm);
+            });
+    }
+
+    /// Adds the homogeneous solution of the Stokes equation, which cancels
+    /// the tangential velocity of the free-slip solution at the walls.
+    fn no_slip_correction(&mut self) {
+        let gs = self.grid_size;
+        let n = gs.x * gs.y;
+        let nz = gs.z;
+        let lz = self.box_size.z;
+
+        let mut ff = self.flow_field.view_mut().into_shape([3, n, nz]).unwrap();
+        let mut g = self
+            .gradient_meanf
+            .view_mut()
+            .into_shape([3, 3, n, nz])
+            .unwrap();
+
+        Zip::from(ff.axis_iter_mut(Axis(1)))
+            .and(g.axis_iter_mut(Axis(2)))
+            .and(self.wall_velocity.axis_iter(Axis(2)))
+            .and(self.k_mesh.axis_iter(Axis(1)))
+            .par_apply(|mut ff, mut g, wall, k| {
+                let (kx, ky) = (k[0], k[1]);
+                let i = Complex::new(0., 1.);
+
+                let wall = [
+                    [-wall[[0, 0]], -wall[[0, 1]]],
+                    [-wall[[1, 0]], -wall[[1, 1]]],
+                ];
+                let h = HomogeneousSolution::new(kx, ky, lz, &wall);
+
+                for iz in 0..nz {
+                    let z = (iz as Float + 0.5) * lz / nz as Float;
+                    let (u, du) = h.at(z);
+
+                    for j in 0..3 {
+                        ff[[j, iz]] += u[j];
+                        g[[0, j, iz]] += i * kx * u[j];
+                        g[[1, j, iz]] += i * ky * u[j];
+                        g[[2, j, iz]] += du[j];
+                    }
+                }
+            });
+    }
+
+    /// Given a distribution `d`, it calculates the mean flow field and its
+    /// vector gradient in the mixed representation `u(k_x, k_y, z)`.
+    pub fn fft_mean_flow_field(&mut self, screening: HydroScreening, dist: &Distribution) {
+        self.fft_mean_flow_field_of_species(screening, slice::from_ref(dist));
+    }
+
+    /// Same as `fft_mean_flow_field`, but for the distributions of all
+    /// particle species in the order of their stress kernels.
+    pub fn fft_mean_flow_field_of_species(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+    ) {
+        self.fft_mean_flow_field_of_weighted_species(screening, dists, &[]);
+    }
+
+    /// Same as `fft_mean_flow_field_of_species`, but with the distributions
+    /// `weighted` of the weighted stresses, see `add_weighted_stress`.
+    pub fn fft_mean_flow_field_of_weighted_species(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+        weighted: &[Distribution],
+    ) {
+        average_stress_of_species(self.stress_field.view_mut(), &self.stress_kernels, dists);
+        add_average_stress_of_species(
+            self.stress_field.view_mut(),
+            &self.weighted_stress_kernels,
+            weighted,
+        );
+        if let Some(m) = &self.magnetic_dipole_stress {
+            m.add_to(self.stress_field.view_mut());
+        }
+        self.solve_stress(screening);
+    }
+
+    /// Calculates the flow field for the stress field, which is stored in
+    /// `self.stress_field`.
+    ///
+    /// Panics for the Brinkman screening, because the correction for the
+    /// no-slip walls solves the unscreened Stokes equation.
+    fn solve_stress(&mut self, screening: HydroScreening) {
+        assert!(
+            !screening.is_brinkman(),
+            "Brinkman screening is not implemented between walls."
+        );
+        self.fft_stress();
+        self.free_slip_flow_field(screening);
+        self.ifft_z();
+        self.no_slip_correction();
+    }
+
+    /// Transforms the flow field and its gradient back to real space and
+    /// updates strain rate and vorticity.
+    fn ifft_xy(&mut self) {
+        let fft = &self.fft_plan_backward;
+        self.flow_field
+            .outer_iter_mut()
+            .into_par_iter()
+            .for_each(|mut v| fft.reexecute3d(&mut v));
+
+        let gs = self.grid_size;
+        self.gradient_meanf
+            .view_mut()
+            .into_shape([9, gs.x, gs.y, gs.z])
+            .unwrap()
+            .outer_iter_mut()
+            .into_par_iter()
+            .for_each(|mut v| fft.reexecute3d(&mut v));
+
+        split_gradient(
+            self.gradient_meanf.view(),
+            self.strain.view_mut(),
+            self.vorticity.view_mut(),
+        );
+    }
+
+    /// Given a distribution `d`, it returns a view into the mean flow field
+    /// and the vector gradient field of it.
+    pub fn mean_flow_field(
+        &mut self,
+        screening: HydroScreening,
+        d: &Distribution,
+    ) -> (
+        ArrayView<'_, Complex<Float>, Ix4>,
+        ArrayView<'_, Complex<Float>, Ix5>,
+    ) {
+        self.mean_flow_field_of_species(screening, slice::from_ref(d))
+    }
+
+    /// Same as `mean_flow_field`, but for a mixture of particle species with
+    /// the distributions `dists`, see `add_species`.
+    pub fn mean_flow_field_of_species(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+    ) -> (
+        ArrayView<'_, Complex<Float>, Ix4>,
+        ArrayView<'_, Complex<Float>, Ix5>,
+    ) {
+        self.mean_flow_field_of_weighted_species(screening, dists, &[])
+    }
+
+    /// Same as `mean_flow_field_of_species`, but with the distributions
+    /// `weighted` of the weighted stresses, see `add_weighted_stress`.
+    pub fn mean_flow_field_of_weighted_species(
+        &mut self,
+        screening: HydroScreening,
+        dists: &[Distribution],
+        weighted: &[Distribution],
+    ) -> (
+        ArrayView<'_, Complex<Float>, Ix4>,
+        ArrayView<'_, Complex<Float>, Ix5>,
+    ) {
+        self.fft_mean_flow_field_of_weighted_species(screening, dists, weighted);
+        self.ifft_xy();
+
+        (self.flow_field.view(), self.gradient_meanf.view())
+    }
+
+    /// Returns a view into the flow field, as calculated by the last call of
+    /// `mean_flow_field`.
+    pub fn get_flow_field(&self) -> ArrayView<'_, Complex<Float>, Ix4> {
+        self.flow_field.view()
+    }
+
+    /// Returns views into the strain rate and vorticity tensor fields of the
+    /// flow field, as calculated by the last call of `mean_flow_field`.
+    pub fn get_strain_vorticity(
+        &self,
+    ) -> (ArrayView<'_, Matrix3, Ix3>, ArrayView<'_, Matrix3, Ix3>) {
+        (self.strain.view(), self.vorticity.view())
+    }
+
+    pub fn get_real_flow_field(&self) -> Array<Float, Ix4> {
+        self.flow_field.map(|v| v.re)
+    }
+}
+
+/// Solution of the homogeneous Stokes equation for the wave vector `(k_x,
+/// k_y)`, which has the given tangential velocities `wall[0]` at `z = 0` and
+/// `wall[1]` at `z = l` and vanishing normal velocity at both walls.
+struct HomogeneousSolution {
+    k: Float,
+    /// unit vector parallel to `(k_x, k_y)`
+    e: [Float; 2],
+    l: Float,
+    /// coefficients of the component perpendicular to k
+    toroidal: [Complex<Float>; 2],
+    /// coefficients of the z component
+    poloidal: [Complex<Float>; 4],
+    /// tangential velocities at the walls, only used for `k = 0`
+    wall: [[Complex<Float>; 2]; 2],
+}
+
+impl HomogeneousSolution {
+    fn new(kx: Float, ky: Float, l: Float, wall: &[[Complex<Float>; 2]; 2]) -> Self {
+        let zero = Complex::new(0., 0.);
+        let k = (kx * kx + ky * ky).sqrt();
+        let mut h = HomogeneousSolution {
+            k,
+            e: [0., 0.],
+            l,
+            toroidal: [zero; 2],
+            poloidal: [zero; 4],
+            wall: *wall,
+        };
+
+        if k == 0. {
+            return h;
+        }
+
+        let i = Complex::new(0., 1.);
+        h.e = [kx / k, ky / k];
+        let e = (-k * l).exp();
+
+        // component perpendicular to k as superposition of `exp(-k z)` and
+        // `exp(-k (l - z))`
+        let b0 = wall[0][1] * h.e[0] - wall[0][0] * h.e[1];
+        let b1 = wall[1][1] * h.e[0] - wall[1][0] * h.e[1];
+        h.toroidal = [(b0 - b1 * e) / (1. - e * e), (b1 - b0 * e) / (1. - e * e)];
+
+        // The component parallel to k follows from incompressibility, `u_l = i
+        // u_z' / k`. Hence, the derivatives of `u_z` are prescribed at the
+        // walls. Rows: u_z(0), u_z(l), u_z'(0), u_z'(l)
+        let a0 = wall[0][0] * h.e[0] + wall[0][1] * h.e[1];
+        let a1 = wall[1][0] * h.e[0] + wall[1][1] * h.e[1];
+        let m = [
+            [1., 0., e, l * e],
+            [e, l * e, 1., 0.],
+            [-k, 1., k * e, (k * l - 1.) * e],
+            [-k * e, (1. - k * l) * e, k, -1.],
+        ];
+        h.poloidal = solve4(m, [zero, zero, -i * k * a0, -i * k * a1]);
+
+        h
+    }
+
+    /// Returns the velocity and its derivative in z direction at height `z`.
+    fn at(&self, z: Float) -> ([Complex<Float>; 3], [Complex<Float>; 3]) {
+        let zero = Complex::new(0., 0.);
+        let (k, l) = (self.k, self.l);
+
+        if k == 0. {
+            let w = &self.wall;
+            let u = [
+                w[0][0] * (1. - z / l) + w[1][0] * z / l,
+                w[0][1] * (1. - z / l) + w[1][1] * z / l,
+                zero,
+            ];
+            let du = [(w[1][0] - w[0][0]) / l, (w[1][1] - w[0][1]) / l, zero];
+            return (u, du);
+        }
+
+        let i = Complex::new(0., 1.);
+        let [ex, ey] = self.e;
+        let e0 = (-k * z).exp();
+        let e1 = (-k * (l - z)).exp();
+
+        let [alpha, beta] = self.toroidal;
+        let w = alpha * e0 + beta * e1;
+        let dw = (beta * e1 - alpha * e0) * k;
+
+        let c = &self.poloidal;
+        let p0 = c[0] + c[1] * z;
+        let p1 = c[2] + c[3] * (l - z);
+        let uz = p0 * e0 + p1 * e1;
+        let duz = (c[1] - p0 * k) * e0 + (p1 * k - c[3]) * e1;
+        let dduz = (p0 * k - c[1] * 2.) * k * e0 + (p1 * k - c[3] * 2.) * k * e1;
+
+        let ul = i * duz / k;
+        let dul = i * dduz / k;
+
+        (
+            [ul * ex - w * ey, ul * ey + w * ex, uz],
+            [dul * ex - dw * ey, dul * ey + dw * ex, duz],
+        )
+    }
+}
+
+/// Solves the linear system `m x = b` by Gaussian elimination with partial
+/// pivoting.
+fn solve4(mut m: [[Float; 4]; 4], mut b: [Complex<Float>; 4]) -> [Complex<Float>; 4] {
+    for col in 0..4 {
+        let pivot = (col..4)
+            .max_by(|&r1, &r2| m[r1][col].abs().partial_cmp(&m[r2][col].abs()).unwrap())
+            .unwrap();
+        m.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot_row = m[col];
+        for row in col + 1..4 {
+            let f = m[row][col] / pivot_row[col];
+            for (a, p) in m[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *a -= f * p;
+            }
+            let bc = b[col];
+            b[row] -= bc * f;
+        }
+    }
+
+    let mut x = [Complex::new(0., 0.); 4];
+    for row in (0..4).rev() {
+        let mut v = b[row];
+        for c in row + 1..4 {
+            v -= x[c] * m[row][c];
+        }
+        x[row] = v / m[row][row];
+    }
+    x
+}
@@ -0,0 +1,157 @@
+use super::*;
+use crate::consts::TWOPI;
+use crate::flowfield::stress::stresses::stress_active;
+
+fn solver(gs: GridSize, bs: BoxSize) -> WallSpectralSolver {
+    WallSpectralSolver::with_orientation(
+        gs,
+        bs,
+        OrientationRepresentation::UniformGrid,
+        stress_active,
+    )
+}
+
+/// Solves the flow field for a given stress field.
+fn solve<F>(s: &mut WallSpectralSolver, stress: F)
+where
+    F: Fn(usize, usize, Float, Float, Float) -> Float,
+{
+    let gs = s.grid_size;
+    let bs = s.box_size;
+    for ((i, j, ix, iy, iz), v) in s.stress_field.indexed_iter_mut() {
+        let x = (ix as Float + 0.5) * bs.x / gs.x as Float;
+        let y = (iy as Float + 0.5) * bs.y / gs.y as Float;
+        let z = (iz as Float + 0.5) * bs.z / gs.z as Float;
+        *v = Complex::new(stress(i, j, x, y, z), 0.);
+    }
+
+    s.solve_stress(HydroScreening::None);
+    s.ifft_xy();
+}
+
+/// A shear stress, that is homogeneous in x and y, drives a flow, which has
+/// to vanish at both walls, i.e. the solution of `-u_x'' = pi / L cos(pi z /
+/// L)` with `u_x(0) = u_x(L) = 0`.
+#[test]
+fn homogeneous_shear_stress() {
+    let gs = GridSize {
+        x: 4,
+        y: 3,
+        z: 16,
+        phi: 1,
+        theta: 1,
+    };
+    let bs = BoxSize {
+        x: 2.,
+        y: 3.,
+        z: 5.,
+    };
+    let l = bs.z;
+
+    let mut s = solver(gs, bs);
+    solve(&mut s, |i, j, _, _, z| {
+        if (i, j) == (0, 2) || (i, j) == (2, 0) {
+            (PI * z / l).sin()
+        } else {
+            0.
+        }
+    });
+
+    for ((c, _, _, iz), v) in s.flow_field.indexed_iter() {
+        let z = (iz as Float + 0.5) * l / gs.z as Float;
+        let expected = if c == 0 {
+            l / PI * ((PI * z / l).cos() - 1.) + 2. * z / PI
+        } else {
+            0.
+        };
+        assert!(
+            (v - expected).norm() < 1e-10,
+            "u_{} = {} != {} at z = {}",
+            c,
+            v,
+            expected,
+            z
+        );
+    }
+
+    for ((i, j, _, _, iz), v) in s.gradient_meanf.indexed_iter() {
+        let z = (iz as Float + 0.5) * l / gs.z as Float;
+        let expected = if (i, j) == (2, 0) {
+            2. / PI - (PI * z / l).sin()
+        } else {
+            0.
+        };
+        assert!(
+            (v - expected).norm() < 1e-10,
+            "g_{}{} = {} != {} at z = {}",
+            i,
+            j,
+            v,
+            expected,
+            z
+        );
+    }
+}
+
+/// For a stress field, which varies in all directions, the velocity
+/// extrapolated to the walls has to vanish and the flow has to be
+/// incompressible.
+#[test]
+fn no_slip_at_walls() {
+    let gs = GridSize {
+        x: 8,
+        y: 8,
+        z: 64,
+        phi: 1,
+        theta: 1,
+    };
+    let bs = BoxSize {
+        x: 10.,
+        y: 8.,
+        z: 4.,
+    };
+    let (kx, ky, q) = (TWOPI / bs.x, TWOPI / bs.y, PI / bs.z);
+
+    let mut s = solver(gs, bs);
+    solve(&mut s, |i, j, x, y, z| {
+        let a = (kx * x).cos() * (ky * y).sin();
+        match (i, j) {
+            (0, 2) | (2, 0) => a * (q * z).sin(),
+            (1, 2) | (2, 1) => 0.5 * a * (3. * q * z).sin(),
+            (0, 0) => a * (2. * q * z).cos(),
+            (1, 1) => -a * (q * z).cos(),
+            (0, 1) | (1, 0) => 0.3 * (kx * x).sin() * (ky * y).cos(),
+            _ => 0.2 * a * (q * z).cos(),
+        }
+    });
+
+    let u = s.get_real_flow_field();
+    let max = u.iter().fold(0., |m: Float, v| m.max(v.abs()));
+    assert!(max > 1e-3);
+    assert!(s.flow_field.iter().all(|v| v.im.abs() < 1e-10 * max));
+
+    // quadratic extrapolation from the three cells closest to the walls
+    let nz = gs.z;
+    for c in 0..3 {
+        for ix in 0..gs.x {
+            for iy in 0..gs.y {
+                let v = |iz| u[[c, ix, iy, iz]];
+                let bottom = (15. * v(0) - 10. * v(1) + 3. * v(2)) / 8.;
+                let top = (15. * v(nz - 1) - 10. * v(nz - 2) + 3. * v(nz - 3)) / 8.;
+                assert!(bottom.abs() < 1e-3 * max, "u_{} = {} at z = 0", c, bottom);
+                assert!(top.abs() < 1e-3 * max, "u_{} = {} at z = L", c, top);
+            }
+        }
+    }
+
+    let g = &s.gradient_meanf;
+    let max_g = g.iter().fold(0., |m: Float, v| m.max(v.re.abs()));
+    for ix in 0..gs.x {
+        for iy in 0..gs.y {
+            for iz in 0..nz {
+                let div = (0..3).map(|i| g[[i, i, ix, iy, iz]].re).sum::<Float>();
+                assert!(div.abs() < 1e-10 * max_g, "div u = {}", div);
+            }
+        }
+    }
+}
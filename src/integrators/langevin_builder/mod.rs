@@ -5,7 +5,9 @@ mod mod_test;
 
 pub mod modifiers;
 
-use crate::particle::{CosSinOrientation, Particle, ParticleVector};
+use crate::particle::{
+    CosSinOrientation, Particle, ParticleAttributes, ParticleVector, WallInteraction,
+};
 use crate::BoxSize;
 use crate::Float;
 
@@ -13,6 +15,8 @@ use crate::Float;
 pub struct OriginalParticle {
     pub vector: ParticleVector,
     pub orientation_angles: CosSinOrientation,
+    /// attributes, which are kept by the modified particle
+    pub attributes: ParticleAttributes,
 }
 
 pub struct LangevinBuilder(OriginalParticle);
@@ -34,6 +38,7 @@ impl LangevinBuilder {
                 orientation: cs.to_orientation_vector(),
             },
             orientation_angles: cs,
+            attributes: p.attributes,
         })
     }
 
@@ -119,8 +124,32 @@ impl Modification {
     }
 
     pub fn finalize(self, bs: &BoxSize) -> Particle {
-        let mut p = Particle::from(self.old.vector + self.delta);
+        let mut p = self.particle();
         p.pbc(bs);
         p
     }
+
+    /// Same as `finalize`, but applies Lees-Edwards boundary conditions with
+    /// the given `offset` of the images above the box.
+    pub fn finalize_lees_edwards(self, bs: &BoxSize, offset: Float) -> Particle {
+        let mut p = self.particle();
+        p.lees_edwards_pbc(bs, offset);
+        p
+    }
+
+    /// Same as `finalize`, but with walls at `z = 0` and `z = L_z`, see
+    /// `Particle::walls_pbc`.
+    pub fn finalize_walls(self, bs: &BoxSize, interaction: WallInteraction) -> Particle {
+        let mut p = self.particle();
+        p.walls_pbc(bs, interaction);
+        p
+    }
+
+    /// Returns the modified particle with the attributes of the original one
+    /// without applying any boundary conditions.
+    fn particle(&self) -> Particle {
+        let mut p = Particle::from(self.old.vector + self.delta);
+        p.attributes = self.old.attributes;
+        p
+    }
 }
@@ -56,6 +56,34 @@ fn test_langevin_builder_conversion() {
     }
 }
 
+#[test]
+/// The attributes of a polydisperse particle are kept by every finalization
+fn test_langevin_builder_attributes() {
+    let bs = BoxSize {
+        x: 10.,
+        y: 10.,
+        z: 10.,
+    };
+    let mut p = Particle::new(9.5, 2., 3., 0.5, 1., &bs);
+    p.attributes = ParticleAttributes {
+        speed: 2.,
+        shape: 0.5,
+        magnetic_moment: 3.,
+    };
+
+    let m = || {
+        LangevinBuilder::new(&p)
+            .with(self_propulsion)
+            .step(&TimeStep(1.))
+    };
+    assert_eq!(m().finalize(&bs).attributes, p.attributes);
+    assert_eq!(m().finalize_lees_edwards(&bs, 1.).attributes, p.attributes);
+    assert_eq!(
+        m().finalize_walls(&bs, WallInteraction::Reflect).attributes,
+        p.attributes
+    );
+}
+
 #[test]
 fn test_langevin_builder_step() {
     let bs = BoxSize {
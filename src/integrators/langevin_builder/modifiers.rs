@@ -16,9 +16,9 @@ mod modifiers_test;
 use super::OriginalParticle;
 use crate::magnetic_interaction;
 use crate::particle::{OrientationVector, ParticleVector, PositionVector};
-use crate::vector::VectorD;
+use crate::steric::StericForce;
+use crate::vector::{mat_vec, Matrix3, VectorD};
 use crate::Float;
-use ndarray::{Array, ArrayView, Ix2};
 use quaternion;
 
 /// Does not change anything, just returns the given `delta`.
@@ -44,6 +44,21 @@ pub fn self_propulsion(p: OriginalParticle, delta: ParticleVector) -> ParticleVe
         }
 }
 
+/// Same as `self_propulsion`, but with the swimming speed `speed` instead of
+/// one.
+#[inline(always)]
+pub fn scaled_self_propulsion(
+    p: OriginalParticle,
+    delta: ParticleVector,
+    speed: Float,
+) -> ParticleVector {
+    delta
+        + ParticleVector {
+            position: p.vector.orientation.to() * speed,
+            orientation: OrientationVector::zero(),
+        }
+}
+
 /// Translates the particle in the convective local flow field.
 #[inline(always)]
 pub fn convection(_p: OriginalParticle, delta: ParticleVector, flow: VectorD) -> ParticleVector {
@@ -59,7 +74,7 @@ pub fn convection(_p: OriginalParticle, delta: ParticleVector, flow: VectorD) ->
 pub fn magnetic_dipole_dipole_force(
     p: OriginalParticle,
     delta: ParticleVector,
-    (drag, grad_b): (Float, ArrayView<Float, Ix2>),
+    (drag, grad_b): (Float, &Matrix3),
 ) -> ParticleVector {
     delta
         + ParticleVector {
@@ -68,7 +83,23 @@ pub fn magnetic_dipole_dipole_force(
         }
 }
 
-/// Translates the particle due to magnetic dipole forces.
+/// Translates the particle due to the force `grad(m . B)` in the gradient
+/// `grad_b` of the external magnetic field.
+#[inline(always)]
+pub fn external_field_force(
+    p: OriginalParticle,
+    delta: ParticleVector,
+    (drag, grad_b): (Float, &Matrix3),
+) -> ParticleVector {
+    delta
+        + ParticleVector {
+            position: magnetic_interaction::mean_force(grad_b, &p.vector.orientation).to() * drag,
+            orientation: OrientationVector::zero(),
+        }
+}
+
+/// Translates the particle due to the mean-field steric repulsion `grad_d`,
+/// i.e. the negative density gradient times the volume exclusion parameter.
 #[inline(always)]
 pub fn volume_exclusion_force(
     _p: OriginalParticle,
@@ -82,6 +113,37 @@ pub fn volume_exclusion_force(
         }
 }
 
+/// Translates and rotates the particle due to the short-range steric
+/// interactions with its neighbours, where the torque rotates the orientation
+/// with the angular velocity `f.torque`.
+#[inline(always)]
+pub fn steric_interaction(
+    p: OriginalParticle,
+    delta: ParticleVector,
+    f: &StericForce,
+) -> ParticleVector {
+    delta
+        + ParticleVector {
+            position: f.force.to(),
+            orientation: f.torque.cross(&p.vector.orientation).to(),
+        }
+}
+
+/// Translates the particle with the constant sedimentation velocity
+/// `velocity`.
+#[inline(always)]
+pub fn sedimentation(
+    _p: OriginalParticle,
+    delta: ParticleVector,
+    velocity: VectorD,
+) -> ParticleVector {
+    delta
+        + ParticleVector {
+            position: velocity.to(),
+            orientation: OrientationVector::zero(),
+        }
+}
+
 /// Translates according to translational diffusion. CAUTION: Must be called
 /// only after `.step`. The random vector should already include the timestep
 /// and translatinal diffusion constant.
@@ -98,16 +160,16 @@ pub fn translational_diffusion(
         }
 }
 
-/// Rotates particle to align with external magnetic field.
+/// Rotates particle to align with external magnetic field `b`, which is
+/// given as the rate of reorientation.
 #[inline(always)]
 pub fn external_field_alignment(
     p: OriginalParticle,
     delta: ParticleVector,
-    realignment: Float,
+    b: VectorD,
 ) -> ParticleVector {
-    // magnetic field points in y direction
-    // CAUTION, changing this requires a change of magnetic stress as well
-    let mut b: VectorD = [0., realignment, 0.].into();
+    // CAUTION, the magnetic stress assumes the uniform field in y direction
+    let mut b = b;
     b -= p.vector.orientation * p.vector.orientation.dot(&b);
 
     delta
@@ -117,27 +179,54 @@ pub fn external_field_alignment(
         }
 }
 
+/// Rotates bottom-heavy particles to align against gravity (gyrotaxis). `up`
+/// points against gravity and its length is the reorientation rate.
+#[inline(always)]
+pub fn gyrotaxis(p: OriginalParticle, delta: ParticleVector, up: VectorD) -> ParticleVector {
+    let mut t = up;
+    t -= p.vector.orientation * p.vector.orientation.dot(&up);
+
+    delta
+        + ParticleVector {
+            position: PositionVector::zero(),
+            orientation: t.to(),
+        }
+}
+
+/// Rotates the particle to swim up the concentration gradient of a chemical.
+/// `grad_c` is the gradient scaled by the chemotactic sensitivity.
+#[inline(always)]
+pub fn chemotactic_rotation(
+    p: OriginalParticle,
+    delta: ParticleVector,
+    grad_c: VectorD,
+) -> ParticleVector {
+    let mut t = grad_c;
+    t -= p.vector.orientation * p.vector.orientation.dot(&grad_c);
+
+    delta
+        + ParticleVector {
+            position: PositionVector::zero(),
+            orientation: t.to(),
+        }
+}
+
 /// Rotates the particle due to coupling to the flow's vorticity. Anti-symmetric
 /// Jeffrey's term.
 #[inline(always)]
 pub fn jeffrey_vorticity(
     p: OriginalParticle,
     delta: ParticleVector,
-    vortm: ArrayView<Float, Ix2>,
+    vortm: &Matrix3,
 ) -> ParticleVector {
     // (1-nn) . (-W[u] . n) == -W[u] . n == 0.5 * Curl[u] x n
 
-    let n = p.vector.orientation;
-
-    let n = Array::from_vec(n.v.to_vec());
-    let f = vortm.dot(&n) * (-1.);
-
-    let r = unsafe { [*f.uget(0), *f.uget(1), *f.uget(2)] }.into();
+    let f = mat_vec(vortm, &p.vector.orientation) * (-1.);
 
     delta
         + ParticleVector {
             position: PositionVector::zero(),
-            orientation: r,
+            orientation: f,
         }
 }
 
@@ -147,24 +236,20 @@ pub fn jeffrey_vorticity(
 pub fn jeffrey_strain(
     p: OriginalParticle,
     delta: ParticleVector,
-    (shape, strainm): (Float, ArrayView<Float, Ix2>),
+    (shape, strainm): (Float, &Matrix3),
 ) -> ParticleVector {
     // (1-nn) . (g E[u] . n)
 
     let n = p.vector.orientation;
 
-    let n = Array::from_vec(n.v.to_vec());
-    let mut f = strainm.dot(&n);
+    let mut f = mat_vec(strainm, &n);
     f *= shape;
-    let nne = &n * f.dot(&n);
-    f -= &nne;
-
-    let r = unsafe { [*f.uget(0), *f.uget(1), *f.uget(2)] }.into();
+    f -= n * f.dot(&n);
 
     delta
         + ParticleVector {
             position: PositionVector::zero(),
-            orientation: r,
+            orientation: f,
         }
 }
 
@@ -186,6 +271,7 @@ pub fn magnetic_dipole_dipole_rotation(
 }
 
 /// Parameters for rotational diffusion
+#[derive(Clone, Copy, Debug)]
 pub struct RotDiff {
     /// Angle of rotation axis perpendicular to the current orientation with
     /// respect to the x axis.
@@ -229,3 +315,33 @@ pub fn rotational_diffusion(
             orientation: new - p.vector.orientation,
         }
 }
+
+/// Rotates particle by a tumble of angle `r.rotate_angle` around an axis
+/// perpendicular to its orientation. Needs to come after `.step`! The tumble
+/// angle does not depend on the timestep.
+#[inline(always)]
+pub fn tumble(p: OriginalParticle, delta: ParticleVector, r: &RotDiff) -> ParticleVector {
+    // a tumble is a single finite rotation like a diffusive step
+    rotational_diffusion(p, delta, r)
+}
+
+/// Rotates particle around the z axis according to rotational diffusion in
+/// the plane. Needs to come after `.step`! Timestep must already be included
+/// in the rotation angle.
+#[inline(always)]
+pub fn planar_rotational_diffusion(
+    p: OriginalParticle,
+    delta: ParticleVector,
+    angle: Float,
+) -> ParticleVector {
+    let (sin, cos) = angle.sin_cos();
+    let v = p.vector.orientation;
+    let new: OrientationVector = [v[0] * cos - v[1] * sin, v[0] * sin + v[1] * cos, v[2]].into();
+
+    delta
+        + ParticleVector {
+            position: PositionVector::zero(),
+            // required to make order of modifiers independent
+            orientation: new - p.vector.orientation,
+        }
+}
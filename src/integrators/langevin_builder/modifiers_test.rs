@@ -2,7 +2,6 @@
 use super::super::*;
 use super::*;
 use crate::BoxSize;
-use ndarray::arr2;
 #[cfg(feature = "single")]
 use std::f32::consts::PI;
 #[cfg(not(feature = "single"))]
@@ -41,6 +40,11 @@ fn self_propulsion() {
     quicktest_modifier!(self_propulsion; (0., 0., 1., 0., 0.));
 }
 
+#[test]
+fn scaled_self_propulsion() {
+    quicktest_modifier!(scaled_self_propulsion; 0.5; (0., 0., 0.5, 0., 0.));
+}
+
 #[test]
 fn convection() {
     quicktest_modifier!(convection; [1., 1., 0.].into(); (1., 1., 0., 0., 0.));
@@ -48,9 +52,26 @@ fn convection() {
 
 #[test]
 fn magnetic_dipole_dipole_force() {
-    let grad_b = arr2(&[[0., 0., 1.], [0., 0., 1.], [0., 0., 1.]]);
+    let grad_b = [[0., 0., 1.], [0., 0., 1.], [0., 0., 1.]];
+
+    quicktest_modifier!(magnetic_dipole_dipole_force; (0.1, &grad_b); (0.1, 0.1, 0.1, 0., 0.));
+}
+
+#[test]
+fn external_field_force() {
+    let grad_b = [[0., 0., 0.5], [0., 0., 0.], [0., 0., -1.]];
+
+    quicktest_modifier!(external_field_force; (0.2, &grad_b); (0.1, 0., -0.2, 0., 0.));
+}
+
+#[test]
+fn sedimentation() {
+    quicktest_modifier!(sedimentation; [0., 0., -0.5].into(); (0., 0., -0.5, 0., 0.));
+}
 
-    quicktest_modifier!(magnetic_dipole_dipole_force; (0.1, grad_b.view()); (0.1, 0.1, 0.1, 0., 0.));
+#[test]
+fn volume_exclusion_force() {
+    quicktest_modifier!(volume_exclusion_force; [0.25, -1., 0.].into(); (0.25, -1., 0., 0., 0.));
 }
 
 #[test]
@@ -63,23 +84,54 @@ fn external_field_alignment() {
     let p = Particle::new(0., 0., 0., 0., PI / 2., &BS);
     let l = LangevinBuilder::new(&p);
     let p = l
-        .with_param(super::external_field_alignment, 0.1)
+        .with_param(super::external_field_alignment, [0., 0.1, 0.].into())
         .finalize(&BS);
     let expect = Particle::new(0., 0., 0., 0.09966865249116204, PI / 2., &BS);
 
     assert_eq!(p, expect);
 }
 
+#[test]
+fn gyrotaxis() {
+    let p = Particle::new(0., 0., 0., 0., PI / 2., &BS);
+    let l = LangevinBuilder::new(&p);
+    let p = l
+        .with_param(super::gyrotaxis, [0., 0., 0.1].into())
+        .finalize(&BS);
+
+    // tilted upwards by atan(0.1)
+    assert!(
+        (p.orientation.theta - (PI / 2. - 0.09966865249116204)).abs() < 1e-12,
+        "{:?}",
+        p
+    );
+    assert!(p.orientation.phi.abs() < 1e-12, "{:?}", p);
+}
+
+#[test]
+fn chemotactic_rotation() {
+    quicktest_modifier!(chemotactic_rotation; [0.01, 0., 0.].into(); (0., 0., 0., 0., 0.009999666686665076));
+}
+
+#[test]
+fn steric_interaction() {
+    let f = StericForce {
+        force: [0.5, 0., 0.].into(),
+        torque: [0., 0.01, 0.].into(),
+    };
+    quicktest_modifier!(steric_interaction; &f; (0.5, 0., 0., 0., 0.009999666686665076));
+}
+
 #[test]
 fn jeffrey_vorticity() {
-    let vortm = arr2(&[[0.0, 0.0, -0.01], [0.0, 0.0, 0.0], [0.01, 0.0, 0.0]]);
-    quicktest_modifier!(jeffrey_vorticity; vortm.view(); (0., 0., 0., 0., 0.009999666686665076));
+    let vortm = [[0.0, 0.0, -0.01], [0.0, 0.0, 0.0], [0.01, 0.0, 0.0]];
+    quicktest_modifier!(jeffrey_vorticity; &vortm; (0., 0., 0., 0., 0.009999666686665076));
 }
 
 #[test]
 fn jeffrey_strain() {
-    let strainm = arr2(&[[0.0, 0.0, 0.01], [0.0, 0.0, 0.02], [0.01, 0.02, 0.0]]);
-    quicktest_modifier!(jeffrey_vorticity; strainm.view(); (0., 0., 0., 4.2487413713838835, 0.022356954112670246));
+    let strainm = [[0.0, 0.0, 0.01], [0.0, 0.0, 0.02], [0.01, 0.02, 0.0]];
+    quicktest_modifier!(jeffrey_vorticity; &strainm; (0., 0., 0., 4.2487413713838835, 0.022356954112670246));
 }
 
 #[test]
@@ -95,3 +147,29 @@ fn rotational_diffusion() {
     };
     quicktest_modifier!(rotational_diffusion; &r; (0., 0., 0., 0., 0.09999999999999987));
 }
+
+#[test]
+fn tumble() {
+    let r = RotDiff {
+        axis_angle: 0.,
+        rotate_angle: PI / 2.,
+    };
+    let p = Particle::new(0., 0., 0., 0., PI / 2., &BS);
+    let p = LangevinBuilder::new(&p)
+        .with_param(super::tumble, &r)
+        .finalize(&BS);
+
+    // the orientation (1, 0, 0) is rotated around the y axis to (0, 0, -1)
+    assert!((p.orientation.theta - PI).abs() < 1e-6, "{:?}", p);
+}
+
+#[test]
+fn planar_rotational_diffusion() {
+    let p = Particle::new(0., 0., 0., 0.3, PI / 2., &BS);
+    let p = LangevinBuilder::new(&p)
+        .with_param(super::planar_rotational_diffusion, 0.1)
+        .finalize(&BS);
+
+    assert!((p.orientation.phi - 0.4).abs() < 1e-12, "{:?}", p);
+    assert!((p.orientation.theta - PI / 2.).abs() < 1e-12, "{:?}", p);
+}
@@ -30,11 +30,10 @@ use crate::magnetic_interaction::mean_force;
 use crate::mesh::grid_width::GridWidth;
 use crate::particle::{CosSinOrientation, OrientationVector, Particle, Position};
 use crate::vector::vorticity::vorticity3d_dispatch;
-use crate::vector::{Vector, VectorD};
+use crate::vector::{Matrix3, Vector, VectorD};
 use crate::Float;
 use crate::{BoxSize, GridSize};
-use ndarray::s;
-use ndarray::{Array, ArrayView, Ix2, Ix4, Ix5};
+use ndarray::{ArrayView, Ix4, Ix5};
 use ndarray_parallel::prelude::*;
 use num_complex::Complex;
 use quaternion;
@@ -122,7 +121,7 @@ impl Integrator {
 
         let (b, gradb) = match magnetic_field {
             Some((v, m)) => (field_at_cell_c(&v, idx), vector_gradient_at_cell(&m, idx)),
-            None => (VectorD::default(), Array::default((3, 3))),
+            None => (VectorD::default(), Matrix3::default()),
         };
 
         // precompute trigonometric functions
@@ -135,7 +134,7 @@ impl Integrator {
         // POSITION ----------------
 
         // Get force in magnetic field
-        let fb = mean_force(gradb.view(), &vector) * param.drag;
+        let fb = mean_force(&gradb, &vector) * param.drag;
 
         let mut new_position: Vector<Position> = p.position.to_vector();
         // convection + self-propulsion + magnetic drag force
@@ -241,11 +240,19 @@ fn field_at_cell_c(field: &ArrayView<Complex<Float>, Ix4>, idx: (usize, usize, u
     f.into()
 }
 
-fn vector_gradient_at_cell(
+/// Returns the real part of the vector gradient `field` in the grid cell
+/// `idx`. The cell index is not checked.
+pub fn vector_gradient_at_cell(
     field: &ArrayView<Complex<Float>, Ix5>,
     idx: (usize, usize, usize),
-) -> Array<Float, Ix2> {
-    field.slice(s![.., .., idx.0, idx.1, idx.2]).map(|v| v.re)
+) -> Matrix3 {
+    let mut m = Matrix3::default();
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, e) in row.iter_mut().enumerate() {
+            *e = unsafe { (*field.uget((i, j, idx.0, idx.1, idx.2))).re };
+        }
+    }
+    m
 }
 
 fn rotational_diffusion_quat_mut(
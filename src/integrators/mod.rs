@@ -1,5 +1,6 @@
 pub mod langevin_builder;
 pub mod langevin_old;
+pub mod tumbling;
 
 pub use self::langevin_builder::LangevinBuilder;
 pub use self::langevin_old as langevin;
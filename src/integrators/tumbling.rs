@@ -0,0 +1,182 @@
+//! Run-and-tumble reorientation dynamics.
+//!
+//! Particles tumble at random times, which are given by a Poisson process
+//! with rate `rate`. During a tumble the orientation is rotated around a
+//! random axis perpendicular to it by the tumble angle `alpha`, whose
+//! distribution is given by `TumbleAngle`. On long time scales, tumbling
+//! decorrelates the orientation like rotational diffusion with the effective
+//! diffusion constant `D = rate * (1 - <cos(alpha)>) / 2` in three dimensions.
+//!
+//! In the planar modes, the orientation is rotated in the plane by `alpha`
+//! or `-alpha` with equal probability, where `alpha` follows the same
+//! distribution as in 3D. The effective diffusion constant is then `D = rate *
+//! (1 - <cos(alpha)>)`.
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./tumbling_test.rs"]
+mod tumbling_test;
+
+use crate::{Dimensionality, Float};
+use serde_derive::{Deserialize, Serialize};
+
+/// Distribution of the tumble angle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TumbleAngle {
+    /// The new orientation is uniformly distributed on the unit sphere.
+    #[default]
+    Uniform,
+    /// The new orientation follows a von Mises-Fisher distribution around the
+    /// old orientation, `p(n) ~ exp(kappa n . n_old)`, where the
+    /// concentration `kappa` is chosen to give the mean cosine
+    /// `<cos(alpha)>`. E. coli tumbles with a mean cosine of about 0.33.
+    VonMisesFisher { mean_cosine: Float },
+}
+
+impl TumbleAngle {
+    /// Returns the mean cosine of the tumble angle.
+    pub fn mean_cosine(&self) -> Float {
+        match *self {
+            TumbleAngle::Uniform => 0.,
+            TumbleAngle::VonMisesFisher { mean_cosine } => mean_cosine,
+        }
+    }
+
+    /// Returns `true`, if the mean cosine lies in the open interval `(-1,
+    /// 1)`.
+    pub fn is_valid(&self) -> bool {
+        let c = self.mean_cosine();
+        c > -1. && c < 1.
+    }
+
+    /// Returns the concentration `kappa` of the von Mises-Fisher distribution
+    /// with the given mean cosine, i.e. the root of the Langevin function
+    /// `coth(kappa) - 1 / kappa = <cos(alpha)>`.
+    pub fn concentration(&self) -> Float {
+        let c = self.mean_cosine();
+        assert!(
+            self.is_valid(),
+            "Mean cosine of the tumble angle must lie in (-1, 1), but is {}.",
+            c
+        );
+
+        // The Langevin function is odd and monotonic and approaches `1 - 1 /
+        // kappa` for large `kappa`, which gives an upper bound of the root.
+        let mut lo: Float = 0.;
+        let mut hi = 2. / (1. - c.abs()) + 1.;
+        for _ in 0..200 {
+            let mid = 0.5 * (lo + hi);
+            if langevin(mid) < c.abs() {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        (0.5 * (lo + hi)).copysign(c)
+    }
+}
+
+/// Run-and-tumble parameters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tumbling {
+    /// Mean number of tumbles per unit time
+    pub rate: Float,
+    // tables need to come after values for the TOML serialization
+    #[serde(default)]
+    pub angle: TumbleAngle,
+}
+
+impl Tumbling {
+    /// Returns `true`, if particles tumble at all.
+    pub fn is_enabled(&self) -> bool {
+        self.rate > 0.
+    }
+
+    /// Returns the probability to tumble during a timestep of length
+    /// `timestep`.
+    pub fn probability(&self, timestep: Float) -> Float {
+        1. - (-self.rate * timestep).exp()
+    }
+
+    /// Same as `probability`, but the tumble rate is biased by `bias`, i.e.
+    /// multiplied by `1 - bias`. For chemotaxis, `bias` is proportional to
+    /// the concentration gradient along the orientation, so that tumbles are
+    /// suppressed when swimming up the gradient.
+    pub fn biased_probability(&self, timestep: Float, bias: Float) -> Float {
+        1. - (-self.rate * (1. - bias).max(0.) * timestep).exp()
+    }
+
+    /// Returns the rotational diffusion constant, that decorrelates the
+    /// orientation on the same time scale as tumbling.
+    pub fn effective_rotational_diffusion(&self, dim: Dimensionality) -> Float {
+        let d = self.rate * (1. - self.angle.mean_cosine());
+        if dim.is_planar() {
+            d
+        } else {
+            0.5 * d
+        }
+    }
+
+    /// Returns a sampler for the tumble angle, that caches the concentration
+    /// of the distribution.
+    pub fn sampler(&self) -> TumbleSampler {
+        TumbleSampler {
+            kappa: match self.angle {
+                TumbleAngle::Uniform => 0.,
+                TumbleAngle::VonMisesFisher { .. } => self.angle.concentration(),
+            },
+        }
+    }
+}
+
+/// Draws tumble angles without recalculating the concentration of the
+/// distribution.
+#[derive(Debug, Clone, Copy)]
+pub struct TumbleSampler {
+    kappa: Float,
+}
+
+impl TumbleSampler {
+    /// Transforms a uniformly distributed random number `x` in `[0, 1)` into
+    /// the cosine of a tumble angle by inverse transform sampling.
+    pub fn cos_angle(&self, x: Float) -> Float {
+        cos_from_concentration(self.kappa, x)
+    }
+
+    /// Same as `cos_angle`, but returns the tumble angle in `[0, pi]`.
+    pub fn angle(&self, x: Float) -> Float {
+        self.cos_angle(x).acos()
+    }
+}
+
+/// Langevin function `coth(x) - 1 / x`.
+fn langevin(x: Float) -> Float {
+    if x.abs() < 1e-4 {
+        x / 3.
+    } else {
+        1. / x.tanh() - 1. / x
+    }
+}
+
+/// Inverse of the cumulative distribution of `cos(alpha)` with density `~
+/// exp(kappa cos(alpha))`, see also `pdf_homogeneous_fixpoint`.
+fn cos_from_concentration(kappa: Float, x: Float) -> Float {
+    if kappa == 0. {
+        return 2. * x - 1.;
+    }
+
+    // Sample for positive concentration to avoid overflow, the distribution
+    // for `-kappa` is mirrored. `x + (1 - x) exp(-2k)` is rearranged to stay
+    // accurate for small concentrations.
+    let k = kappa.abs();
+    let c = 1. + ((1. - x) * (-2. * k).exp_m1()).ln_1p() / k;
+    let c = c.clamp(-1., 1.);
+    if kappa < 0. {
+        -c
+    } else {
+        c
+    }
+}
@@ -0,0 +1,72 @@
+use super::*;
+use crate::test_helper::equal_floats_eps;
+
+/// Averages `f` of the sampled cosine over equally spaced random numbers.
+fn mean_of_samples<F: Fn(Float) -> Float>(s: &TumbleSampler, f: F) -> Float {
+    let n = 100_000;
+    (0..n)
+        .map(|i| f(s.cos_angle((i as Float + 0.5) / n as Float)))
+        .sum::<Float>()
+        / n as Float
+}
+
+#[test]
+fn concentration() {
+    for &c in &[-0.9, -0.33, 0., 0.01, 0.33, 0.9, 0.999] {
+        let angle = TumbleAngle::VonMisesFisher { mean_cosine: c };
+        let kappa = angle.concentration();
+
+        assert!(equal_floats_eps(langevin(kappa), c, 1e-10), "{}", c);
+    }
+}
+
+#[test]
+fn sampled_mean_cosine() {
+    for &c in &[-0.5, 0., 0.33, 0.8] {
+        let t = Tumbling {
+            rate: 1.,
+            angle: TumbleAngle::VonMisesFisher { mean_cosine: c },
+        };
+        let s = t.sampler();
+
+        assert!((mean_of_samples(&s, |x| x) - c).abs() < 1e-4, "{}", c);
+        assert!(mean_of_samples(&s, |x| x.abs()) <= 1.);
+    }
+
+    let s = Tumbling {
+        rate: 1.,
+        angle: TumbleAngle::Uniform,
+    }
+    .sampler();
+    assert!(mean_of_samples(&s, |x| x).abs() < 1e-10);
+    // second moment of a uniformly distributed cosine
+    assert!((mean_of_samples(&s, |x| x * x) - 1. / 3.).abs() < 1e-6);
+}
+
+#[test]
+fn effective_rotational_diffusion() {
+    let t = Tumbling {
+        rate: 2.,
+        angle: TumbleAngle::VonMisesFisher { mean_cosine: 0.25 },
+    };
+
+    assert_eq!(
+        t.effective_rotational_diffusion(Dimensionality::ThreeD),
+        0.75
+    );
+    assert_eq!(t.effective_rotational_diffusion(Dimensionality::TwoD), 1.5);
+    assert_eq!(
+        Tumbling::default().effective_rotational_diffusion(Dimensionality::ThreeD),
+        0.
+    );
+    assert!(!Tumbling::default().is_enabled());
+    assert!((t.probability(1e-3) - 2e-3).abs() < 1e-5);
+    assert_eq!(t.biased_probability(1e-3, 0.), t.probability(1e-3));
+    assert_eq!(t.biased_probability(1e-3, 1.5), 0.);
+}
+
+#[test]
+#[should_panic]
+fn invalid_mean_cosine() {
+    TumbleAngle::VonMisesFisher { mean_cosine: 1. }.concentration();
+}
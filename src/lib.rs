@@ -11,6 +11,7 @@
 extern crate error_chain;
 use serde_derive::{Deserialize, Serialize};
 
+pub mod chemical_field;
 pub mod consts;
 pub mod distribution;
 pub mod flowfield;
@@ -20,6 +21,9 @@ pub mod mesh;
 pub mod output;
 pub mod particle;
 pub mod polarization;
+pub mod polydispersity;
+pub mod schedule;
+pub mod steric;
 mod test_helper;
 pub mod vector;
 
@@ -52,3 +56,24 @@ pub struct GridSize {
     pub phi: usize,
     pub theta: usize,
 }
+
+/// Dimensionality of the system.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Dimensionality {
+    /// Particles move and rotate freely in a 3D fluid.
+    #[default]
+    ThreeD,
+    /// Particles are confined to a thin film in the plane `z = 0`, which is
+    /// embedded in a 3D fluid. The in-plane flow is not incompressible.
+    QuasiTwoD,
+    /// Particles and fluid are two dimensional.
+    TwoD,
+}
+
+impl Dimensionality {
+    /// Returns true, if particles move in the plane `z = 0` and are oriented
+    /// in it.
+    pub fn is_planar(self) -> bool {
+        self != Dimensionality::ThreeD
+    }
+}
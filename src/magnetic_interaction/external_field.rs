@@ -0,0 +1,244 @@
+//! External magnetic field, that varies in space, e.g. the field of a
+//! quadrupole for magnetic focusing.
+//!
+//! The field is given in units of the reorientation rate of a particle with
+//! unit magnetic moment like the uniform field of `magnetic_reorientation`.
+//! Particles rotate towards the field and are pulled along its gradient by
+//! the force `grad(m . B)`.
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./external_field_test.rs"]
+mod external_field_test;
+
+use crate::mesh::grid_width::GridWidth;
+use crate::mesh::interpolate::interpolate_vector_field;
+use crate::particle::Position;
+use crate::vector::{Matrix3, VectorD};
+use crate::BoxSize;
+use crate::Float;
+use ndarray::{Array, Axis, Ix4, Ix5};
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+/// Spatial dependence of the external magnetic field `B(x)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ExternalField {
+    /// Field `B_i = field_i + gradient_ij (x_j - center_j)`, which is only
+    /// physical for a symmetric and traceless gradient.
+    Linear {
+        field: [Float; 3],
+        gradient: [[Float; 3]; 3],
+        #[serde(default)]
+        center: [Float; 3],
+    },
+    /// Field of a pair of anti-Helmholtz coils, which vanishes at `center`
+    /// and grows with `gradient` along `axis` and with `-gradient / 2`
+    /// perpendicular to it. The axis points in y direction by default like
+    /// the uniform field.
+    Quadrupole {
+        gradient: Float,
+        center: [Float; 3],
+        #[serde(default = "default_quadrupole_axis")]
+        axis: [Float; 3],
+    },
+    /// Field on a grid, which covers the simulation box periodically, read
+    /// from a text file with whitespace separated values. The first three
+    /// values are the number of cells in x, y and z direction followed by
+    /// the three components of the field at the center of every cell, where
+    /// the z index changes fastest.
+    File { path: String },
+}
+
+fn default_quadrupole_axis() -> [Float; 3] {
+    [0., 1., 0.]
+}
+
+impl ExternalField {
+    /// Returns true, if the axis of a quadrupole does not vanish.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            ExternalField::Quadrupole { axis, .. } => axis.iter().any(|&a| a != 0.),
+            _ => true,
+        }
+    }
+
+    /// Returns the field map, where a file is read and interpolated on the
+    /// box `box_size`.
+    pub fn load(&self, box_size: BoxSize) -> io::Result<FieldMap> {
+        match self {
+            ExternalField::Linear {
+                field,
+                gradient,
+                center,
+            } => {
+                // transpose to the vector gradient `d_i B_j`
+                let mut g = Matrix3::default();
+                for (i, row) in gradient.iter().enumerate() {
+                    for (j, &v) in row.iter().enumerate() {
+                        g[j][i] = v;
+                    }
+                }
+
+                Ok(FieldMap::Linear {
+                    field: (*field).into(),
+                    gradient: g,
+                    center: (*center).into(),
+                })
+            }
+            ExternalField::Quadrupole {
+                gradient,
+                center,
+                axis,
+            } => {
+                let a: VectorD = (*axis).into();
+                let a = a * (1. / a.dot(&a).sqrt());
+
+                // gradient * (3 a a - 1) / 2
+                let mut g = Matrix3::default();
+                for (i, row) in g.iter_mut().enumerate() {
+                    for (j, e) in row.iter_mut().enumerate() {
+                        let delta = if i == j { 1. } else { 0. };
+                        *e = gradient * (3. * a[i] * a[j] - delta) / 2.;
+                    }
+                }
+
+                Ok(FieldMap::Linear {
+                    field: VectorD::zero(),
+                    gradient: g,
+                    center: (*center).into(),
+                })
+            }
+            ExternalField::File { path } => read_grid(path, box_size),
+        }
+    }
+}
+
+/// External field, that can be evaluated at the positions of the particles.
+#[derive(Debug, Clone)]
+pub enum FieldMap {
+    /// Field `B = field + gradient^T . (x - center)` with the constant
+    /// vector gradient `gradient[i][j] = d_i B_j`
+    Linear {
+        field: VectorD,
+        gradient: Matrix3,
+        center: VectorD,
+    },
+    /// Field and its vector gradient at the centers of the grid cells, which
+    /// are interpolated trilinearly.
+    Grid {
+        field: Array<Float, Ix4>,
+        gradient: Array<Float, Ix5>,
+        grid_width: GridWidth,
+    },
+}
+
+impl FieldMap {
+    /// Returns the field and its vector gradient `grad_b[i][j] = d_i B_j` at
+    /// `position`.
+    pub fn field_at(&self, position: &Position) -> (VectorD, Matrix3) {
+        match self {
+            FieldMap::Linear {
+                field,
+                gradient,
+                center,
+            } => {
+                let mut r: VectorD = position.to_vector().to();
+                r -= *center;
+                let mut b: [Float; 3] = (*field).into();
+                for (i, row) in gradient.iter().enumerate() {
+                    for (b, g) in b.iter_mut().zip(row) {
+                        *b += g * r[i];
+                    }
+                }
+                (b.into(), *gradient)
+            }
+            FieldMap::Grid {
+                field,
+                gradient,
+                grid_width,
+            } => {
+                let b = interpolate_vector_field(position, &field.view(), grid_width);
+                let mut g = Matrix3::default();
+                for (i, row) in g.iter_mut().enumerate() {
+                    let d = interpolate_vector_field(
+                        position,
+                        &gradient.index_axis(Axis(0), i),
+                        grid_width,
+                    );
+                    *row = d.into();
+                }
+                (b, g)
+            }
+        }
+    }
+}
+
+/// Reads the field on a grid from the file `path` and calculates its vector
+/// gradient by central differences.
+fn read_grid(path: &str, box_size: BoxSize) -> io::Result<FieldMap> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let content = fs::read_to_string(path)?;
+    let mut values = content.split_whitespace();
+
+    let mut size = [0; 3];
+    for s in size.iter_mut() {
+        let v = values.next().unwrap_or_default();
+        *s = match v.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(invalid(format!("'{}' is not a positive grid size.", v))),
+        };
+    }
+
+    let field = values
+        .map(|v| {
+            v.parse::<Float>()
+                .map_err(|_| invalid(format!("'{}' is not a number.", v)))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let n = size[0] * size[1] * size[2];
+    if field.len() != 3 * n {
+        return Err(invalid(format!(
+            "'{}' contains {} values, but a grid of {:?} cells needs {}.",
+            path,
+            field.len(),
+            size,
+            3 * n
+        )));
+    }
+
+    // components are stored for every cell
+    let field = Array::from_shape_fn((3, size[0], size[1], size[2]), |(c, x, y, z)| {
+        field[3 * ((x * size[1] + y) * size[2] + z) + c]
+    });
+
+    let grid_width = GridWidth {
+        x: box_size.x / size[0] as Float,
+        y: box_size.y / size[1] as Float,
+        z: box_size.z / size[2] as Float,
+        phi: 0.,
+        theta: 0.,
+    };
+    let widths = [grid_width.x, grid_width.y, grid_width.z];
+
+    // central differences with periodic boundaries
+    let mut gradient = Array::zeros((3, 3, size[0], size[1], size[2]));
+    for ((i, j, x, y, z), g) in gradient.indexed_iter_mut() {
+        let mut next = [x, y, z];
+        let mut prev = [x, y, z];
+        next[i] = (next[i] + 1) % size[i];
+        prev[i] = (prev[i] + size[i] - 1) % size[i];
+        *g = (field[[j, next[0], next[1], next[2]]] - field[[j, prev[0], prev[1], prev[2]]])
+            / (2. * widths[i]);
+    }
+
+    Ok(FieldMap::Grid {
+        field,
+        gradient,
+        grid_width,
+    })
+}
@@ -0,0 +1,106 @@
+use super::*;
+use std::env;
+#[cfg(feature = "single")]
+use std::f32::consts::PI;
+#[cfg(not(feature = "single"))]
+use std::f64::consts::PI;
+
+const BS: BoxSize = BoxSize {
+    x: 4.,
+    y: 4.,
+    z: 2.,
+};
+
+fn position(x: Float, y: Float, z: Float) -> Position {
+    Position { x, y, z }
+}
+
+fn assert_matrix_eq(a: &Matrix3, b: &Matrix3, eps: Float) {
+    for (ra, rb) in a.iter().zip(b) {
+        for (ea, eb) in ra.iter().zip(rb) {
+            assert!((ea - eb).abs() < eps, "{:?} != {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn linear_field() {
+    let map = ExternalField::Linear {
+        field: [0., 1., 0.],
+        gradient: [[0., 0.5, 0.], [0.5, 0., 0.], [0., 0., 0.]],
+        center: [1., 1., 1.],
+    }
+    .load(BS)
+    .unwrap();
+
+    let (b, grad_b) = map.field_at(&position(3., 1., 0.));
+    assert_eq!(<[Float; 3]>::from(b), [0., 2., 0.]);
+    assert_eq!(grad_b, [[0., 0.5, 0.], [0.5, 0., 0.], [0., 0., 0.]]);
+}
+
+#[test]
+fn quadrupole_field() {
+    let q = ExternalField::Quadrupole {
+        gradient: 2.,
+        center: [2., 2., 1.],
+        axis: [0., 0., 3.],
+    };
+    assert!(q.is_valid());
+    let map = q.load(BS).unwrap();
+
+    let (b, grad_b) = map.field_at(&position(3., 2.5, 1.5));
+    assert_eq!(<[Float; 3]>::from(b), [-1., -0.5, 1.]);
+    assert_matrix_eq(
+        &grad_b,
+        &[[-1., 0., 0.], [0., -1., 0.], [0., 0., 2.]],
+        1e-12,
+    );
+
+    // the field is free of divergence
+    assert!((grad_b[0][0] + grad_b[1][1] + grad_b[2][2]).abs() < 1e-12);
+
+    assert!(!ExternalField::Quadrupole {
+        gradient: 2.,
+        center: [2., 2., 1.],
+        axis: [0., 0., 0.],
+    }
+    .is_valid());
+}
+
+#[test]
+fn field_from_file() {
+    // B_x = sin(pi x / 2) on 8 x 1 x 1 cells
+    let n = 8;
+    let values: Vec<String> = (0..n)
+        .map(|i| {
+            let x = (i as Float + 0.5) * BS.x / n as Float;
+            format!("{} 0 0", (PI * x / 2.).sin())
+        })
+        .collect();
+    let path = env::temp_dir().join("external_field_test_grid.txt");
+    fs::write(&path, format!("{} 1 1\n{}\n", n, values.join("\n"))).unwrap();
+    let file = ExternalField::File {
+        path: path.to_str().unwrap().to_string(),
+    };
+
+    let map = file.load(BS).unwrap();
+    let (b, grad_b) = map.field_at(&position(0.25, 1., 1.));
+    assert!((b[0] - (PI / 8.).sin()).abs() < 1e-12, "{:?}", b);
+    assert_eq!((b[1], b[2]), (0., 0.));
+
+    // central difference of the sine with the grid width h
+    let h = BS.x / n as Float;
+    let expected = (PI * h / 2.).sin() / h * (PI / 8.).cos();
+    assert!((grad_b[0][0] - expected).abs() < 1e-12, "{:?}", grad_b);
+    assert_eq!(grad_b[1], [0.; 3]);
+    assert_eq!(grad_b[2], [0.; 3]);
+
+    fs::write(&path, "2 1 1\n1 0 0\n").unwrap();
+    assert!(file.load(BS).is_err());
+
+    fs::write(&path, "0 1 1\n").unwrap();
+    assert!(file.load(BS).is_err());
+
+    fs::remove_file(&path).unwrap();
+    assert!(file.load(BS).is_err());
+}
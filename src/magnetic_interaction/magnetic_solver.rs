@@ -3,8 +3,11 @@
 #[path = "./magnetic_solver_test.rs"]
 mod magnetic_solver_test;
 
-use crate::distribution::Distribution;
-use crate::mesh::fft_helper::{get_k_mesh, get_norm_k_mesh};
+use crate::distribution::{Distribution, OrientationRepresentation};
+use crate::mesh::fft_helper::{
+    get_filter_mesh, get_k_mesh, get_norm_k_mesh, get_sheared_k_mesh, get_sheared_norm_k_mesh,
+    SpectralFilter,
+};
 use crate::mesh::grid_width::GridWidth;
 use crate::polarization::director::DirectorField;
 use crate::Float;
@@ -14,6 +17,7 @@ use fftw3::fft::FFTPlan;
 use ndarray::{Array, ArrayView, Axis, Ix3, Ix4, Ix5, Zip};
 use ndarray_parallel::prelude::*;
 use num_complex::Complex;
+use std::slice;
 use std::sync::Arc;
 
 pub type MagneticField = Array<Complex<Float>, Ix4>;
@@ -25,11 +29,32 @@ pub struct MagneticSolver {
     k_norm_mesh: Array<Complex<Float>, Ix4>,
     director_field: DirectorField,
     gradient_meanb: Array<Complex<Float>, Ix5>,
+    grid_size: GridSize,
+    box_size: BoxSize,
+    /// parameter `alpha` of the splitting into long-range and short-range
+    /// part, see `set_splitting`
+    splitting: Option<Float>,
+    /// factors of the spectral filter of every mode, see `set_filter`
+    filter: Array<Float, Ix3>,
     // magnetic_field: Array<Complex<Float>, Ix4>,
 }
 
 impl MagneticSolver {
     pub fn new(grid_size: GridSize, box_size: BoxSize) -> MagneticSolver {
+        MagneticSolver::with_orientation(
+            grid_size,
+            box_size,
+            OrientationRepresentation::UniformGrid,
+        )
+    }
+
+    /// Returns a solver for distributions, that represent the orientations as
+    /// given by `orientation`.
+    pub fn with_orientation(
+        grid_size: GridSize,
+        box_size: BoxSize,
+        orientation: OrientationRepresentation,
+    ) -> MagneticSolver {
         let grid_width = GridWidth::new(grid_size, box_size);
 
         let mesh = get_k_mesh(grid_size, box_size);
@@ -56,11 +81,38 @@ impl MagneticSolver {
             k_norm_mesh: norm_mesh,
             fft_plan_forward: Arc::new(plan_forward),
             fft_plan_backward: Arc::new(plan_backward),
-            director_field: DirectorField::new(grid_size, grid_width),
+            director_field: DirectorField::with_orientation(grid_size, grid_width, orientation),
             gradient_meanb: Array::default([3, 3, grid_size.x, grid_size.y, grid_size.z]),
+            grid_size,
+            box_size,
+            splitting: None,
+            filter: get_filter_mesh(grid_size, SpectralFilter::None),
         }
     }
 
+    /// Sets the strain of the box for Lees-Edwards boundary conditions. See
+    /// `SpectralSolver::set_strain`.
+    pub fn set_strain(&mut self, strain: Float) {
+        self.k_mesh = get_sheared_k_mesh(self.grid_size, self.box_size, strain);
+        self.k_norm_mesh = get_sheared_norm_k_mesh(self.grid_size, self.box_size, strain);
+    }
+
+    /// Restricts the mean magnetic field to the long-range part of the
+    /// dipole-dipole interaction with the splitting parameter `alpha`. The
+    /// dipolar part of the field is screened by `exp(-k^2 / 4 alpha^2)`,
+    /// while the complementary short-range part has to be calculated by
+    /// `NearField`.
+    pub fn set_splitting(&mut self, alpha: Float) {
+        assert!(alpha > 0., "Splitting parameter must be positive.");
+        self.splitting = Some(alpha);
+    }
+
+    /// Sets the spectral filter `filter`, which is applied to the magnetic
+    /// field and hence to its gradient.
+    pub fn set_filter(&mut self, filter: SpectralFilter) {
+        self.filter = get_filter_mesh(self.grid_size, filter);
+    }
+
     /// Calculates the fourier transform of the mean magnetic field.
     /// CAUTION: In order to prevent reallocation the magnetic field is saved
     /// in the DirectorField. Which is complete and utter non-sense. But
@@ -68,11 +120,9 @@ impl MagneticSolver {
     /// deal with that.
     ///
     /// Set k=0 mode to zero, since an constant offset field is unphysical.
-    fn fft_mean_magnetic_field(&mut self, dist: &Distribution) {
-        // let dist_sh = dist.dim();
-
+    fn fft_mean_magnetic_field(&mut self, dists: &[Distribution], moments: &[Float]) {
         // calculate FFT of averaged stress field
-        self.director_field.from_distribution(dist);
+        self.director_field.from_distributions(dists, moments);
         let mut p = self.director_field.field.view_mut();
 
         let sh = p.dim();
@@ -85,12 +135,21 @@ impl MagneticSolver {
         // FFT normalization
         let norm = (sh.1 * sh.2 * sh.3) as Float;
 
+        // Gaussian screening of the dipolar part with the splitting
+        let screening = self.splitting.map(|a| -0.25 / (a * a));
+
         Zip::from(p.lanes_mut(Axis(0)))
             .and(self.k_norm_mesh.lanes(Axis(0)))
-            .par_apply(|mut p, k| {
-                let kdotp = k.dot(&p);
-                let mag = (&p - &(&k * kdotp)) / norm;
-                p.assign(&mag);
+            .and(self.k_mesh.lanes(Axis(0)))
+            .and(&self.filter)
+            .par_apply(|mut p, k, kk, &f| {
+                let mut kdotp = k.dot(&p);
+                if let Some(s) = screening {
+                    kdotp *= (s * kk.iter().map(|k| k.norm_sqr()).sum::<Float>()).exp();
+                }
+                for (p, k) in p.iter_mut().zip(k.iter()) {
+                    *p = (*p - k * kdotp) * (f / norm);
+                }
             });
     }
 
@@ -99,12 +158,8 @@ impl MagneticSolver {
         let sh = self.director_field.field.dim();
         let n = sh.1 * sh.2 * sh.3;
 
-        // Construct an outer product by making use of broadcasting
+        // Construct the outer product ab_ij = a_i b_j of
         // a = [a1, a2, a3], b = [b1, b2, b3]
-        // ab =
-        // [[a1, a1, a1],    [[b1, b2, b3]
-        //  [a2, a2, a2], .*  [b1, b2, b3]
-        //  [a3, a3, a3]]     [b1, b2, b3]]
 
         let k = self.k_mesh.view();
         let k = k.into_shape([3, n]).unwrap();
@@ -120,10 +175,9 @@ impl MagneticSolver {
             .and(k.axis_iter(Axis(1)))
             .and(b.axis_iter(Axis(1)))
             .par_apply(|mut g, k, b| {
-                let k = k.broadcast([3, 3]).unwrap();
-                let k = k.t();
-                let e = (&k * &b) * Complex::new(0., 1.);
-                g.assign(&e)
+                for ((i, j), g) in g.indexed_iter_mut() {
+                    *g = k[i] * b[j] * Complex::new(0., 1.);
+                }
             });
 
         let mut g = g.into_shape([9, sh.1, sh.2, sh.3]).unwrap();
@@ -138,14 +192,29 @@ impl MagneticSolver {
     /// field and the (flattened) vector gradient field of it.
     pub fn mean_magnetic_field(
         &mut self,
-        d: &Distribution
+        d: &Distribution,
     ) -> (
         ArrayView<Complex<Float>, Ix4>,
         ArrayView<Complex<Float>, Ix5>,
+    ) {
+        self.mean_magnetic_field_of_species(slice::from_ref(d), &[1.])
+    }
+
+    /// Same as `mean_magnetic_field`, but for a mixture of particle species
+    /// with the distributions `dists`. The magnetic moment of every species
+    /// relative to the moment, which enters the dipole-dipole coupling, is
+    /// given by `moments`.
+    pub fn mean_magnetic_field_of_species(
+        &mut self,
+        dists: &[Distribution],
+        moments: &[Float],
+    ) -> (
+        ArrayView<'_, Complex<Float>, Ix4>,
+        ArrayView<'_, Complex<Float>, Ix5>,
     ) {
         // TODO refactor to make it more explicit and readable
         // calculate FFT of magnetic field and store result in self.director_field.field
-        self.fft_mean_magnetic_field(d);
+        self.fft_mean_magnetic_field(dists, moments);
         // use stored value of FFT of magnetic field to calculate vector gradient and
         // store it in self.gradient_meanb
         self.update_gradient();
@@ -154,3 +154,86 @@ fn test_magnetic_vector_gradient() {
     assert_eq!(solver.gradient_meanb.slice(s![0, 1, 0, 0, ..]), zero);
     assert_eq!(solver.gradient_meanb.slice(s![1, 1, 0, 0, ..]), zero);
 }
+
+/// Particles in a slab perpendicular to x, that are oriented along x, form a
+/// longitudinal magnetisation. Its dipolar field cancels the magnetisation
+/// apart from the mean, unless the dipolar part is screened by the splitting.
+#[test]
+fn test_splitting() {
+    let bs = BoxSize {
+        x: 4.,
+        y: 1.,
+        z: 1.,
+    };
+    let gs = GridSize {
+        x: 4,
+        y: 1,
+        z: 1,
+        phi: 4,
+        theta: 2,
+    };
+
+    let p: Vec<_> = (0..10)
+        .map(|i| Particle::new(0.05 + 0.09 * i as Float, 0.5, 0.5, 0., PI / 2., &bs))
+        .collect();
+    let mut d = Distribution::new(gs, bs);
+    d.sample_from(&p);
+
+    let mut solver = MagneticSolver::new(gs, bs);
+    let b = solver.mean_magnetic_field(&d).0.map(|v| v.re);
+    for v in b.slice(s![0, .., 0, 0]).iter() {
+        assert!((v - b[[0, 0, 0, 0]]).abs() < 1e-12);
+    }
+
+    // long-range part vanishes for all wave vectors except zero
+    solver.set_splitting(1e-3);
+    let b_long = solver.mean_magnetic_field(&d).0.map(|v| v.re);
+    assert!(b_long[[0, 0, 0, 0]] > b_long[[0, 1, 0, 0]] + 0.1);
+    assert!((b_long.slice(s![0, .., 0, 0]).sum() - b.slice(s![0, .., 0, 0]).sum()).abs() < 1e-12);
+
+    // weak splitting does not change the field
+    solver.set_splitting(1e6);
+    let b_long = solver.mean_magnetic_field(&d).0.map(|v| v.re);
+    for (a, b) in b_long.iter().zip(b.iter()) {
+        assert!((a - b).abs() < 1e-9);
+    }
+}
+
+/// Particles in a slab perpendicular to x, that are oriented along y, form a
+/// transverse magnetisation, which is not changed by the dipolar field. The
+/// 2/3 rule removes the mode `m = 2` of the four cells.
+#[test]
+fn test_filter() {
+    let bs = BoxSize {
+        x: 4.,
+        y: 1.,
+        z: 1.,
+    };
+    let gs = GridSize {
+        x: 4,
+        y: 1,
+        z: 1,
+        phi: 4,
+        theta: 2,
+    };
+
+    let p: Vec<_> = (0..10)
+        .map(|i| Particle::new(0.05 + 0.09 * i as Float, 0.5, 0.5, PI / 2., PI / 2., &bs))
+        .collect();
+    let mut d = Distribution::new(gs, bs);
+    d.sample_from(&p);
+
+    let mut solver = MagneticSolver::new(gs, bs);
+    let b = solver.mean_magnetic_field(&d).0.map(|v| v.re);
+    let b = b.slice(s![1, .., 0, 0]).to_vec();
+    let sign = [1., -1., 1., -1.];
+    let nyquist = b.iter().zip(&sign).map(|(b, s)| b * s).sum::<Float>() / 4.;
+    assert!(nyquist.abs() > 0.1);
+
+    solver.set_filter(SpectralFilter::TwoThirds);
+    let filtered = solver.mean_magnetic_field(&d).0.map(|v| v.re);
+    for (i, (f, b)) in filtered.slice(s![1, .., 0, 0]).iter().zip(&b).enumerate() {
+        let expect = b - sign[i] * nyquist;
+        assert!((f - expect).abs() < 1e-12, "{} != {}", f, expect);
+    }
+}
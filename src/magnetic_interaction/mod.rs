@@ -1,17 +1,16 @@
+pub mod external_field;
 pub mod magnetic_solver;
+pub mod near_field;
 
 use crate::particle::OrientationVector;
-use crate::vector::Vector;
-use crate::Float;
-use ndarray::{Array, ArrayView, Ix2};
+use crate::vector::{mat_vec, Matrix3, Vector};
 
 pub struct Force();
 
 /// Returns force on unit magnetic moment with orientation `o` in a given field
-/// gradient `grad_b`.
+/// gradient `grad_b`, i.e. `grad(o . B)` with `grad_b[i][j] = d_i B_j`. It
+/// applies to the mean dipole-dipole field as well as to an external field.
 ///
-pub fn mean_force(grad_b: ArrayView<Float, Ix2>, o: &OrientationVector) -> Vector<Force> {
-    let o = Array::from_vec(o.v.to_vec());
-    let f = grad_b.dot(&o);
-    unsafe { [*f.uget(0), *f.uget(1), *f.uget(2)] }.into()
+pub fn mean_force(grad_b: &Matrix3, o: &OrientationVector) -> Vector<Force> {
+    mat_vec(grad_b, o).to()
 }
@@ -0,0 +1,187 @@
+//! Short-range part of the magnetic dipole-dipole interaction, which is split
+//! from the long-range part like in the Ewald summation of dipoles.
+//!
+//! The dipolar field is split with the parameter `alpha` into a long-range
+//! part, which is screened by `exp(-k^2 / 4 alpha^2)` in Fourier space and
+//! calculated from the mean magnetic field on the mesh (see
+//! `MagneticSolver::set_splitting`), and the complementary short-range part.
+//! The short-range part decays with `exp(-alpha^2 r^2)` and is summed
+//! explicitly over all neighbours within the cutoff.
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./near_field_test.rs"]
+mod near_field_test;
+
+use crate::particle::Particle;
+use crate::steric::cell_list::{nearest_image_distance, CellList};
+use crate::vector::{Matrix3, VectorD};
+use crate::BoxSize;
+use crate::Float;
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+#[cfg(feature = "single")]
+use std::f32::consts::PI;
+#[cfg(not(feature = "single"))]
+use std::f64::consts::PI;
+
+/// Parameters of the splitting into long-range and short-range part.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NearFieldParameters {
+    /// Splitting parameter `alpha`. The mesh only resolves the long-range
+    /// part, if `1 / alpha` is larger than the grid width.
+    pub splitting: Float,
+    /// Neighbours beyond the cutoff are neglected in the short-range part.
+    /// A cutoff of `3 / alpha` is usually sufficient.
+    pub cutoff: Float,
+}
+
+impl NearFieldParameters {
+    /// Returns the radial functions `(B, C, D)` of the screened dipolar field
+    /// at distance `r`. The field of a dipole `m` is
+    /// `(C r (r . m) - B m) / 4 pi`, and `dB/dr = -r C`, `dC/dr = -r D`.
+    fn radial_functions(&self, r: Float) -> (Float, Float, Float) {
+        let a = self.splitting;
+        let ar2 = (a * r).powi(2);
+        let erfc = erfc(a * r);
+        let e = 2. * a * r / PI.sqrt() * (-ar2).exp();
+
+        let r2 = r * r;
+        let b = (erfc + e) / (r2 * r);
+        let c = (3. * erfc + e * (3. + 2. * ar2)) / (r2 * r2 * r);
+        let d = (15. * erfc + e * (15. + 10. * ar2 + 4. * ar2 * ar2)) / (r2 * r2 * r2 * r);
+
+        (b, c, d)
+    }
+
+    /// Returns the short-range field and its vector gradient
+    /// `grad_b[i][j] = d_i b_j` at the distance vector `r` from a dipole
+    /// `m`.
+    pub fn field(&self, r: &VectorD, m: &VectorD) -> (VectorD, Matrix3) {
+        let (b, c, d) = self.radial_functions(r.dot(r).sqrt());
+        let rm = r.dot(m);
+        let norm = 1. / (4. * PI);
+
+        let field = (*r * (c * rm) - *m * b) * norm;
+
+        let mut grad = [[0.; 3]; 3];
+        for (i, row) in grad.iter_mut().enumerate() {
+            for (j, g) in row.iter_mut().enumerate() {
+                let delta = if i == j { rm } else { 0. };
+                *g = (-d * r[i] * r[j] * rm + c * (delta + r[j] * m[i] + r[i] * m[j])) * norm;
+            }
+        }
+
+        (field, grad)
+    }
+}
+
+/// Calculates the short-range magnetic field and its gradient at the position
+/// of every particle.
+pub struct NearField {
+    parameters: NearFieldParameters,
+    box_size: BoxSize,
+    /// Particles interact with the periodic images in z direction, which is
+    /// not the case for walls.
+    periodic_z: bool,
+    cell_list: CellList,
+    field: Vec<VectorD>,
+    gradient: Vec<Matrix3>,
+}
+
+impl NearField {
+    pub fn new(parameters: NearFieldParameters, box_size: BoxSize, periodic_z: bool) -> Self {
+        NearField {
+            parameters,
+            box_size,
+            periodic_z,
+            cell_list: CellList::new(box_size, parameters.cutoff),
+            field: Vec::new(),
+            gradient: Vec::new(),
+        }
+    }
+
+    /// Calculates the short-range field of the dipoles of all `particles` with
+    /// the magnetic moment `moments` of every particle. The moments have to be
+    /// normalised like the magnetisation on the mesh.
+    pub fn update(&mut self, particles: &[Particle], moments: &[Float]) {
+        assert_eq!(
+            particles.len(),
+            moments.len(),
+            "Every particle needs a magnetic moment."
+        );
+        self.cell_list.update(particles);
+        self.field.resize(particles.len(), VectorD::zero());
+        self.gradient.resize(particles.len(), [[0.; 3]; 3]);
+
+        let param = &self.parameters;
+        let (bs, periodic_z) = (&self.box_size, self.periodic_z);
+        let cell_list = &self.cell_list;
+
+        self.field
+            .par_iter_mut()
+            .zip(self.gradient.par_iter_mut())
+            .zip(particles.par_iter())
+            .enumerate()
+            .for_each(|(i, ((b, grad_b), p))| {
+                *b = VectorD::zero();
+                *grad_b = [[0.; 3]; 3];
+
+                cell_list.for_each_neighbour(&p.position, |j| {
+                    if i == j || moments[j] == 0. {
+                        return;
+                    }
+                    let q = &particles[j];
+                    let r = nearest_image_distance(&p.position, &q.position, bs, periodic_z);
+                    if r.dot(&r) >= param.cutoff * param.cutoff {
+                        return;
+                    }
+
+                    let m: VectorD = q.orientation.to_vector().to();
+                    let (f, g) = param.field(&r, &(m * moments[j]));
+                    *b += f;
+                    for (row, grow) in grad_b.iter_mut().zip(g.iter()) {
+                        for (x, y) in row.iter_mut().zip(grow.iter()) {
+                            *x += y;
+                        }
+                    }
+                });
+            });
+    }
+
+    /// Returns the short-range field at every particle in the order of the
+    /// particles given to `update`.
+    pub fn get_field(&self) -> &[VectorD] {
+        &self.field
+    }
+
+    /// Returns the vector gradient of the short-range field at every particle.
+    pub fn get_gradient(&self) -> &[Matrix3] {
+        &self.gradient
+    }
+}
+
+/// Complementary error function with a fractional error below `1.2e-7`, see
+/// Numerical Recipes, chapter 6.2.
+fn erfc(x: Float) -> Float {
+    let z = x.abs();
+    let t = 1. / (1. + 0.5 * z);
+    let ans = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+
+    if x >= 0. {
+        ans
+    } else {
+        2. - ans
+    }
+}
@@ -0,0 +1,112 @@
+use super::*;
+
+const BS: BoxSize = BoxSize {
+    x: 10.,
+    y: 10.,
+    z: 10.,
+};
+
+fn parameters(splitting: Float) -> NearFieldParameters {
+    NearFieldParameters {
+        splitting,
+        cutoff: 3.,
+    }
+}
+
+#[test]
+fn complementary_error_function() {
+    for &(x, expect) in &[
+        (0., 1.),
+        (0.5, 0.4795001221869535),
+        (1., 0.15729920705028513),
+        (2., 0.004677734981047266),
+        (-1., 1.8427007929497148),
+    ] {
+        assert!((erfc(x) - expect).abs() < 2e-7, "erfc({})", x);
+    }
+}
+
+/// Without screening, the short-range part is the full dipolar field.
+#[test]
+fn unscreened_dipole_field() {
+    let p = parameters(1e-8);
+    let m: VectorD = [1., 0., 0.].into();
+
+    let (b, _) = p.field(&[2., 0., 0.].into(), &m);
+    let expect = [2. / (4. * PI * 8.), 0., 0.];
+    for (b, e) in b.iter().zip(expect.iter()) {
+        assert!((b - e).abs() < 1e-8, "{:?}", b);
+    }
+
+    let (b, _) = p.field(&[0., 0., 2.].into(), &m);
+    let expect = [-1. / (4. * PI * 8.), 0., 0.];
+    for (b, e) in b.iter().zip(expect.iter()) {
+        assert!((b - e).abs() < 1e-8, "{:?}", b);
+    }
+
+    // the screened field decays faster
+    let (b, _) = parameters(1.).field(&[2., 0., 0.].into(), &m);
+    assert!(b[0] > 0. && b[0] < 0.5 * 2. / (4. * PI * 8.));
+}
+
+/// Compares the vector gradient with finite differences of the field.
+#[test]
+fn gradient_of_field() {
+    let p = parameters(1.3);
+    let r: VectorD = [0.3, -0.2, 0.5].into();
+    let m: VectorD = [0.2, 0.7, -0.4].into();
+    let (_, grad) = p.field(&r, &m);
+
+    let h = 1e-6;
+    for i in 0..3 {
+        let mut dr = [0.; 3];
+        dr[i] = h;
+        let dr: VectorD = dr.into();
+        let (bp, _) = p.field(&(r + dr), &m);
+        let (bm, _) = p.field(&(r - dr), &m);
+        for j in 0..3 {
+            let fd = (bp[j] - bm[j]) / (2. * h);
+            assert!(
+                (grad[i][j] - fd).abs() < 1e-5,
+                "{} {}: {} != {}",
+                i,
+                j,
+                grad[i][j],
+                fd
+            );
+        }
+    }
+}
+
+#[test]
+fn field_of_neighbours() {
+    let particles = vec![
+        Particle::new(0.2, 5., 5., 0., 0., &BS),
+        Particle::new(9.7, 5.2, 5., 0., 1., &BS),
+        Particle::new(5., 5., 5., 0., 0., &BS),
+        Particle::new(0.2, 5.5, 5., 0., 0., &BS),
+    ];
+    let moments = [1., 2., 3., 0.];
+    let p = parameters(1.);
+
+    let mut nf = NearField::new(p, BS, true);
+    nf.update(&particles, &moments);
+
+    let r: VectorD = [0.5, -0.2, 0.].into();
+    let m: VectorD = particles[1].orientation.to_vector().to();
+    let (b, grad) = p.field(&r, &(m * 2.));
+
+    // the distance across the boundary differs by rounding
+    let diff = nf.get_field()[0] - b;
+    assert!(diff.dot(&diff).sqrt() < 1e-12);
+    for (row, grow) in nf.get_gradient()[0].iter().zip(grad.iter()) {
+        for (x, y) in row.iter().zip(grow.iter()) {
+            assert!((x - y).abs() < 1e-12);
+        }
+    }
+
+    // far away from all other particles
+    assert_eq!(nf.get_field()[2].v, [0.; 3]);
+    // the last particle does not carry a moment, but feels the field
+    assert!(nf.get_field()[3].dot(&nf.get_field()[3]) > 0.);
+}
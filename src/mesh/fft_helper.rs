@@ -7,8 +7,11 @@ use super::mesh3d;
 use crate::consts::TWOPI;
 use crate::Float;
 use crate::{BoxSize, GridSize};
-use ndarray::{s, Array, ArrayView, Axis, Ix1, Ix3, Ix4};
+use fftw3::fft::FFTPlan;
+use ndarray::{s, Array, ArrayView, ArrayViewMut, Axis, Ix1, Ix3, Ix4, Zip};
+use ndarray_parallel::prelude::*;
 use num_complex::Complex;
+use serde_derive::{Deserialize, Serialize};
 
 /// Returns a sampling of k values along all grid axes in FFTW standard form.
 /// In this case 3D.
@@ -73,6 +76,43 @@ pub fn get_norm_k_mesh(grid_size: GridSize, box_size: BoxSize) -> Array<Complex<
     &mesh * &kinv
 }
 
+/// Transforms a meshgrid of k values into the physical wave vectors of a field,
+/// that is sampled in sheared coordinates `x' = x - strain * (y - y_0)`.
+///
+/// A Fourier mode `exp(i (K_x x' + K_y y))` in sheared coordinates has the
+/// wave vector `(K_x, K_y - strain * K_x)` in the laboratory frame. Hence,
+/// for Lees-Edwards boundary conditions the wave vectors depend on time.
+pub fn shear_k_mesh(k_mesh: ArrayViewMut<Complex<Float>, Ix4>, strain: Float) {
+    let (kx, mut kyz) = k_mesh.split_at(Axis(0), 1);
+    kyz.index_axis_mut(Axis(0), 0)
+        .zip_mut_with(&kx.index_axis(Axis(0), 0), |ky, kx| *ky -= kx * strain);
+}
+
+/// Returns a meshgrid of k values for FFT of a field in sheared coordinates.
+/// See `shear_k_mesh`.
+pub fn get_sheared_k_mesh(
+    grid_size: GridSize,
+    box_size: BoxSize,
+    strain: Float,
+) -> Array<Complex<Float>, Ix4> {
+    let mut mesh = get_k_mesh(grid_size, box_size);
+    shear_k_mesh(mesh.view_mut(), strain);
+    mesh
+}
+
+/// Returns a normalized meshgrid of k values for FFT of a field in sheared
+/// coordinates, except for zero which is zero. See `shear_k_mesh`.
+pub fn get_sheared_norm_k_mesh(
+    grid_size: GridSize,
+    box_size: BoxSize,
+    strain: Float,
+) -> Array<Complex<Float>, Ix4> {
+    let mesh = get_sheared_k_mesh(grid_size, box_size, strain);
+    let kinv = get_inverse_norm(mesh.view());
+
+    &mesh * &kinv
+}
+
 /// Returns scalar field of inversed norm squared of k-vector-values.
 ///
 /// The inverse norm of k=0 is set to zero, i.e. 1/(k=0)^2 == 0
@@ -98,3 +138,133 @@ pub fn get_inverse_norm(k_mesh: ArrayView<Complex<Float>, Ix4>) -> Array<Complex
 
     inorm
 }
+
+/// Filter of the Fourier modes of a field, which suppresses aliasing errors
+/// of products and the sampling noise at high wave numbers.
+///
+/// The filters are given in terms of the mode numbers `eta_i = 2 m_i / n_i`
+/// relative to the largest mode `n_i / 2` in every direction, so they do not
+/// depend on the box size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SpectralFilter {
+    /// All modes are kept.
+    #[default]
+    None,
+    /// Orszag's 2/3 rule, which removes the modes with `|eta_i| > 2/3` in any
+    /// direction, so quadratic products are free of aliasing.
+    TwoThirds,
+    /// Smooth filter `exp(-strength sum_i eta_i^order)`, which damps the
+    /// largest mode in every direction by `exp(-strength)`.
+    Exponential {
+        #[serde(default = "default_exponential_strength")]
+        strength: Float,
+        #[serde(default = "default_exponential_order")]
+        order: i32,
+    },
+    /// Gaussian `exp(-sum_i eta_i^2 / (2 width^2))`, which removes the modes
+    /// with `|eta_i| > cutoff` in any direction.
+    Gaussian { width: Float, cutoff: Float },
+}
+
+/// Damps the largest mode to machine precision
+fn default_exponential_strength() -> Float {
+    36.
+}
+
+fn default_exponential_order() -> i32 {
+    8
+}
+
+impl SpectralFilter {
+    /// Returns true, if the parameters of the filter are valid, i.e. the
+    /// order of the exponential filter is positive and even and the width
+    /// and cutoff of the Gaussian are positive.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            SpectralFilter::None | SpectralFilter::TwoThirds => true,
+            SpectralFilter::Exponential { strength, order } => {
+                strength >= 0. && order > 0 && order % 2 == 0
+            }
+            SpectralFilter::Gaussian { width, cutoff } => width > 0. && cutoff > 0.,
+        }
+    }
+
+    /// Returns the factor of the mode with the absolute mode numbers `modes`
+    /// on a grid with `n` cells in every direction.
+    fn factor(&self, modes: [usize; 3], n: [usize; 3]) -> Float {
+        let eta = |i: usize| 2. * modes[i] as Float / n[i] as Float;
+
+        match *self {
+            SpectralFilter::None => 1.,
+            SpectralFilter::TwoThirds => {
+                if (0..3).all(|i| 3 * modes[i] <= n[i]) {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            SpectralFilter::Exponential { strength, order } => {
+                (-strength * (0..3).map(|i| eta(i).powi(order)).sum::<Float>()).exp()
+            }
+            SpectralFilter::Gaussian { width, cutoff } => {
+                if (0..3).all(|i| eta(i) <= cutoff) {
+                    let eta2 = (0..3).map(|i| eta(i).powi(2)).sum::<Float>();
+                    (-eta2 / (2. * width * width)).exp()
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+}
+
+/// Returns the factors of the filter `filter` for all modes of the grid in
+/// FFTW standard form, see `get_k_sampling`.
+pub fn get_filter_mesh(grid_size: GridSize, filter: SpectralFilter) -> Array<Float, Ix3> {
+    let n = [grid_size.x, grid_size.y, grid_size.z];
+
+    // the index `i` and `n - i` belong to the same absolute mode number
+    let mode = |i: usize, n: usize| i.min(n - i);
+
+    Array::from_shape_fn((n[0], n[1], n[2]), |(i, j, k)| {
+        filter.factor([mode(i, n[0]), mode(j, n[1]), mode(k, n[2])], n)
+    })
+}
+
+/// Calculates the gradient of a real scalar field from its FFT `field_k` and
+/// writes it into `gradient`, with the components along the first axis. Every
+/// component is transformed back to real space by the backward plan `fft`, so
+/// the result is real up to rounding errors.
+pub fn spectral_gradient(
+    field_k: ArrayView<Complex<Float>, Ix3>,
+    k_mesh: ArrayView<Complex<Float>, Ix4>,
+    fft: &FFTPlan,
+    gradient: ArrayViewMut<Complex<Float>, Ix4>,
+) {
+    let sh = field_k.dim();
+    let n = sh.0 * sh.1 * sh.2;
+
+    let k = k_mesh.into_shape([3, n]).unwrap();
+    let c = field_k.into_shape([n]).unwrap();
+    let mut g = gradient.into_shape([3, n]).unwrap();
+
+    // FFT normalization
+    let norm = Complex::new(0., 1.) / n as Float;
+
+    Zip::from(g.axis_iter_mut(Axis(1)))
+        .and(k.axis_iter(Axis(1)))
+        .and(c.axis_iter(Axis(0)))
+        .par_apply(|mut g, k, c| {
+            // trick needed, because Zip cannot iterate over scalar array yet
+            let c = unsafe { *c.as_ptr() };
+            for (g, k) in g.iter_mut().zip(k.iter()) {
+                *g = k * c * norm;
+            }
+        });
+
+    let mut g = g.into_shape([3, sh.0, sh.1, sh.2]).unwrap();
+    g.outer_iter_mut()
+        .into_par_iter()
+        .for_each(|mut v| fft.reexecute3d(&mut v));
+}
@@ -484,4 +484,120 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sheared_k_mesh() {
+        let bs = BoxSize {
+            x: TWOPI,
+            y: TWOPI,
+            z: TWOPI,
+        };
+        let gs = GridSize {
+            x: 4,
+            y: 3,
+            z: 2,
+            phi: 1,
+            theta: 1,
+        };
+        let strain = 0.3;
+
+        let mesh = get_k_mesh(gs, bs);
+        let sheared = get_sheared_k_mesh(gs, bs, strain);
+        let normed = get_sheared_norm_k_mesh(gs, bs, strain);
+
+        for ((i, j, k), ky) in sheared.index_axis(Axis(0), 1).indexed_iter() {
+            let kx = mesh[[0, i, j, k]].re;
+            let e = mesh[[1, i, j, k]].re - strain * kx;
+            assert!(equal_floats(ky.re, e), "{} != {}", ky.re, e);
+            assert!(equal_floats(sheared[[0, i, j, k]].re, kx));
+            assert!(equal_floats(
+                sheared[[2, i, j, k]].re,
+                mesh[[2, i, j, k]].re
+            ));
+
+            let k2 = (0..3)
+                .map(|c| sheared[[c, i, j, k]].re.powi(2))
+                .sum::<Float>();
+            let n2 = (0..3)
+                .map(|c| normed[[c, i, j, k]].re.powi(2))
+                .sum::<Float>();
+            let e = if k2 > 0. { 1. } else { 0. };
+            assert!(equal_floats(n2, e), "{} != {}", n2, e);
+        }
+    }
+
+    #[test]
+    fn test_filter_mesh() {
+        let gs = GridSize {
+            x: 6,
+            y: 4,
+            z: 1,
+            phi: 1,
+            theta: 1,
+        };
+
+        // every filter keeps the mean
+        for &filter in &[
+            SpectralFilter::None,
+            SpectralFilter::TwoThirds,
+            SpectralFilter::Exponential {
+                strength: 36.,
+                order: 8,
+            },
+            SpectralFilter::Gaussian {
+                width: 0.5,
+                cutoff: 0.8,
+            },
+        ] {
+            assert!(filter.is_valid());
+            let mesh = get_filter_mesh(gs, filter);
+            assert_eq!(mesh.dim(), (6, 4, 1));
+            assert_eq!(mesh[[0, 0, 0]], 1.);
+        }
+
+        assert!(get_filter_mesh(gs, SpectralFilter::None)
+            .iter()
+            .all(|&f| f == 1.));
+
+        // modes 0, 1, 2, -3, -2, -1 and 0, 1, -2, -1
+        let two_thirds = get_filter_mesh(gs, SpectralFilter::TwoThirds);
+        let x = two_thirds.slice(s![.., 0, 0]).to_vec();
+        let y = two_thirds.slice(s![0, .., 0]).to_vec();
+        assert_eq!(x, vec![1., 1., 1., 0., 1., 1.]);
+        assert_eq!(y, vec![1., 1., 0., 1.]);
+
+        let exponential = get_filter_mesh(
+            gs,
+            SpectralFilter::Exponential {
+                strength: 2.,
+                order: 2,
+            },
+        );
+        assert!(equal_floats(exponential[[3, 0, 0]], (-2.0 as Float).exp()));
+        assert!(equal_floats(
+            exponential[[1, 3, 0]],
+            (-2. * (1. / 9. + 1. / 4.) as Float).exp()
+        ));
+
+        let gaussian = get_filter_mesh(
+            gs,
+            SpectralFilter::Gaussian {
+                width: 0.5,
+                cutoff: 0.8,
+            },
+        );
+        assert!(equal_floats(gaussian[[5, 0, 0]], (-2. / 9. as Float).exp()));
+        assert_eq!(gaussian[[0, 2, 0]], 0.);
+        assert_eq!(gaussian[[3, 1, 0]], 0.);
+
+        assert!(!SpectralFilter::Exponential {
+            strength: 1.,
+            order: 3
+        }
+        .is_valid());
+        assert!(!SpectralFilter::Gaussian {
+            width: 0.,
+            cutoff: 1.
+        }
+        .is_valid());
+    }
 }
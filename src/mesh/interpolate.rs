@@ -19,7 +19,7 @@ where
     let p = Position {
         x: (position.x / gw.x + 0.5) % 1.0,
         y: (position.y / gw.y + 0.5) % 1.0,
-        z: (position.y / gw.z + 0.5) % 1.0,
+        z: (position.z / gw.z + 0.5) % 1.0,
     };
 
     [
@@ -218,6 +218,7 @@ mod tests {
 
         test(1.0, 1.0, 1.0, [0.0, 1000.0, 2000.0]);
         test(1.0, 1.0, 3.0, [1.0, 1001.0, 2001.0]);
+        test(1.0, 1.0, 2.0, [0.5, 1000.5, 2000.5]);
         test(1.5, 1.5, 1.5, [27.75, 1027.75, 2027.75]);
     }
 }
@@ -0,0 +1,34 @@
+use super::*;
+use crate::BoxSize;
+
+#[test]
+fn sort_particles_by_cell() {
+    let bs = BoxSize {
+        x: 2.,
+        y: 3.,
+        z: 4.,
+    };
+    let gs = GridSize {
+        x: 4,
+        y: 6,
+        z: 8,
+        phi: 1,
+        theta: 1,
+    };
+    let gw = GridWidth::new(gs, bs);
+
+    let mut particles = Particle::create_isotropic(1000, &bs, 1);
+    let mut expect = particles.clone();
+    sort_by_cell(&mut particles, &gw, &gs);
+
+    let idx: Vec<_> = particles
+        .iter()
+        .map(|p| get_flat_cell_index(&p.position, &gw, &gs))
+        .collect();
+    assert!(idx.windows(2).all(|w| w[0] <= w[1]));
+    assert!(idx.iter().all(|&i| i < gs.x * gs.y * gs.z));
+
+    // sorting is stable and keeps all particles
+    expect.sort_by_key(|p| get_flat_cell_index(&p.position, &gw, &gs));
+    assert_eq!(particles, expect);
+}
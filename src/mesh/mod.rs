@@ -1,12 +1,18 @@
-use crate::particle::Position;
+use crate::particle::{Particle, Position};
 use crate::GridSize;
 use grid_width::GridWidth;
 use ndarray::{Array, Axis, Ix1, Ix4};
+use rayon::prelude::*;
 
 pub mod fft_helper;
 pub mod grid_width;
 pub mod interpolate;
 
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./mesh_test.rs"]
+mod mesh_test;
+
 pub fn mesh3d<T: Clone + Default>(k: &[Array<T, Ix1>]) -> Array<T, Ix4> {
     let sh_x = k[0].len();
     let sh_y = k[1].len();
@@ -64,3 +70,29 @@ pub fn get_cell_index(
 
     (ix, iy, iz)
 }
+
+/// Returns the index of the grid cell containing `p` in a flattened, row-major
+/// spatial grid.
+pub fn get_flat_cell_index(p: &Position, grid_width: &GridWidth, grid_size: &GridSize) -> usize {
+    let (ix, iy, iz) = get_cell_index(p, grid_width, grid_size);
+    (ix * grid_size.y + iy) * grid_size.z + iz
+}
+
+/// Sorts `particles` by the flat index of the grid cell they are located in.
+/// Particles in the same cell keep their relative order.
+///
+/// Afterwards, particles that are close in memory look up field values at
+/// neighbouring grid points, which improves cache locality.
+pub fn sort_by_cell(particles: &mut [Particle], grid_width: &GridWidth, grid_size: &GridSize) {
+    // calculate the keys only once, since comparisons are much more frequent
+    let mut keyed: Vec<(usize, Particle)> = particles
+        .par_iter()
+        .map(|p| (get_flat_cell_index(&p.position, grid_width, grid_size), *p))
+        .collect();
+    keyed.par_sort_by_key(|k| k.0);
+
+    particles
+        .par_iter_mut()
+        .zip(keyed.par_iter())
+        .for_each(|(p, k)| *p = k.1);
+}
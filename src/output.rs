@@ -1,17 +1,32 @@
 use crate::distribution::Distribution;
+use crate::flowfield::stress::BulkStress;
 use crate::flowfield::FlowField3D;
-use crate::particle::Particle;
+use crate::particle::{Particle, Position};
 use crate::Float;
-use ndarray::{Array, Ix4};
+use ndarray::{Array, Ix3, Ix4};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Captures values that can be outputed during simulation.
 /// Not all fields need to have values, which is reflected in the Option type.
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct OutputEntry {
     pub distribution: Option<Distribution>,
+    /// Distribution of every particle species, if there is more than one
+    #[serde(default)]
+    pub species_distributions: Option<Vec<Distribution>>,
     pub flowfield: Option<FlowField3D>,
     pub magneticfield: Option<Array<Float, Ix4>>,
     pub particles: Option<Vec<Particle>>,
+    #[serde(default)]
+    pub stress: Option<BulkStress>,
+    #[serde(default)]
+    pub tracers: Option<Vec<Position>>,
+    /// Concentration of the chemical
+    #[serde(default)]
+    pub chemical: Option<Array<Float, Ix3>>,
+    /// Current values of the scheduled parameters
+    #[serde(default)]
+    pub parameters: Option<BTreeMap<String, Float>>,
     pub timestep: usize,
 }
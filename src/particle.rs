@@ -43,6 +43,16 @@ pub fn ang_pbc(phi: Float, theta: Float) -> (Float, Float) {
     }
 }
 
+/// Interaction of particles with confining walls at `z = 0` and `z = L_z`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WallInteraction {
+    /// Particles are reflected specularly, which also mirrors their
+    /// orientation.
+    Reflect,
+    /// Particles are reflected and aligned parallel to the wall.
+    Align,
+}
+
 pub type PositionVector = Vector<Position>;
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
@@ -75,6 +85,45 @@ impl Position {
         self.z = modulo(self.z, bs.z);
     }
 
+    /// Applies Lees-Edwards boundary conditions, i.e. periodic boundaries,
+    /// where the images above (below) the box are shifted by `offset`
+    /// (`-offset`) in x direction. A particle leaving the box through the top
+    /// re-enters at the bottom shifted by `-offset` and vice versa.
+    pub fn lees_edwards_pbc(&mut self, bs: &BoxSize, offset: Float) {
+        let crossings = (self.y / bs.y).floor();
+        self.x -= crossings * offset;
+        self.pbc(bs);
+    }
+
+    /// Applies periodic boundary conditions in x and y direction and reflects
+    /// the position at walls at `z = 0` and `z = L_z`. Returns the number of
+    /// reflections.
+    pub fn walls_pbc(&mut self, bs: &BoxSize) -> usize {
+        let reflections = (self.z / bs.z).floor().abs() as usize;
+
+        self.x = modulo(self.x, bs.x);
+        self.y = modulo(self.y, bs.y);
+        self.z = modulo(self.z, 2. * bs.z);
+        if self.z >= bs.z {
+            self.z = 2. * bs.z - self.z;
+        }
+        // keep the position inside of `[0, L_z)`
+        self.z = self.z.min(bs.z * (1. - Float::EPSILON));
+
+        reflections
+    }
+
+    /// Returns the position in sheared coordinates `(x - strain * (y - L_y /
+    /// 2), y, z)`, in which fields obeying Lees-Edwards boundary conditions
+    /// with an offset of `strain * L_y` are periodic in the box.
+    pub fn sheared(&self, bs: &BoxSize, strain: Float) -> Position {
+        Position {
+            x: modulo(self.x - strain * (self.y - bs.y / 2.), bs.x),
+            y: self.y,
+            z: self.z,
+        }
+    }
+
     pub fn from_vector_mut(&mut self, v: &PositionVector) {
         self.x = v[0];
         self.y = v[1];
@@ -84,6 +133,24 @@ impl Position {
     pub fn to_vector(&self) -> PositionVector {
         [self.x, self.y, self.z].into()
     }
+
+    /// Places n positions uniformly at random in the box, e.g. for passive
+    /// tracers.
+    pub fn create_uniform(n: usize, bs: &BoxSize, seed: u64) -> Vec<Position> {
+        let mut rng = Pcg64Mcg::seed_from_u64(seed);
+        let range: rand::distributions::Uniform<Float> = Uniform::new(0., 1.);
+
+        (0..n)
+            .map(|_| {
+                Position::new(
+                    bs.x * rng.sample(range),
+                    bs.y * rng.sample(range),
+                    bs.z * rng.sample(range),
+                    bs,
+                )
+            })
+            .collect()
+    }
 }
 
 pub type OrientationVector = Vector<Orientation>;
@@ -172,6 +239,29 @@ impl CosSinOrientation {
     }
 }
 
+/// Properties of a single particle, which vary between the particles of a
+/// polydisperse suspension. They are given relative to the parameters of the
+/// particle species, i.e. they are one for a monodisperse suspension.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParticleAttributes {
+    /// swimming speed
+    pub speed: Float,
+    /// shape parameter, which scales the coupling to the strain rate
+    pub shape: Float,
+    /// magnetic moment
+    pub magnetic_moment: Float,
+}
+
+impl Default for ParticleAttributes {
+    fn default() -> Self {
+        ParticleAttributes {
+            speed: 1.,
+            shape: 1.,
+            magnetic_moment: 1.,
+        }
+    }
+}
+
 /// Coordinates (including the orientation) of a particle in 2D.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Particle {
@@ -179,6 +269,8 @@ pub struct Particle {
     pub position: Position,
     /// orientation of particle as an angle
     pub orientation: Orientation,
+    /// properties of a polydisperse particle
+    pub attributes: ParticleAttributes,
 }
 
 impl Particle {
@@ -194,6 +286,7 @@ impl Particle {
         let mut p = Particle {
             position: Position::new(x, y, z, box_size),
             orientation: Orientation::new(phi, theta),
+            attributes: ParticleAttributes::default(),
         };
 
         p.pbc(box_size);
@@ -210,6 +303,7 @@ impl Particle {
         let mut p = Particle {
             position: pos,
             orientation: o,
+            attributes: ParticleAttributes::default(),
         };
 
         p.pbc(box_size);
@@ -221,6 +315,38 @@ impl Particle {
         self.orientation.pbc();
     }
 
+    /// Same as `pbc`, but with Lees-Edwards boundary conditions for the
+    /// position. See `Position::lees_edwards_pbc`.
+    pub fn lees_edwards_pbc(&mut self, bs: &BoxSize, offset: Float) {
+        self.position.lees_edwards_pbc(bs, offset);
+        self.orientation.pbc();
+    }
+
+    /// Same as `pbc`, but with walls at `z = 0` and `z = L_z`. Depending on
+    /// `interaction`, the orientation of a particle hitting a wall is either
+    /// mirrored or aligned parallel to the wall.
+    pub fn walls_pbc(&mut self, bs: &BoxSize, interaction: WallInteraction) {
+        let reflections = self.position.walls_pbc(bs);
+        if reflections > 0 {
+            match interaction {
+                WallInteraction::Reflect => {
+                    if reflections % 2 == 1 {
+                        self.orientation.theta = PI - self.orientation.theta;
+                    }
+                }
+                WallInteraction::Align => self.orientation.theta = PIHALF,
+            }
+        }
+        self.orientation.pbc();
+    }
+
+    /// Projects the particle onto the plane `z = 0` and orients it in the
+    /// plane, see `Dimensionality::is_planar`.
+    pub fn project_to_plane(&mut self) {
+        self.position.z = 0.;
+        self.orientation.theta = PIHALF;
+    }
+
     pub fn place_isotropic<F>(r: &mut F, bs: &BoxSize) -> Particle
     where
         F: FnMut() -> Float,
@@ -333,6 +459,7 @@ impl From<ParticleVector> for Particle {
         Particle {
             position: Position::from_vector(&p.position),
             orientation: Orientation::from_vector(&p.orientation),
+            attributes: ParticleAttributes::default(),
         }
     }
 }
@@ -359,7 +486,8 @@ pub fn pdf_homogeneous_fixpoint(kappa: Float, x: Float) -> Float {
     r
 }
 
-/// Serialize particle as continuous array instead of struct
+/// Serialize particle as continuous array instead of struct. The attributes
+/// are not included, snapshots store them separately.
 impl Serialize for Particle {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -390,6 +518,7 @@ impl<'de> Deserialize<'de> for Particle {
                 z: pz,
             },
             orientation: Orientation { phi: op, theta: ot },
+            attributes: ParticleAttributes::default(),
         })
     }
 }
@@ -29,6 +29,24 @@ fn test_random_particles() {
     }
 }
 
+#[test]
+fn test_random_positions() {
+    let bs = BoxSize {
+        x: 1.,
+        y: 2.,
+        z: 3.,
+    };
+
+    let positions = Position::create_uniform(1000, &bs, 1);
+    assert_eq!(positions.len(), 1000);
+
+    for &Position { x, y, z } in &positions {
+        assert!(0. <= x && x < 1.);
+        assert!(0. <= y && y < 2.);
+        assert!(0. <= z && z < 3.);
+    }
+}
+
 #[test]
 fn test_modulo() {
     let input = [
@@ -163,3 +181,117 @@ fn test_orientation_from_orientation_vector() {
         );
     }
 }
+
+#[test]
+fn test_lees_edwards_pbc() {
+    let bs = BoxSize {
+        x: 2.,
+        y: 3.,
+        z: 4.,
+    };
+    let strain = 0.2;
+    let offset = strain * bs.y;
+
+    // leaving through the top and bottom shifts by the offset of the images
+    let input = [
+        [0.5, 3.1, 1.],
+        [0.5, -0.1, 1.],
+        [1.9, 6.5, 1.],
+        [0.5, 1., 1.],
+    ];
+    let expect = [
+        [0.5 - offset, 0.1, 1.],
+        [0.5 + offset, 2.9, 1.],
+        [1.9 - 2. * offset, 0.5, 1.],
+        [0.5, 1., 1.],
+    ];
+
+    for (i, e) in input.iter().zip(&expect) {
+        let mut p = Position {
+            x: i[0],
+            y: i[1],
+            z: i[2],
+        };
+        // sheared coordinates are periodic in the box
+        let sheared = Position {
+            x: modulo(p.x - strain * (p.y - bs.y / 2.), bs.x),
+            y: modulo(p.y, bs.y),
+            z: p.z,
+        };
+        p.lees_edwards_pbc(&bs, offset);
+        let e = Position::new(e[0], e[1], e[2], &bs);
+
+        for (a, b) in [(p.x, e.x), (p.y, e.y), (p.z, e.z)].iter() {
+            assert!((a - b).abs() < 1e-12, "{:?} != {:?}", p, e);
+        }
+
+        let s = p.sheared(&bs, strain);
+        assert!((s.x - sheared.x).abs() < 1e-12, "{:?} != {:?}", s, sheared);
+        assert!((s.y - sheared.y).abs() < 1e-12, "{:?} != {:?}", s, sheared);
+    }
+}
+
+#[test]
+fn test_walls_pbc() {
+    let bs = BoxSize {
+        x: 2.,
+        y: 3.,
+        z: 4.,
+    };
+
+    // [x, y, z, theta] -> [x, y, z, theta] for reflecting walls
+    let input = [
+        [0.5, 1., 4.5, 0.3],
+        [-0.5, 1., -0.5, 2.],
+        [0.5, 3.5, 9., 0.3],
+        [0.5, 1., 1., 0.3],
+    ];
+    let expect = [
+        [0.5, 1., 3.5, PI - 0.3],
+        [1.5, 1., 0.5, PI - 2.],
+        [0.5, 0.5, 1., 0.3],
+        [0.5, 1., 1., 0.3],
+    ];
+
+    for (i, e) in input.iter().zip(&expect) {
+        let mut p = Particle {
+            position: Position {
+                x: i[0],
+                y: i[1],
+                z: i[2],
+            },
+            orientation: Orientation {
+                phi: 1.,
+                theta: i[3],
+            },
+            attributes: ParticleAttributes::default(),
+        };
+        let mut aligned = p;
+
+        p.walls_pbc(&bs, WallInteraction::Reflect);
+        aligned.walls_pbc(&bs, WallInteraction::Align);
+
+        let pos = p.position;
+        for (a, b) in [(pos.x, e[0]), (pos.y, e[1]), (pos.z, e[2])].iter() {
+            assert!((a - b).abs() < 1e-12, "{:?} != {:?}", p, e);
+        }
+        assert_eq!(aligned.position, p.position);
+        assert!((p.orientation.theta - e[3]).abs() < 1e-12, "{:?}", p);
+        assert_eq!(p.orientation.phi, 1.);
+
+        if i[2] == e[2] {
+            assert_eq!(aligned.orientation, p.orientation);
+        } else {
+            assert!((aligned.orientation.theta - PI / 2.).abs() < 1e-12);
+        }
+    }
+
+    // a particle exactly at the upper wall is kept inside of the box
+    let mut p = Position {
+        x: 0.,
+        y: 0.,
+        z: 4.,
+    };
+    assert_eq!(p.walls_pbc(&bs), 1);
+    assert!(p.z < bs.z);
+}
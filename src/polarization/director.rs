@@ -3,8 +3,7 @@
 #[path = "./director_test.rs"]
 mod director_test;
 
-use crate::consts::TWOPI;
-use crate::distribution::Distribution;
+use crate::distribution::{Distribution, OrientationRepresentation};
 use crate::mesh::grid_width::GridWidth;
 use crate::particle::Orientation;
 use crate::Float;
@@ -12,10 +11,7 @@ use crate::GridSize;
 use ndarray::{Array, Axis, Ix3, Ix4, Zip};
 use ndarray_parallel::prelude::*;
 use num_complex::Complex;
-#[cfg(feature = "single")]
-use std::f32::consts::PI;
-#[cfg(not(feature = "single"))]
-use std::f64::consts::PI;
+use std::slice;
 
 pub struct DirectorField {
     pub field: Array<Complex<Float>, Ix4>,
@@ -26,38 +22,67 @@ pub struct DirectorField {
 
 impl DirectorField {
     pub fn new(grid_size: GridSize, grid_width: GridWidth) -> DirectorField {
+        DirectorField::with_orientation(
+            grid_size,
+            grid_width,
+            OrientationRepresentation::UniformGrid,
+        )
+    }
+
+    /// Returns a director field for distributions, that represent the
+    /// orientations as given by `orientation`.
+    pub fn with_orientation(
+        grid_size: GridSize,
+        grid_width: GridWidth,
+        orientation: OrientationRepresentation,
+    ) -> DirectorField {
         DirectorField {
             field: Array::default([3, grid_size.x, grid_size.y, grid_size.z]),
             grid_width: grid_width,
-            kernel: orientation_kernel(grid_size, grid_width),
+            kernel: orientation_kernel(grid_size, grid_width, orientation),
         }
     }
 
     pub fn from_distribution(&mut self, dist: &Distribution) {
-        let dist_sh = dist.dim();
+        self.from_distributions(slice::from_ref(dist), &[1.]);
+    }
+
+    /// Calculates the director field of a mixture of particle species as the
+    /// sum of the director fields of the distributions `dists`, each weighted
+    /// by the corresponding entry of `weights`.
+    pub fn from_distributions(&mut self, dists: &[Distribution], weights: &[Float]) {
+        assert_eq!(
+            dists.len(),
+            weights.len(),
+            "Every species needs a distribution and a weight."
+        );
+
+        let dist_sh = dists[0].dim();
         let n_angle = dist_sh.3 * dist_sh.4;
         let n_dist = dist_sh.0 * dist_sh.1 * dist_sh.2;
 
-        let gw = dist.get_grid_width();
-
         // collapse dimension to ease calculations
-        let kernel = self.kernel.view_mut().into_shape([3, n_angle]).unwrap();
+        let kernel = self.kernel.view().into_shape([3, n_angle]).unwrap();
 
-        let dist = dist.dist.view().into_shape([n_dist, n_angle]).unwrap();
         let mut field = self.field.view_mut().into_shape([3, n_dist]).unwrap();
+        field.fill(Complex::new(0., 0.));
+
+        for (dist, w) in dists.iter().zip(weights) {
+            // Integration measure. sin(theta) is already included.
+            let measure = dist.orientation_measure() * w;
 
-        // Integration measure. sin(theta) is already included.
-        let measure = gw.phi * gw.theta;
+            let dist = dist.dist.view().into_shape([n_dist, n_angle]).unwrap();
 
-        // Calculating the integral over the orientation. `norm` includes weights for
-        // integration and normalisation of DFT
-        Zip::from(field.axis_iter_mut(Axis(1)))
-            .and(dist.outer_iter())
-            .par_apply(|mut f, d| {
-                for (f, kern) in f.iter_mut().zip(kernel.outer_iter()) {
-                    *f =  Complex::from(kern.dot(&d) * measure);
-                }
-            });
+            // Calculating the integral over the orientation. `norm` includes weights
+            // for integration and normalisation of DFT
+            Zip::from(field.axis_iter_mut(Axis(1)))
+                .and(dist.outer_iter())
+                .par_apply(|mut f, d| {
+                    for (f, kern) in f.iter_mut().zip(kernel.outer_iter()) {
+                        *f += Complex::from(kern.dot(&d) * measure);
+                    }
+                });
+        }
     }
 }
 
@@ -66,27 +91,16 @@ impl DirectorField {
 ///
 /// It returns n(theta, sin) = [sin(theta) cos(phi), sin(theta) sin(phi),
 /// cos(theta)] as a discrete field over angles.
-fn orientation_kernel(grid_size: GridSize, grid_width: GridWidth) -> Array<Float, Ix3> {
-    let mut s = Array::<Float, _>::zeros((3, grid_size.phi, grid_size.theta));
-    // Calculate discrete angles, considering the cell centered sample points of
-    // the distribution
-    let gw_half_phi = grid_width.phi / 2.;
-    let gw_half_theta = grid_width.theta / 2.;
-    let angles_phi = Array::linspace(0. + gw_half_phi, TWOPI - gw_half_phi, grid_size.phi);
-    let angles_theta = Array::linspace(0. + gw_half_theta, PI - gw_half_theta, grid_size.theta);
-
-    for (mut ax1, phi) in s.axis_iter_mut(Axis(1)).zip(&angles_phi) {
-        for (mut e, theta) in ax1.axis_iter_mut(Axis(1)).zip(&angles_theta) {
-            let o = Orientation::new(*phi, *theta).to_vector().v;
-            // Create ndarray::Array from array
-            let o = Array::from_vec(o.to_vec());
-
-            e.assign(&o);
-
-            // Gram's determinant already taken care of by the modified cell average in the
-            // distribution code e *= theta.sin();
-        }
-    }
-
-    s
+fn orientation_kernel(
+    grid_size: GridSize,
+    grid_width: GridWidth,
+    orientation: OrientationRepresentation,
+) -> Array<Float, Ix3> {
+    // Gram's determinant of the uniform grid is already taken care of by the
+    // modified cell average in the distribution code
+    orientation.kernel(grid_size, grid_width, 3, |phi, theta| {
+        let o = Orientation::new(phi, theta).to_vector().v;
+        // Create ndarray::Array from array
+        Array::from_vec(o.to_vec())
+    })
 }
@@ -5,6 +5,10 @@ use crate::test_helper::equal_floats;
 use crate::Float;
 use crate::{BoxSize, GridSize};
 use ndarray::s;
+#[cfg(feature = "single")]
+use std::f32::consts::PI;
+#[cfg(not(feature = "single"))]
+use std::f64::consts::PI;
 
 #[test]
 fn test_polarization_from_distribution() {
@@ -71,3 +75,122 @@ fn test_polarization_from_distribution() {
         );
     }
 }
+
+#[test]
+fn test_polarization_from_spherical_harmonics() {
+    let bs = BoxSize {
+        x: 3.,
+        y: 3.,
+        z: 1.,
+    };
+    let gs = GridSize {
+        x: 3,
+        y: 3,
+        z: 1,
+        phi: 1,
+        theta: 1,
+    };
+    let orientation = OrientationRepresentation::SphericalHarmonics { degree: 2 };
+    let gw = GridWidth::new(gs, bs);
+
+    // The director is exactly represented by spherical harmonics of degree one,
+    // also close to the poles.
+    let p = vec![Particle::new(1.5, 2.5, 0.0, 0.3, 0.01, &bs)];
+    let mut d = Distribution::with_orientation(gs, bs, orientation);
+    d.sample_from(&p);
+
+    let mut field = DirectorField::with_orientation(gs, gw, orientation);
+    field.from_distribution(&d);
+
+    let n = p[0].orientation.to_vector();
+    for (i, n) in n.iter().enumerate() {
+        let v = field.field[[i, 1, 2, 0]].re;
+        assert!((v - n).abs() < 1e-14, "{} != {}", v, n);
+    }
+
+    field
+        .field
+        .slice_mut(s![.., 1, 2, 0])
+        .map_inplace(|v| *v = (0.0).into());
+
+    for v in field.field.iter() {
+        assert!(v.norm() < 1e-14, "Value should be zero, but is {}.", v);
+    }
+}
+
+#[test]
+fn test_polarization_from_equal_area_grid() {
+    let bs = BoxSize {
+        x: 3.,
+        y: 3.,
+        z: 1.,
+    };
+    let gs = GridSize {
+        x: 3,
+        y: 3,
+        z: 1,
+        phi: 4,
+        theta: 4,
+    };
+    let orientation = OrientationRepresentation::EqualAreaGrid;
+    let gw = GridWidth::new(gs, bs);
+
+    // falls into the bin centered at phi = pi / 4 and cos(theta) = 0.75
+    let p = vec![Particle::new(
+        1.5,
+        2.5,
+        0.0,
+        1.0,
+        (0.8 as Float).acos(),
+        &bs,
+    )];
+    let mut d = Distribution::with_orientation(gs, bs, orientation);
+    d.sample_from(&p);
+
+    let mut field = DirectorField::with_orientation(gs, gw, orientation);
+    field.from_distribution(&d);
+
+    let sin_theta = (1. - 0.75 * 0.75 as Float).sqrt();
+    let expect = [
+        sin_theta * (PI / 4.).cos(),
+        sin_theta * (PI / 4.).sin(),
+        0.75,
+    ];
+    for (i, n) in expect.iter().enumerate() {
+        let v = field.field[[i, 1, 2, 0]].re;
+        assert!(equal_floats(v, *n), "{} != {}", v, n);
+    }
+}
+
+#[test]
+fn test_weighted_director_of_species() {
+    let bs = BoxSize {
+        x: 3.,
+        y: 3.,
+        z: 1.,
+    };
+    let gs = GridSize {
+        x: 3,
+        y: 3,
+        z: 1,
+        phi: 8,
+        theta: 5,
+    };
+
+    let gw = GridWidth::new(gs, bs);
+
+    let p = Particle::create_isotropic(50, &bs, 3);
+    let mut d = Distribution::new(gs, bs);
+    d.sample_from(&p);
+
+    let mut single = DirectorField::new(gs, gw);
+    single.from_distribution(&d);
+
+    // a non-magnetic species does not contribute
+    let mut mixture = DirectorField::new(gs, gw);
+    mixture.from_distributions(&[d.clone(), d.clone()], &[0.5, 0.]);
+
+    for (a, b) in mixture.field.iter().zip(single.field.iter()) {
+        assert!(equal_floats(a.re, 0.5 * b.re), "{} != {}", a, 0.5 * b);
+    }
+}
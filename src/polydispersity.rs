@@ -0,0 +1,177 @@
+//! Distributions of the properties of polydisperse particles, which are
+//! sampled once at the initialisation.
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./polydispersity_test.rs"]
+mod polydispersity_test;
+
+use crate::particle::ParticleAttributes;
+use crate::Float;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_distr::{LogNormal, Normal};
+use rand_pcg::Pcg64Mcg;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+/// Distribution of a single particle attribute relative to the parameter of
+/// the species, i.e. with mean one.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AttributeDistribution {
+    /// All particles have the parameter of their species.
+    #[default]
+    Constant,
+    /// Normal distribution with the standard deviation `std`. Negative values
+    /// are drawn again, i.e. the distribution is truncated at zero. This
+    /// biases the mean to `1 + std phi(1 / std) / Phi(1 / std)`, where `phi`
+    /// and `Phi` are the density and the cumulative distribution function of
+    /// the standard normal distribution. The bias is negligible for `std <=
+    /// 0.3` (below `5e-4`), but grows to `0.028` for `std = 0.5` and `0.29`
+    /// for `std = 1`.
+    Normal { std: Float },
+    /// Log-normal distribution, where the logarithm has the standard
+    /// deviation `sigma`.
+    LogNormal { sigma: Float },
+    /// Values are read from a text file with whitespace separated values, one
+    /// for every particle in the order of the particles.
+    File { path: String },
+}
+
+impl AttributeDistribution {
+    /// Returns true, if the parameters describe a distribution of
+    /// non-negative values.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            AttributeDistribution::Constant | AttributeDistribution::File { .. } => true,
+            AttributeDistribution::Normal { std } => std >= 0.,
+            AttributeDistribution::LogNormal { sigma } => sigma >= 0.,
+        }
+    }
+
+    /// Checks, that a file of values exists and contains at least `n` valid
+    /// non-negative numbers. The other distributions are only checked by
+    /// `is_valid`.
+    pub fn check_file(&self, n: usize) -> io::Result<()> {
+        match self {
+            AttributeDistribution::File { path } => read_values(path, n).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `n` values drawn from the distribution. Reading the values
+    /// from a file fails, if the file does not contain at least `n` valid
+    /// non-negative numbers.
+    pub fn sample<R: Rng>(&self, n: usize, rng: &mut R) -> io::Result<Vec<Float>> {
+        match self {
+            AttributeDistribution::Constant => Ok(vec![1.; n]),
+            AttributeDistribution::Normal { std } => {
+                let d = Normal::new(1., *std).map_err(invalid_parameter)?;
+                Ok((0..n)
+                    .map(|_| loop {
+                        let v: Float = rng.sample(d);
+                        if v >= 0. {
+                            break v;
+                        }
+                    })
+                    .collect())
+            }
+            AttributeDistribution::LogNormal { sigma } => {
+                // the mean of a log-normal distribution is `exp(mu + sigma^2 / 2)`
+                let d = LogNormal::new(-0.5 * sigma * sigma, *sigma).map_err(invalid_parameter)?;
+                Ok((0..n).map(|_| rng.sample(d)).collect())
+            }
+            AttributeDistribution::File { path } => read_values(path, n),
+        }
+    }
+}
+
+/// Distributions of all attributes of polydisperse particles.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Polydispersity {
+    #[serde(default)]
+    pub speed: AttributeDistribution,
+    #[serde(default)]
+    pub shape: AttributeDistribution,
+    #[serde(default)]
+    pub magnetic_moment: AttributeDistribution,
+}
+
+impl Polydispersity {
+    /// Returns true, if all particles have the parameters of their species.
+    pub fn is_monodisperse(&self) -> bool {
+        [&self.speed, &self.shape, &self.magnetic_moment]
+            .iter()
+            .all(|d| **d == AttributeDistribution::Constant)
+    }
+
+    /// Returns true, if all distributions are valid, see
+    /// `AttributeDistribution::is_valid`.
+    pub fn is_valid(&self) -> bool {
+        self.speed.is_valid() && self.shape.is_valid() && self.magnetic_moment.is_valid()
+    }
+
+    /// Checks the files of all distributions for `n` particles, see
+    /// `AttributeDistribution::check_file`.
+    pub fn check_files(&self, n: usize) -> io::Result<()> {
+        self.speed.check_file(n)?;
+        self.shape.check_file(n)?;
+        self.magnetic_moment.check_file(n)
+    }
+
+    /// Returns the attributes of `n` particles drawn independently from the
+    /// distributions with random numbers seeded by `seed`.
+    pub fn sample(&self, n: usize, seed: u64) -> io::Result<Vec<ParticleAttributes>> {
+        let mut rng = Pcg64Mcg::seed_from_u64(seed);
+        let speed = self.speed.sample(n, &mut rng)?;
+        let shape = self.shape.sample(n, &mut rng)?;
+        let magnetic_moment = self.magnetic_moment.sample(n, &mut rng)?;
+
+        Ok(speed
+            .into_iter()
+            .zip(shape)
+            .zip(magnetic_moment)
+            .map(|((speed, shape), magnetic_moment)| ParticleAttributes {
+                speed,
+                shape,
+                magnetic_moment,
+            })
+            .collect())
+    }
+}
+
+fn invalid_parameter<E: std::fmt::Debug>(e: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Invalid parameter of the distribution: {:?}", e),
+    )
+}
+
+/// Reads the first `n` values of the whitespace separated file `path`.
+fn read_values(path: &str, n: usize) -> io::Result<Vec<Float>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let content = fs::read_to_string(path)?;
+    let values = content
+        .split_whitespace()
+        .take(n)
+        .map(|v| match v.parse::<Float>() {
+            Ok(v) if v >= 0. => Ok(v),
+            _ => Err(invalid(format!("'{}' is not a non-negative number.", v))),
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    if values.len() < n {
+        return Err(invalid(format!(
+            "'{}' contains {} values, but {} particles need one.",
+            path,
+            values.len(),
+            n
+        )));
+    }
+
+    Ok(values)
+}
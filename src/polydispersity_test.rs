@@ -0,0 +1,94 @@
+use super::*;
+use std::env;
+
+fn mean(v: &[Float]) -> Float {
+    v.iter().sum::<Float>() / v.len() as Float
+}
+
+#[test]
+fn sample_distributions() {
+    let mut rng = Pcg64Mcg::seed_from_u64(1);
+    let n = 100_000;
+
+    assert_eq!(
+        AttributeDistribution::Constant.sample(3, &mut rng).unwrap(),
+        vec![1.; 3]
+    );
+
+    let normal = AttributeDistribution::Normal { std: 0.2 }
+        .sample(n, &mut rng)
+        .unwrap();
+    assert!((mean(&normal) - 1.).abs() < 1e-2);
+    assert!(normal.iter().all(|&v| v >= 0.));
+
+    // the truncation at zero biases the mean for wider distributions
+    let truncated = AttributeDistribution::Normal { std: 0.5 }
+        .sample(n, &mut rng)
+        .unwrap();
+    assert!((mean(&truncated) - 1.0276).abs() < 5e-3);
+
+    // negative values are drawn again
+    let wide = AttributeDistribution::Normal { std: 2. }
+        .sample(1000, &mut rng)
+        .unwrap();
+    assert!(wide.iter().all(|&v| v >= 0.));
+
+    let log_normal = AttributeDistribution::LogNormal { sigma: 0.5 }
+        .sample(n, &mut rng)
+        .unwrap();
+    assert!((mean(&log_normal) - 1.).abs() < 1e-2);
+    assert!(log_normal.iter().all(|&v| v > 0.));
+
+    assert!(AttributeDistribution::Normal { std: -1. }
+        .sample(n, &mut rng)
+        .is_err());
+    assert!(!AttributeDistribution::LogNormal { sigma: -1. }.is_valid());
+}
+
+#[test]
+fn read_from_file() {
+    let path = env::temp_dir().join("polydispersity_test_values.txt");
+    fs::write(&path, "0.5 1.5\n2.0\n3.0").unwrap();
+    let d = AttributeDistribution::File {
+        path: path.to_str().unwrap().to_string(),
+    };
+    let mut rng = Pcg64Mcg::seed_from_u64(1);
+
+    assert_eq!(d.sample(3, &mut rng).unwrap(), vec![0.5, 1.5, 2.0]);
+    assert!(d.sample(5, &mut rng).is_err());
+    assert!(d.check_file(4).is_ok());
+    assert!(d.check_file(5).is_err());
+
+    fs::write(&path, "0.5 -1.5").unwrap();
+    assert!(d.sample(2, &mut rng).is_err());
+
+    fs::remove_file(&path).unwrap();
+    assert!(d.sample(2, &mut rng).is_err());
+    assert!(d.check_file(2).is_err());
+    assert!(AttributeDistribution::Normal { std: 0.2 }
+        .check_file(2)
+        .is_ok());
+}
+
+#[test]
+fn sample_attributes() {
+    let p = Polydispersity::default();
+    assert!(p.is_monodisperse());
+    assert!(p
+        .sample(4, 1)
+        .unwrap()
+        .iter()
+        .all(|a| *a == ParticleAttributes::default()));
+
+    let p = Polydispersity {
+        speed: AttributeDistribution::LogNormal { sigma: 0.3 },
+        ..Default::default()
+    };
+    assert!(!p.is_monodisperse());
+    assert!(p.is_valid());
+
+    let a = p.sample(100, 2).unwrap();
+    assert_eq!(a, p.sample(100, 2).unwrap());
+    assert!(a.iter().all(|a| a.shape == 1. && a.magnetic_moment == 1.));
+    assert!(a.iter().any(|a| a.speed != 1.));
+}
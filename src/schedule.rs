@@ -0,0 +1,178 @@
+//! Time-dependent values of parameters, e.g. for switching an external field
+//! on and off or ramping the activity during a simulation.
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./schedule_test.rs"]
+mod schedule_test;
+
+use crate::consts::TWOPI;
+use crate::Float;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+/// Value of a parameter as a function of the simulation time `t`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Schedule {
+    /// Piecewise constant with the value `values[i]` from `times[i]` until
+    /// `times[i + 1]`. Before `times[0]`, the first value is used.
+    Steps {
+        times: Vec<Float>,
+        values: Vec<Float>,
+    },
+    /// Linear ramp from `from` at time `start` to `to` at time `end`, which
+    /// is constant before and after.
+    Ramp {
+        start: Float,
+        end: Float,
+        from: Float,
+        to: Float,
+    },
+    /// Oscillation `mean + amplitude sin(2 pi t / period + phase)`
+    Sine {
+        mean: Float,
+        amplitude: Float,
+        period: Float,
+        #[serde(default)]
+        phase: Float,
+    },
+    /// Linear interpolation between the points `(times[i], values[i])`, which
+    /// is constant before the first and after the last point.
+    Table {
+        times: Vec<Float>,
+        values: Vec<Float>,
+    },
+    /// Table read from a text file with two whitespace separated columns of
+    /// times and values. It has to be converted by `load` before it can be
+    /// evaluated.
+    File { path: String },
+}
+
+impl Schedule {
+    /// Returns true, if the schedule is well-defined, i.e. tables have one
+    /// value for every time in increasing order and periods are positive.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Schedule::Steps { times, values } | Schedule::Table { times, values } => {
+                !times.is_empty()
+                    && times.len() == values.len()
+                    && times.windows(2).all(|w| w[0] <= w[1])
+            }
+            Schedule::Ramp { start, end, .. } => start <= end,
+            Schedule::Sine { period, .. } => *period > 0.,
+            Schedule::File { .. } => true,
+        }
+    }
+
+    /// Returns the schedule with the file read into a `Table`. All other
+    /// schedules are returned unchanged.
+    pub fn load(&self) -> io::Result<Schedule> {
+        match self {
+            Schedule::File { path } => read_table(path),
+            s => Ok(s.clone()),
+        }
+    }
+
+    /// Returns the value at time `t`.
+    ///
+    /// Panics for `File`, which has to be loaded first.
+    pub fn value_at(&self, t: Float) -> Float {
+        match self {
+            Schedule::Steps { times, values } => {
+                let i = times.iter().take_while(|&&s| s <= t).count();
+                values[i.max(1) - 1]
+            }
+            Schedule::Ramp {
+                start,
+                end,
+                from,
+                to,
+            } => {
+                if t <= *start {
+                    *from
+                } else if t >= *end {
+                    *to
+                } else {
+                    from + (to - from) * (t - start) / (end - start)
+                }
+            }
+            Schedule::Sine {
+                mean,
+                amplitude,
+                period,
+                phase,
+            } => mean + amplitude * (TWOPI * t / period + phase).sin(),
+            Schedule::Table { times, values } => {
+                let i = times.iter().take_while(|&&s| s <= t).count();
+                if i == 0 {
+                    values[0]
+                } else if i == times.len() {
+                    values[i - 1]
+                } else {
+                    let (t0, t1) = (times[i - 1], times[i]);
+                    let (v0, v1) = (values[i - 1], values[i]);
+                    v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+                }
+            }
+            Schedule::File { path } => panic!("Schedule of '{}' has not been loaded.", path),
+        }
+    }
+
+    /// Returns times `t >= 0`, at which the schedule takes all its extreme
+    /// values for `t >= 0`, i.e. the start and the breakpoints of piecewise
+    /// linear schedules and the maximum and minimum of an oscillation.
+    ///
+    /// Panics for `File`, which has to be loaded first.
+    pub fn extreme_times(&self) -> Vec<Float> {
+        let mut times = match self {
+            Schedule::Steps { times, .. } | Schedule::Table { times, .. } => times.clone(),
+            Schedule::Ramp { start, end, .. } => vec![*start, *end],
+            Schedule::Sine { period, phase, .. } => {
+                let max = period * (0.25 - phase / TWOPI).rem_euclid(1.);
+                vec![max, max + 0.5 * period]
+            }
+            Schedule::File { path } => panic!("Schedule of '{}' has not been loaded.", path),
+        };
+        times.push(0.);
+
+        // values before the start of the simulation are never used
+        times.iter().map(|t| t.max(0.)).collect()
+    }
+}
+
+/// Reads a table of times and values from the file `path`.
+fn read_table(path: &str) -> io::Result<Schedule> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let content = fs::read_to_string(path)?;
+    let numbers = content
+        .split_whitespace()
+        .map(|v| {
+            v.parse::<Float>()
+                .map_err(|_| invalid(format!("'{}' is not a number.", v)))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    if numbers.len() % 2 != 0 {
+        return Err(invalid(format!(
+            "'{}' does not contain pairs of times and values.",
+            path
+        )));
+    }
+
+    let table = Schedule::Table {
+        times: numbers.iter().step_by(2).cloned().collect(),
+        values: numbers.iter().skip(1).step_by(2).cloned().collect(),
+    };
+
+    if !table.is_valid() {
+        return Err(invalid(format!(
+            "'{}' needs at least one row with times in increasing order.",
+            path
+        )));
+    }
+
+    Ok(table)
+}
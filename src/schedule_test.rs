@@ -0,0 +1,144 @@
+use super::*;
+use std::env;
+
+#[test]
+fn steps_and_ramp() {
+    let steps = Schedule::Steps {
+        times: vec![1., 2.],
+        values: vec![3., 4.],
+    };
+    assert!(steps.is_valid());
+    assert_eq!(steps.value_at(0.), 3.);
+    assert_eq!(steps.value_at(1.5), 3.);
+    assert_eq!(steps.value_at(2.), 4.);
+    assert_eq!(steps.value_at(10.), 4.);
+
+    let ramp = Schedule::Ramp {
+        start: 1.,
+        end: 3.,
+        from: 0.,
+        to: 1.,
+    };
+    assert!(ramp.is_valid());
+    assert_eq!(ramp.value_at(0.), 0.);
+    assert_eq!(ramp.value_at(2.), 0.5);
+    assert_eq!(ramp.value_at(4.), 1.);
+
+    assert!(!Schedule::Steps {
+        times: vec![2., 1.],
+        values: vec![3., 4.],
+    }
+    .is_valid());
+    assert!(!Schedule::Steps {
+        times: vec![1.],
+        values: vec![3., 4.],
+    }
+    .is_valid());
+}
+
+#[test]
+fn sine_and_table() {
+    let sine = Schedule::Sine {
+        mean: 1.,
+        amplitude: 2.,
+        period: 4.,
+        phase: 0.,
+    };
+    assert!((sine.value_at(1.) - 3.).abs() < 1e-12);
+    assert!((sine.value_at(3.) + 1.).abs() < 1e-12);
+    assert!(!Schedule::Sine {
+        mean: 1.,
+        amplitude: 2.,
+        period: 0.,
+        phase: 0.,
+    }
+    .is_valid());
+
+    let table = Schedule::Table {
+        times: vec![0., 1., 3.],
+        values: vec![1., 3., -1.],
+    };
+    assert_eq!(table.value_at(-1.), 1.);
+    assert_eq!(table.value_at(0.5), 2.);
+    assert_eq!(table.value_at(2.), 1.);
+    assert_eq!(table.value_at(5.), -1.);
+}
+
+#[test]
+fn extreme_times() {
+    let extremes = |s: &Schedule| {
+        let values: Vec<Float> = s.extreme_times().iter().map(|&t| s.value_at(t)).collect();
+        (
+            values.iter().cloned().fold(Float::INFINITY, Float::min),
+            values.iter().cloned().fold(Float::NEG_INFINITY, Float::max),
+        )
+    };
+
+    let table = Schedule::Table {
+        times: vec![-1., 1., 3., 4.],
+        values: vec![-5., 3., -1., 0.],
+    };
+    assert_eq!(extremes(&table), (-1., 3.));
+
+    let steps = Schedule::Steps {
+        times: vec![1., 2.],
+        values: vec![3., -4.],
+    };
+    assert_eq!(extremes(&steps), (-4., 3.));
+
+    let ramp = Schedule::Ramp {
+        start: 1.,
+        end: 3.,
+        from: 2.,
+        to: -1.,
+    };
+    assert_eq!(extremes(&ramp), (-1., 2.));
+
+    let sine = Schedule::Sine {
+        mean: 1.,
+        amplitude: 2.,
+        period: 4.,
+        phase: 2.,
+    };
+    let (min, max) = extremes(&sine);
+    assert!((min + 1.).abs() < 1e-12);
+    assert!((max - 3.).abs() < 1e-12);
+    assert!(sine.extreme_times().iter().all(|&t| t >= 0.));
+}
+
+#[test]
+fn read_from_file() {
+    let path = env::temp_dir().join("schedule_test_table.txt");
+    fs::write(&path, "0.0 1.0\n2.0 3.0\n").unwrap();
+    let file = Schedule::File {
+        path: path.to_str().unwrap().to_string(),
+    };
+
+    let table = file.load().unwrap();
+    assert_eq!(
+        table,
+        Schedule::Table {
+            times: vec![0., 2.],
+            values: vec![1., 3.],
+        }
+    );
+    assert_eq!(table.value_at(1.), 2.);
+
+    fs::write(&path, "0.0 1.0\n2.0").unwrap();
+    assert!(file.load().is_err());
+
+    fs::write(&path, "2.0 1.0\n1.0 3.0").unwrap();
+    assert!(file.load().is_err());
+
+    fs::remove_file(&path).unwrap();
+    assert!(file.load().is_err());
+}
+
+#[test]
+#[should_panic]
+fn unloaded_file() {
+    Schedule::File {
+        path: "schedule.txt".to_string(),
+    }
+    .value_at(0.);
+}
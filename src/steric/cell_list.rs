@@ -0,0 +1,136 @@
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./cell_list_test.rs"]
+mod cell_list_test;
+
+use crate::mesh::grid_width::GridWidth;
+use crate::mesh::{get_cell_index, get_flat_cell_index};
+use crate::particle::{Particle, Position};
+use crate::vector::VectorD;
+use crate::Float;
+use crate::{BoxSize, GridSize};
+use rayon::prelude::*;
+
+/// Cell list for the neighbour search of short-range interactions. The box is
+/// divided into cells, that are at least as wide as the cutoff, such that all
+/// neighbours of a particle are located in its own or one of the adjacent
+/// cells. The cells are periodic in every direction.
+#[derive(Debug, Clone)]
+pub struct CellList {
+    grid_size: GridSize,
+    grid_width: GridWidth,
+    /// index of the first particle of every cell in `particles`, with one
+    /// additional entry for the end of the last cell
+    cell_start: Vec<usize>,
+    /// indices of the particles, sorted by cell
+    particles: Vec<usize>,
+    /// preallocated cell index of every particle
+    cell_index: Vec<usize>,
+}
+
+impl CellList {
+    /// Returns an empty cell list for the box `box_size` and neighbours within
+    /// the distance `cutoff`.
+    pub fn new(box_size: BoxSize, cutoff: Float) -> CellList {
+        assert!(cutoff > 0., "Cutoff of the cell list must be positive.");
+        let cells = |l: Float| ((l / cutoff).floor() as usize).max(1);
+        let grid_size = GridSize {
+            x: cells(box_size.x),
+            y: cells(box_size.y),
+            z: cells(box_size.z),
+            phi: 1,
+            theta: 1,
+        };
+        let n = grid_size.x * grid_size.y * grid_size.z;
+
+        CellList {
+            grid_size,
+            grid_width: GridWidth::new(grid_size, box_size),
+            cell_start: vec![0; n + 1],
+            particles: Vec::new(),
+            cell_index: Vec::new(),
+        }
+    }
+
+    /// Returns the number of cells in every direction.
+    pub fn grid_size(&self) -> GridSize {
+        self.grid_size
+    }
+
+    /// Sorts the particles `particles` into the cells.
+    pub fn update(&mut self, particles: &[Particle]) {
+        let gw = &self.grid_width;
+        let gs = &self.grid_size;
+
+        self.cell_index.resize(particles.len(), 0);
+        self.cell_index
+            .par_iter_mut()
+            .zip(particles.par_iter())
+            .for_each(|(c, p)| *c = get_flat_cell_index(&p.position, gw, gs));
+
+        // counting sort by cell index
+        for c in self.cell_start.iter_mut() {
+            *c = 0;
+        }
+        for &c in &self.cell_index {
+            self.cell_start[c + 1] += 1;
+        }
+        for i in 1..self.cell_start.len() {
+            self.cell_start[i] += self.cell_start[i - 1];
+        }
+
+        let mut next = self.cell_start.clone();
+        self.particles.resize(particles.len(), 0);
+        for (i, &c) in self.cell_index.iter().enumerate() {
+            self.particles[next[c]] = i;
+            next[c] += 1;
+        }
+    }
+
+    /// Calls `f` with the index of every particle in the cell of `position`
+    /// and the adjacent cells, including the particle at `position` itself.
+    /// Every particle is visited once, even if the grid has less than three
+    /// cells in some direction.
+    pub fn for_each_neighbour<F: FnMut(usize)>(&self, position: &Position, mut f: F) {
+        let gs = &self.grid_size;
+        let idx = get_cell_index(position, &self.grid_width, gs);
+
+        // adjacent cells in one direction without duplicates
+        let adjacent = |i: usize, n: usize| ([i, (i + 1) % n, (i + n - 1) % n], n.min(3));
+
+        let (ax, nx) = adjacent(idx.0, gs.x);
+        let (ay, ny) = adjacent(idx.1, gs.y);
+        let (az, nz) = adjacent(idx.2, gs.z);
+
+        for &ix in &ax[..nx] {
+            for &iy in &ay[..ny] {
+                for &iz in &az[..nz] {
+                    let c = (ix * gs.y + iy) * gs.z + iz;
+                    for &j in &self.particles[self.cell_start[c]..self.cell_start[c + 1]] {
+                        f(j);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the distance vector `a - b` of the nearest periodic images. The
+/// box is not periodic in z direction, if `periodic_z` is false, e.g. for
+/// walls.
+pub fn nearest_image_distance(
+    a: &Position,
+    b: &Position,
+    bs: &BoxSize,
+    periodic_z: bool,
+) -> VectorD {
+    let image = |d: Float, l: Float| d - l * (d / l).round();
+    let dz = a.z - b.z;
+
+    [
+        image(a.x - b.x, bs.x),
+        image(a.y - b.y, bs.y),
+        if periodic_z { image(dz, bs.z) } else { dz },
+    ]
+    .into()
+}
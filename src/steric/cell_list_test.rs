@@ -0,0 +1,53 @@
+use super::*;
+
+const BS: BoxSize = BoxSize {
+    x: 5.,
+    y: 2.,
+    z: 1.,
+};
+
+/// Compares the neighbours found by the cell list with a brute force search.
+#[test]
+fn neighbours_within_cutoff() {
+    let cutoff = 0.7;
+    let particles = Particle::create_isotropic(500, &BS, 3);
+    let mut cl = CellList::new(BS, cutoff);
+    cl.update(&particles);
+
+    let gs = cl.grid_size();
+    assert_eq!((gs.x, gs.y, gs.z), (7, 2, 1));
+
+    let image = |d: Float, l: Float| d - l * (d / l).round();
+    for (i, p) in particles.iter().enumerate() {
+        let mut found = Vec::new();
+        cl.for_each_neighbour(&p.position, |j| found.push(j));
+
+        // every particle is visited at most once
+        let mut unique = found.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), found.len());
+        assert!(found.contains(&i));
+
+        for (j, q) in particles.iter().enumerate() {
+            let dx = image(p.position.x - q.position.x, BS.x);
+            let dy = image(p.position.y - q.position.y, BS.y);
+            let dz = image(p.position.z - q.position.z, BS.z);
+            if (dx * dx + dy * dy + dz * dz).sqrt() < cutoff {
+                assert!(found.contains(&j), "{} misses neighbour {}", i, j);
+            }
+        }
+    }
+}
+
+#[test]
+fn single_cell() {
+    let particles = Particle::create_isotropic(20, &BS, 1);
+    let mut cl = CellList::new(BS, 10.);
+    cl.update(&particles);
+
+    let mut found = Vec::new();
+    cl.for_each_neighbour(&particles[0].position, |j| found.push(j));
+    found.sort();
+    assert_eq!(found, (0..20).collect::<Vec<_>>());
+}
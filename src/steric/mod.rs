@@ -0,0 +1,158 @@
+//! Short-range steric interactions between pairs of particles, which prevent
+//! local overlaps, that the mean-field volume exclusion cannot resolve.
+pub mod cell_list;
+
+// Move unit test into own file
+#[cfg(test)]
+#[path = "./steric_test.rs"]
+mod steric_test;
+
+use self::cell_list::{nearest_image_distance, CellList};
+use crate::particle::Particle;
+use crate::vector::VectorD;
+use crate::BoxSize;
+use crate::Float;
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+/// Repulsive pair potential of two particles at distance `r`. The strength
+/// includes the translational mobility of the particles, i.e. the force is
+/// given as a velocity.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum StericPotential {
+    /// Weeks-Chandler-Andersen potential, i.e. the repulsive part of the
+    /// Lennard-Jones potential
+    /// `4 strength ((d / r)^12 - (d / r)^6) + strength` for `r < 2^(1/6) d`
+    WCA { strength: Float },
+    /// Elastic spheres with `strength (1 - r / d)^(5/2)` for `r < d`
+    Hertz { strength: Float },
+}
+
+/// Parameters of the steric interaction of particles with diameter
+/// `diameter`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StericParameters {
+    pub diameter: Float,
+    /// Strength of the nematic alignment of rods within the cutoff with the
+    /// potential `-alignment (n_i . n_j)^2`. It includes the rotational
+    /// mobility.
+    #[serde(default)]
+    pub alignment: Float,
+    // tables need to come after values for the TOML serialization
+    pub potential: StericPotential,
+}
+
+impl StericParameters {
+    /// Returns the distance, beyond which particles do not interact.
+    pub fn cutoff(&self) -> Float {
+        match self.potential {
+            StericPotential::WCA { .. } => (2. as Float).powf(1. / 6.) * self.diameter,
+            StericPotential::Hertz { .. } => self.diameter,
+        }
+    }
+
+    /// Returns true, if the parameters describe a repulsive interaction of
+    /// particles with finite size.
+    pub fn is_valid(&self) -> bool {
+        let strength = match self.potential {
+            StericPotential::WCA { strength } | StericPotential::Hertz { strength } => strength,
+        };
+        self.diameter > 0. && strength >= 0.
+    }
+
+    /// Returns the repulsive force `-dU/dr` of two particles at distance `r`.
+    pub fn force(&self, r: Float) -> Float {
+        if r >= self.cutoff() {
+            return 0.;
+        }
+
+        let d = self.diameter;
+        match self.potential {
+            StericPotential::WCA { strength } => {
+                let s6 = (d / r).powi(6);
+                24. * strength / r * (2. * s6 * s6 - s6)
+            }
+            StericPotential::Hertz { strength } => 2.5 * strength / d * (1. - r / d).powf(1.5),
+        }
+    }
+}
+
+/// Force and torque on a particle due to its neighbours. Both include the
+/// mobility of the particle, i.e. they are its velocity and angular velocity.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StericForce {
+    pub force: VectorD,
+    pub torque: VectorD,
+}
+
+/// Calculates the steric forces and torques on all particles.
+pub struct StericInteraction {
+    parameters: StericParameters,
+    box_size: BoxSize,
+    /// Particles interact with the periodic images in z direction, which is
+    /// not the case for walls.
+    periodic_z: bool,
+    cell_list: CellList,
+    forces: Vec<StericForce>,
+}
+
+impl StericInteraction {
+    pub fn new(parameters: StericParameters, box_size: BoxSize, periodic_z: bool) -> Self {
+        StericInteraction {
+            parameters,
+            box_size,
+            periodic_z,
+            cell_list: CellList::new(box_size, parameters.cutoff()),
+            forces: Vec::new(),
+        }
+    }
+
+    /// Calculates the forces and torques on all `particles`. Every pair is
+    /// evaluated for both particles, which avoids synchronisation between
+    /// threads.
+    pub fn update(&mut self, particles: &[Particle]) {
+        self.cell_list.update(particles);
+        self.forces.resize(particles.len(), StericForce::default());
+
+        let param = &self.parameters;
+        let cutoff = param.cutoff();
+        let (bs, periodic_z) = (&self.box_size, self.periodic_z);
+        let cell_list = &self.cell_list;
+
+        self.forces
+            .par_iter_mut()
+            .zip(particles.par_iter())
+            .enumerate()
+            .for_each(|(i, (f, p))| {
+                *f = StericForce::default();
+                let n = p.orientation.to_vector();
+
+                cell_list.for_each_neighbour(&p.position, |j| {
+                    if i == j {
+                        return;
+                    }
+                    let q = &particles[j];
+                    let d = nearest_image_distance(&p.position, &q.position, bs, periodic_z);
+                    let r = d.dot(&d).sqrt();
+                    if r >= cutoff || r == 0. {
+                        return;
+                    }
+
+                    f.force += d * (param.force(r) / r);
+
+                    if param.alignment != 0. {
+                        let m = q.orientation.to_vector();
+                        f.torque += n.cross(&m) * (2. * param.alignment * n.dot(&m));
+                    }
+                });
+            });
+    }
+
+    /// Returns the force and torque on every particle in the order of the
+    /// particles given to `update`.
+    pub fn get_forces(&self) -> &[StericForce] {
+        &self.forces
+    }
+}
@@ -0,0 +1,110 @@
+use super::*;
+
+const BS: BoxSize = BoxSize {
+    x: 10.,
+    y: 10.,
+    z: 10.,
+};
+
+fn parameters(potential: StericPotential) -> StericParameters {
+    StericParameters {
+        potential,
+        diameter: 1.,
+        alignment: 0.,
+    }
+}
+
+#[test]
+fn pair_potentials() {
+    let wca = parameters(StericPotential::WCA { strength: 2. });
+    assert_eq!(wca.force(1.), 48.);
+    // the force vanishes continuously at the cutoff
+    assert!(wca.force(wca.cutoff() * (1. - 1e-10)).abs() < 1e-6);
+    assert_eq!(wca.force(1.2), 0.);
+
+    let hertz = parameters(StericPotential::Hertz { strength: 2. });
+    assert_eq!(hertz.cutoff(), 1.);
+    assert!((hertz.force(0.75) - 5. * 0.125).abs() < 1e-12);
+    assert_eq!(hertz.force(1.), 0.);
+
+    assert!(wca.is_valid());
+    assert!(!StericParameters {
+        diameter: 0.,
+        ..wca
+    }
+    .is_valid());
+}
+
+/// Two particles across the periodic boundary push each other apart.
+#[test]
+fn repulsion_of_periodic_images() {
+    let p = parameters(StericPotential::Hertz { strength: 1. });
+    let particles = vec![
+        Particle::new(0.1, 5., 5., 0., 0., &BS),
+        Particle::new(9.6, 5., 5., 0., 0., &BS),
+        Particle::new(5., 5., 5., 0., 0., &BS),
+    ];
+
+    let mut s = StericInteraction::new(p, BS, true);
+    s.update(&particles);
+    let f = s.get_forces();
+
+    let expect = p.force(0.5);
+    assert!((f[0].force.v[0] - expect).abs() < 1e-12, "{:?}", f[0]);
+    assert!((f[1].force.v[0] + expect).abs() < 1e-12, "{:?}", f[1]);
+    assert_eq!(f[2].force.v, [0.; 3]);
+    assert_eq!(f[0].torque.v, [0.; 3]);
+}
+
+/// Without periodic boundaries in z direction, particles do not interact
+/// through the walls.
+#[test]
+fn no_interaction_through_walls() {
+    let p = parameters(StericPotential::WCA { strength: 1. });
+    let particles = vec![
+        Particle::new(5., 5., 0.1, 0., 0., &BS),
+        Particle::new(5., 5., 9.8, 0., 0., &BS),
+    ];
+
+    let mut s = StericInteraction::new(p, BS, false);
+    s.update(&particles);
+    assert_eq!(s.get_forces()[0].force.v, [0.; 3]);
+
+    let mut s = StericInteraction::new(p, BS, true);
+    s.update(&particles);
+    assert!(s.get_forces()[0].force.v[2] > 0.);
+}
+
+/// Neighbouring rods rotate towards each other, while the total force
+/// vanishes.
+#[test]
+fn alignment_and_momentum_balance() {
+    let p = StericParameters {
+        alignment: 0.5,
+        ..parameters(StericPotential::Hertz { strength: 1. })
+    };
+    let particles = Particle::create_isotropic(2000, &BS, 7);
+
+    let mut s = StericInteraction::new(p, BS, true);
+    s.update(&particles);
+
+    let total = s
+        .get_forces()
+        .iter()
+        .fold(VectorD::zero(), |acc, f| acc + f.force);
+    assert!(total.dot(&total).sqrt() < 1e-10);
+
+    let particles = vec![
+        Particle::new(5., 5., 5., 0., 0.5, &BS),
+        Particle::new(5.5, 5., 5., 0., 0.2, &BS),
+    ];
+    s.update(&particles);
+    let f = s.get_forces();
+
+    // both rotate around the y axis towards each other
+    let n = particles[0].orientation.to_vector();
+    let dn = f[0].torque.cross(&n);
+    assert!(dn.dot(&particles[1].orientation.to_vector()) > 0.);
+    assert!((f[0].torque.v[1] + f[1].torque.v[1]).abs() < 1e-12);
+    assert!(f[0].torque.v[1] < 0.);
+}
@@ -17,6 +17,28 @@ pub type NumVectorD<N> = NumVector<Default, N>;
 pub type Vector<T> = NumVector<T, Float>;
 pub type VectorD = Vector<Default>;
 
+/// Fixed size 3x3 matrix in row-major order, that lives on the stack.
+pub type Matrix3 = [[Float; 3]; 3];
+
+/// Returns the matrix-vector product `m . v`.
+#[inline(always)]
+pub fn mat_vec<T>(m: &Matrix3, v: &Vector<T>) -> Vector<T> {
+    let row = |r: &[Float; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+    [row(&m[0]), row(&m[1]), row(&m[2])].into()
+}
+
+/// Returns the element-wise sum `a + b`.
+#[inline(always)]
+pub fn mat_add(a: &Matrix3, b: &Matrix3) -> Matrix3 {
+    let mut m = *a;
+    for (mi, bi) in m.iter_mut().zip(b) {
+        for (mij, bij) in mi.iter_mut().zip(bi) {
+            *mij += bij;
+        }
+    }
+    m
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct NumVector<T, N>
 where
@@ -52,6 +74,17 @@ where
         self.iter().zip(rhs.iter()).map(|(a, b)| (*a) * (*b)).sum()
     }
 
+    /// Returns the cross product `self x rhs`.
+    pub fn cross<D>(&self, rhs: &NumVector<D, N>) -> NumVector<T, N> {
+        let (a, b) = (self.v, rhs.v);
+        [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ]
+        .into()
+    }
+
     pub fn zero() -> NumVector<T, N> {
         NumVector::<T, N> {
             v: [N::zero(), N::zero(), N::zero()],
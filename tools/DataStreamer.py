@@ -159,6 +159,24 @@ def data_to_magneticfield(data):
     return mf
 
 
+def data_to_bulk_stress(data):
+    """ Return box averaged stresses as dictionary of 3x3 arrays with keys
+    'active', 'magnetic' and 'rods'. """
+    return {k: np.array(v) for k, v in data['stress'].items()}
+
+
+def bulk_stress_to_rheology(stress, shear_rate):
+    """Takes box averaged stresses and returns the effective viscosity
+    (relative to the solvent viscosity) and the first and second normal stress
+    difference for a simple shear flow in x direction with gradient in y
+    direction.
+    """
+    s = stress['active'] + stress['magnetic'] + stress['rods']
+    viscosity = 1. + 0.5 * (s[0, 1] + s[1, 0]) / shear_rate
+
+    return viscosity, s[0, 0] - s[1, 1], s[1, 1] - s[2, 2]
+
+
 def data_to_dist(data):
     """Takes data dictonary and returns numpy array of sampled
     distribution in the correct shape, with (x, y, angle).
@@ -167,6 +185,72 @@ def data_to_dist(data):
         data['distribution']['dist']['dim'])
 
 
+def data_to_species_dists(data):
+    """Takes data dictonary and returns a list with the sampled distribution
+    of every particle species in the same shape as `data_to_dist`. Returns
+    `None` for a single species.
+    """
+    dists = data.get('species_distributions')
+    if dists is None:
+        return None
+    return [np.array(d['dist']['data']).reshape(d['dist']['dim'])
+            for d in dists]
+
+
+def data_to_tracers(data):
+    """Takes data dictonary and returns the positions of the passive tracers
+    as array with (tracer, component).
+    """
+    return np.array([[t['x'], t['y'], t['z']] for t in data['tracers']])
+
+
+def data_to_chemical(data):
+    """ Return concentration of the chemical with [x, y, z]. """
+    c = data['chemical']
+    c = np.array(c['data']).reshape(c['dim'])
+    return c
+
+
+def data_to_sh_coefficients(data):
+    """Takes data dictonary of a simulation with the `SphericalHarmonics`
+    orientation representation and returns the coefficients of the real
+    spherical harmonics with (x, y, z, coefficient). The coefficient of degree
+    `l` and order `m` is found at `sh_index(l, m)`.
+    """
+    dist = data_to_dist(data)
+    return dist[:, :, :, :, 0]
+
+
+def sh_index(l, m):
+    """Returns the position of the coefficient of degree `l` and order `m` in
+    the last axis of `data_to_sh_coefficients`."""
+    return l * l + l + m
+
+
+def sh_degree(coefficients):
+    """Returns the maximal degree of the spherical harmonic coefficients."""
+    return int(np.sqrt(coefficients.shape[-1])) - 1
+
+
+def sh_coefficients_to_concentration3d(coefficients):
+    """Takes spherical harmonic coefficients and returns the concentration
+    field, which is given by the coefficient of degree zero.
+    """
+    return coefficients[..., sh_index(0, 0)] * np.sqrt(4 * np.pi)
+
+
+def sh_coefficients_to_polarisation(coefficients):
+    """Takes spherical harmonic coefficients and returns the polarisation
+    field with [x, y, z, component], which is given by the coefficients of
+    degree one.
+    """
+    c = coefficients
+    return np.sqrt(4 * np.pi / 3) * np.stack(
+        [c[..., sh_index(1, 1)], c[..., sh_index(1, -1)],
+         c[..., sh_index(1, 0)]],
+        axis=-1)
+
+
 def dist_to_concentration3d(dist, gw):
     """Takes an distribution array and returns a concentration
     field by naive integraton of orientation.